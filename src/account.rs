@@ -0,0 +1,322 @@
+//! The accounts codes are generated for.
+
+use serde::{Deserialize, Serialize};
+
+use crate::otp::{self, Algorithm, Totp};
+
+/// How many days back the copies of each day are kept
+pub const USAGE_DAYS: u64 = 90;
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// An account and its secret
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Account {
+    pub issuer: String,
+    pub name: String,
+    /// The secret key in Base32, never shown unless explicitly asked for
+    pub secret: String,
+    /// Unix time the TOTP counter starts from
+    #[serde(default)]
+    pub t0: u64,
+    /// Hash function of the HMAC
+    #[serde(default)]
+    pub algorithm: Algorithm,
+    /// Number of digits in a code
+    #[serde(default = "default_digits")]
+    pub digits: u32,
+    /// Length of a time step in seconds
+    #[serde(default = "default_period")]
+    pub period: u64,
+    /// Free text, e.g. the recovery email or where the backup codes are kept
+    #[serde(default)]
+    pub note: String,
+    /// One-time recovery codes handed out by the service at enrollment
+    #[serde(default)]
+    pub recovery_codes: Vec<RecoveryCode>,
+    /// Unix time a code of this account was last copied
+    #[serde(default)]
+    pub last_used: Option<u64>,
+    /// How many times a code of this account was copied
+    #[serde(default)]
+    pub copies: u64,
+    /// Codes copied on each of the last [`USAGE_DAYS`] days a code was copied on, as
+    /// days since the Unix epoch and copies that day
+    #[serde(default)]
+    pub usage: Vec<(u64, u64)>,
+    /// Kept, but out of the list of codes
+    #[serde(default)]
+    pub archived: bool,
+    /// Listed first, the first nine copied with the keys 1 to 9
+    #[serde(default)]
+    pub pinned: bool,
+    /// A short emoji or symbol shown with the account, empty for the one suggested by the issuer
+    #[serde(default)]
+    pub icon: String,
+    /// A color name or #rrggbb the account is shown in, empty for the usual one
+    #[serde(default)]
+    pub color: String,
+    /// The domain of the web site the account is for, e.g. github.com, on whose pages the
+    /// browser extension fills in its codes; empty for the issuer's, if it is a domain or
+    /// a well-known service
+    #[serde(default)]
+    pub site: String,
+    /// Enrolled with the first versions of this app, whose codes were not the RFC 6238
+    /// ones: the secret is used as the characters it is written with and the codes are
+    /// made as they made them, so that services set up back then keep accepting them
+    #[serde(default)]
+    pub legacy: bool,
+    /// The secret before the account was enrolled again, whose codes are still shown for
+    /// a while in case the service has not switched to the new one yet
+    #[serde(default)]
+    pub previous: Option<PreviousSecret>,
+}
+
+/// The secret an account had before it was enrolled again
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PreviousSecret {
+    pub secret: String,
+    #[serde(default)]
+    pub legacy: bool,
+    /// Unix time it is forgotten at
+    pub until: u64,
+}
+
+/// Icons of well-known services, by a word found in their issuer
+const ICONS: &[(&str, &str)] = &[
+    ("github", "🐙"),
+    ("gitlab", "🦊"),
+    ("google", "🔎"),
+    ("aws", "📦"),
+    ("amazon", "📦"),
+    ("microsoft", "💻"),
+    ("apple", "🍎"),
+    ("dropbox", "📥"),
+    ("discord", "🎮"),
+    ("steam", "🎮"),
+    ("slack", "💬"),
+    ("twitter", "🐦"),
+    ("facebook", "📘"),
+    ("reddit", "👽"),
+    ("paypal", "💳"),
+    ("proton", "📧"),
+    ("bank", "🏦"),
+];
+
+/// Domains of well-known services, by their issuer in lowercase without spaces
+const SITES: &[(&str, &str)] = &[
+    ("github", "github.com"),
+    ("gitlab", "gitlab.com"),
+    ("google", "google.com"),
+    ("aws", "aws.amazon.com"),
+    ("amazon", "amazon.com"),
+    ("amazonwebservices", "aws.amazon.com"),
+    ("microsoft", "microsoft.com"),
+    ("apple", "apple.com"),
+    ("dropbox", "dropbox.com"),
+    ("discord", "discord.com"),
+    ("steam", "steampowered.com"),
+    ("slack", "slack.com"),
+    ("twitter", "twitter.com"),
+    ("x", "x.com"),
+    ("facebook", "facebook.com"),
+    ("reddit", "reddit.com"),
+    ("paypal", "paypal.com"),
+    ("proton", "proton.me"),
+    ("protonmail", "proton.me"),
+];
+
+/// The domain of the well-known service named `issuer`, if it is one
+pub fn known_site(issuer: &str) -> Option<&'static str> {
+    let issuer: String = issuer
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    SITES
+        .iter()
+        .find(|(name, _)| *name == issuer)
+        .map(|(_, site)| *site)
+}
+
+/// The icon of the well-known service `issuer` belongs to, if it is one
+pub fn suggest_icon(issuer: &str) -> Option<&'static str> {
+    let issuer = issuer.to_lowercase();
+    ICONS
+        .iter()
+        .find(|(word, _)| issuer.contains(word))
+        .map(|(_, icon)| *icon)
+}
+
+fn default_digits() -> u32 {
+    otp::DIGITS
+}
+
+fn default_period() -> u64 {
+    otp::PERIOD
+}
+
+impl Account {
+    /// An account with the usual SHA1, 6 digits and 30 seconds
+    pub fn new(issuer: String, name: String, secret: String) -> Account {
+        Account {
+            issuer,
+            name,
+            secret,
+            t0: 0,
+            algorithm: Algorithm::default(),
+            digits: otp::DIGITS,
+            period: otp::PERIOD,
+            note: String::new(),
+            recovery_codes: vec![],
+            last_used: None,
+            copies: 0,
+            usage: vec![],
+            archived: false,
+            pinned: false,
+            icon: String::new(),
+            color: String::new(),
+            site: String::new(),
+            legacy: false,
+            previous: None,
+        }
+    }
+
+    /// The generator of this account's codes, a secret that is not Base32 is
+    /// used as it is
+    pub fn totp(&self) -> Totp {
+        self.totp_of(&self.secret, self.legacy)
+    }
+
+    /// The generator of the codes of the previous secret, while it is kept at `time`
+    pub fn previous_totp(&self, time: u64) -> Option<Totp> {
+        let previous = self
+            .previous
+            .as_ref()
+            .filter(|previous| time < previous.until)?;
+        Some(self.totp_of(&previous.secret, previous.legacy))
+    }
+
+    /// Take the new secret of a re-enrollment, keeping everything else of the account; the
+    /// old secret keeps giving codes until `keep_until` if it is set
+    pub fn reenroll(&mut self, secret: String, keep_until: Option<u64>) {
+        let old = std::mem::replace(&mut self.secret, secret);
+        self.previous = keep_until.map(|until| PreviousSecret {
+            secret: old,
+            legacy: self.legacy,
+            until,
+        });
+        // a new secret is a Base32 one, whatever the old one was
+        self.legacy = false;
+    }
+
+    /// Forget the previous secret once it is no longer kept at `time`
+    pub fn forget_previous(&mut self, time: u64) {
+        if self
+            .previous
+            .as_ref()
+            .is_some_and(|previous| time >= previous.until)
+        {
+            self.previous = None;
+        }
+    }
+
+    /// Note that a code of this account was copied at `time`
+    pub fn record_use(&mut self, time: u64) {
+        self.last_used = Some(time);
+        self.copies += 1;
+        let day = time / DAY;
+        match self.usage.iter_mut().find(|(used, _)| *used == day) {
+            Some((_, copies)) => *copies += 1,
+            None => self.usage.push((day, 1)),
+        }
+        self.usage.retain(|(used, _)| used + USAGE_DAYS > day);
+    }
+
+    /// The copies of a code of this account on each of the `days` days up to that of
+    /// `time`, oldest first
+    pub fn copies_per_day(&self, time: u64, days: u64) -> Vec<u64> {
+        let today = time / DAY;
+        (0..days)
+            .rev()
+            .map(|ago| {
+                let day = today.saturating_sub(ago);
+                self.usage
+                    .iter()
+                    .filter(|(used, _)| *used == day)
+                    .map(|(_, copies)| copies)
+                    .sum()
+            })
+            .collect()
+    }
+
+    // the generator of the codes of `secret` with the settings of this account
+    fn totp_of(&self, secret: &str, legacy: bool) -> Totp {
+        if legacy {
+            let secret = secret.as_bytes().to_vec();
+            return Totp::legacy(secret, self.t0, self.period, self.digits);
+        }
+        let secret = otp::decode_base32(secret).unwrap_or_else(|| secret.as_bytes().to_vec());
+        Totp::new(secret, self.t0, self.period, self.algorithm, self.digits)
+    }
+
+    /// The icon shown with the account, its own or the one of its issuer, empty if neither
+    pub fn icon(&self) -> &str {
+        if self.icon.is_empty() {
+            suggest_icon(&self.issuer).unwrap_or_default()
+        } else {
+            &self.icon
+        }
+    }
+
+    /// The time step `time` falls in, the code only changes with it
+    pub fn step_at(&self, time: u64) -> u64 {
+        time.saturating_sub(self.t0) / self.period.max(1)
+    }
+
+    /// The code at `time` (seconds since the unix epoch), as text
+    pub fn code_at(&self, time: u64) -> String {
+        let totp = self.totp();
+        totp.format(totp.generate_at(time))
+    }
+}
+
+/// A one-time recovery code of an account
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RecoveryCode {
+    pub code: String,
+    pub used: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account() -> Account {
+        Account::new(
+            "Example".to_string(),
+            "me@example.com".to_string(),
+            "JBSWY3DPEHPK3PXP".to_string(),
+        )
+    }
+
+    #[test]
+    fn decodes_the_secret_unless_legacy() {
+        assert_eq!(account().code_at(1_700_000_000), "324550");
+        // the first versions used the characters of the secret as the key
+        let legacy = Account {
+            legacy: true,
+            ..account()
+        };
+        assert_eq!(legacy.code_at(1_700_000_000), "887910");
+    }
+
+    #[test]
+    fn uses_a_secret_that_is_not_base32_as_it_is() {
+        let raw = Account {
+            secret: "12345678901234567890".to_string(),
+            ..account()
+        };
+        assert_eq!(raw.code_at(59), "287082");
+    }
+}
@@ -0,0 +1,384 @@
+//! Serves codes from the vault over a Unix domain socket, so scripts and other
+//! frontends do not have to open the vault themselves.
+//!
+//! Every connection sends one request line and reads the answer until the agent
+//! closes the connection:
+//!
+//! - `list` answers the account names, one per line
+//! - `get-code ACCOUNT` answers the current code of ACCOUNT
+//! - `site-code SITE` answers the current code of the account of the web site SITE,
+//!   e.g. github.com, once it is approved on the terminal of the agent, or in a desktop
+//!   notification when it has none, such as under systemd; unanswered after 30 seconds it
+//!   is refused. This is what the browser extension asks through the
+//!   [native messaging host](crate::native)
+//! - `lock` answers `locked` and stops the agent, forgetting the accounts
+//!
+//! Anything that cannot be answered gets a single line starting with `error: `.
+//!
+//! The agent can also copy codes on a global hotkey, see [`hotkey`](crate::hotkey),
+//! and be started by systemd on the first request, see [`systemd`](crate::systemd).
+
+use crate::hotkey;
+use crate::notify;
+use crate::paths;
+use crate::permissions;
+use crate::systemd;
+use cli_totp::{account, Account, Vault};
+use std::fs;
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// how long a client has to send its request, as the others wait for it meanwhile
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+// and how long a request of the browser waits to be approved before it is refused
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where the agent listens unless told otherwise
+pub fn default_socket() -> PathBuf {
+    paths::runtime_dir().join("cli-totp-agent.sock")
+}
+
+/// Answer requests on `socket`, and `hotkey` if given, until killed or locked, with codes
+/// for the clock corrected by `time_offset`
+pub fn run(
+    vault: Vault,
+    socket: &Path,
+    time_offset: i64,
+    hotkey: Option<hotkey::Settings>,
+) -> io::Result<()> {
+    let inherited = systemd::listener()?;
+    let activated = inherited.is_some();
+    if !activated && socket.exists() {
+        if UnixStream::connect(socket).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("an agent is already listening on {}", socket.display()),
+            ));
+        }
+        // nobody answers, left behind by an agent that did not exit cleanly
+        fs::remove_file(socket)?;
+    }
+    // systemd made the socket for us, as the socket unit says
+    let listener = match inherited {
+        Some(listener) => listener,
+        None => bind(socket)?,
+    };
+    let vault = Arc::new(vault);
+    if let Some(settings) = hotkey {
+        hotkey::spawn(Arc::clone(&vault), settings, time_offset)
+            .map_err(|err| io::Error::other(err.to_string()))?;
+    }
+    let socket = listener
+        .local_addr()
+        .ok()
+        .and_then(|addr| addr.as_pathname().map(Path::to_path_buf))
+        .unwrap_or_else(|| socket.to_path_buf());
+    eprintln!("agent listening on {}", socket.display());
+
+    for stream in listener.incoming() {
+        match stream.and_then(|stream| serve(&vault, stream, time_offset)) {
+            Ok(true) => {
+                eprintln!("agent locked");
+                break;
+            }
+            Ok(false) => {}
+            Err(err) => eprintln!("agent: {}", err),
+        }
+    }
+    drop(vault);
+    if activated {
+        // systemd listens on it again for the next request
+        return Ok(());
+    }
+    fs::remove_file(socket)
+}
+
+// listen on `socket` in a directory of the user's own, made if needed, readable by the
+// user only from the start rather than once it is there
+fn bind(socket: &Path) -> io::Result<UnixListener> {
+    let dir = socket
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    permissions::create_dir(dir)?;
+    let metadata = fs::metadata(dir)?;
+    // SAFETY: getuid cannot fail and touches no memory
+    if metadata.uid() != unsafe { libc::getuid() } || metadata.permissions().mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "others than you can use {}, the socket of the agent needs a directory of your own",
+                dir.display()
+            ),
+        ));
+    }
+    // SAFETY: umask cannot fail, the one before is put back right after
+    let umask = unsafe { libc::umask(0o077) };
+    let listener = UnixListener::bind(socket);
+    unsafe { libc::umask(umask) };
+    listener
+}
+
+// the user on the other end of `stream`
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let mut credentials = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: the option is written into `credentials`, of the size given
+    let failed = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&mut credentials as *mut libc::ucred).cast(),
+            &mut len,
+        )
+    };
+    if failed != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(credentials.uid)
+}
+
+// the user on the other end of `stream`
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let (mut uid, mut gid) = (0, 0);
+    // SAFETY: both are written to, nothing else
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(uid)
+}
+
+/// Send `request` to the agent on `socket` and return its answer
+pub fn request(socket: &Path, request: &str) -> io::Result<String> {
+    let mut stream = UnixStream::connect(socket).map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("no agent on {}: {}", socket.display(), err),
+        )
+    })?;
+    writeln!(stream, "{}", request)?;
+    let mut answer = String::new();
+    io::Read::read_to_string(&mut stream, &mut answer)?;
+    Ok(answer)
+}
+
+// read one request from `stream` and write back the answer, true if it locked the agent
+fn serve(vault: &Vault, stream: UnixStream, time_offset: i64) -> io::Result<bool> {
+    // only the user the agent runs as gets codes, whatever the socket's permissions
+    let uid = peer_uid(&stream)?;
+    // SAFETY: getuid cannot fail and touches no memory
+    if uid != unsafe { libc::getuid() } {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("refused a request of user {}", uid),
+        ));
+    }
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut request = String::new();
+    let read = BufReader::new(&stream)
+        .read_line(&mut request)
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => io::Error::new(
+                err.kind(),
+                format!("no request within {}s", REQUEST_TIMEOUT.as_secs()),
+            ),
+            _ => err,
+        })?;
+    if read == 0 {
+        // hung up without asking, e.g. another agent checking whether we are alive
+        return Ok(false);
+    }
+    if request.trim() == "lock" {
+        (&stream).write_all(b"locked\n")?;
+        return Ok(true);
+    }
+    let answer = answer(vault, request.trim(), time_offset);
+    (&stream).write_all(answer.as_bytes())?;
+    Ok(false)
+}
+
+fn answer(vault: &Vault, request: &str, time_offset: i64) -> String {
+    // taken when answering, as approving a request can take a while
+    let now = || {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_secs()
+            .saturating_add_signed(time_offset)
+    };
+    let (command, argument) = request.split_once(' ').unwrap_or((request, ""));
+    match command {
+        "list" => vault
+            .accounts
+            .iter()
+            .map(|account| format!("{}\n", account.name))
+            .collect(),
+        "get-code" => match vault.accounts.iter().find(|a| a.name == argument.trim()) {
+            Some(account) => format!("{}\n", account.code_at(now())),
+            None => format!("error: no account named {:?}\n", argument.trim()),
+        },
+        "site-code" => {
+            let site = argument.trim().to_lowercase();
+            let accounts: Vec<&Account> = vault
+                .accounts
+                .iter()
+                .filter(|account| !account.archived && for_site(account, &site))
+                .collect();
+            if accounts.is_empty() {
+                return format!("error: no account for {}\n", site);
+            }
+            match approve(&site, &accounts) {
+                Ok(Some(account)) => format!("{}\n", account.code_at(now())),
+                Ok(None) => "error: refused\n".to_string(),
+                Err(err) => format!("error: {}\n", err),
+            }
+        }
+        _ => format!("error: unknown request {:?}\n", command),
+    }
+}
+
+// whether `account` is for `site`: the site is the domain of the account, or under it.
+// That is the site of the account, or else its issuer if it is a domain, or else the
+// domain of the well-known service it names, e.g. github.com for GitHub and api.github.com
+// but not github.io nor github.attacker.com; any other account is for no site
+fn for_site(account: &Account, site: &str) -> bool {
+    let issuer = account.issuer.trim().to_lowercase();
+    let domain = if !account.site.trim().is_empty() {
+        account.site.trim().to_lowercase()
+    } else if issuer.contains('.') {
+        issuer
+    } else {
+        match account::known_site(&issuer) {
+            Some(domain) => domain.to_string(),
+            None => return false,
+        }
+    };
+    // a domain of its own, and not a bare top-level one such as .com
+    let domain = domain.trim_matches('.');
+    domain.contains('.') && (site == domain || site.ends_with(&format!(".{}", domain)))
+}
+
+// ask on the terminal of the agent, or in a notification without one, whether the browser
+// gets the code of `site`, and of which of `accounts`; None if refused or not answered in time
+fn approve<'a>(site: &str, accounts: &[&'a Account]) -> io::Result<Option<&'a Account>> {
+    if !io::stdin().is_terminal() {
+        let choices: Vec<String> = accounts
+            .iter()
+            .map(|account| format!("{} {}", account.issuer, account.name))
+            .collect();
+        let question = format!("The browser asks for the code of {}", site);
+        let chosen = notify::choose(&question, &choices, APPROVAL_TIMEOUT).map_err(|err| {
+            io::Error::other(format!(
+                "no terminal to approve the request on, nor notifications: {}",
+                err
+            ))
+        })?;
+        eprintln!(
+            "{} the code of {}",
+            if chosen.is_some() { "Gave" } else { "Refused" },
+            site
+        );
+        return Ok(chosen.map(|i| accounts[i]));
+    }
+    eprintln!("The browser asks for the code of {}:", site);
+    for (i, account) in accounts.iter().enumerate() {
+        eprintln!("  {}. {} {}", i + 1, account.issuer, account.name);
+    }
+    if accounts.len() == 1 {
+        eprint!("Give it? [y/N] ");
+    } else {
+        eprint!("Give which? [1-{}, anything else refuses] ", accounts.len());
+    }
+    io::stderr().flush()?;
+    let mut stdin = libc::pollfd {
+        fd: io::stdin().as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let timeout = i32::try_from(APPROVAL_TIMEOUT.as_millis()).unwrap_or(i32::MAX);
+    // SAFETY: a single pollfd is given, as the count says
+    match unsafe { libc::poll(&mut stdin, 1, timeout) } {
+        -1 => return Err(io::Error::last_os_error()),
+        0 => {
+            eprintln!(
+                "\nRefused, no answer within {}s",
+                APPROVAL_TIMEOUT.as_secs()
+            );
+            return Ok(None);
+        }
+        _ => {}
+    }
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim();
+    let approved = if accounts.len() == 1 {
+        matches!(answer, "y" | "yes").then_some(accounts[0])
+    } else {
+        answer
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| accounts.get(n.wrapping_sub(1)).copied())
+    };
+    eprintln!(
+        "{}",
+        if approved.is_some() {
+            "Given"
+        } else {
+            "Refused"
+        }
+    );
+    Ok(approved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(issuer: &str, site: &str) -> Account {
+        Account {
+            site: site.to_string(),
+            ..Account::new(issuer.to_string(), "me".to_string(), String::new())
+        }
+    }
+
+    #[test]
+    fn matches_the_domain_of_a_well_known_issuer() {
+        let github = account("GitHub", "");
+        assert!(for_site(&github, "github.com"));
+        assert!(for_site(&github, "api.github.com"));
+        for site in [
+            "github.io",
+            "evil.github.io",
+            "github.ru",
+            "github.attacker.com",
+        ] {
+            assert!(!for_site(&github, site), "{}", site);
+        }
+        assert!(!for_site(&account("Some Shop", ""), "someshop.com"));
+    }
+
+    #[test]
+    fn matches_the_domain_of_an_account() {
+        let shop = account("Some Shop", "shop.example.co.uk");
+        assert!(for_site(&shop, "shop.example.co.uk"));
+        assert!(for_site(&shop, "login.shop.example.co.uk"));
+        assert!(!for_site(&shop, "example.co.uk"));
+        let issuer = account("example.org", "");
+        assert!(for_site(&issuer, "www.example.org"));
+        assert!(!for_site(&issuer, "example.org.attacker.com"));
+        assert!(!for_site(&account(".com", ""), "example.com"));
+    }
+}
@@ -0,0 +1,145 @@
+//! The record of sensitive actions kept inside the encrypted vault, for shared
+//! machines and peace of mind.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How many entries a vault keeps, the oldest go first
+pub const MAX_ENTRIES: usize = 1000;
+
+/// What was done
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Added,
+    Edited,
+    Archived,
+    Restored,
+    Deleted,
+    Imported,
+    Exported,
+    /// The secret of an account was shown
+    Revealed,
+    /// The vault was encrypted with a new passphrase
+    Rekeyed,
+    /// An account got the new secret of a re-enrollment
+    Reenrolled,
+    /// A decoy vault was made next to the vault; no longer recorded, a log that says so
+    /// tells there is a decoy, but kept to read the logs that have it
+    DecoyMade,
+    /// The key of the vault was split into shares
+    KeySplit,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Action::Added => "added",
+            Action::Edited => "edited",
+            Action::Archived => "archived",
+            Action::Restored => "restored",
+            Action::Deleted => "deleted",
+            Action::Imported => "imported",
+            Action::Exported => "exported",
+            Action::Revealed => "revealed the secret of",
+            Action::Rekeyed => "changed the passphrase of",
+            Action::Reenrolled => "replaced the secret of",
+            Action::DecoyMade => "made a decoy next to",
+            Action::KeySplit => "split into shares the key of",
+        })
+    }
+}
+
+/// One action on the vault
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// Unix time of the action
+    pub time: u64,
+    pub action: Action,
+    /// The account acted on, or what the action was about when there is no single one
+    pub subject: String,
+}
+
+impl Entry {
+    /// An entry for `action` on `subject`, now
+    pub fn now(action: Action, subject: impl Into<String>) -> Entry {
+        Entry {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("Time went backwards")
+                .as_secs(),
+            action,
+            subject: subject.into(),
+        }
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}  {} {}",
+            format_time(self.time),
+            self.action,
+            self.subject
+        )
+    }
+}
+
+/// `time` (seconds since the unix epoch) as "YYYY-MM-DD HH:MM UTC"
+pub fn format_time(time: u64) -> String {
+    let (year, month, day, seconds) = civil(time);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
+}
+
+/// `time` as "YYYYMMDD-HHMMSS" in UTC, for file names that sort by time
+pub fn format_timestamp(time: u64) -> String {
+    let (year, month, day, seconds) = civil(time);
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// `time` as an RFC 3339 date in UTC, "YYYY-MM-DDTHH:MM:SSZ"
+pub fn format_rfc3339(time: u64) -> String {
+    let (year, month, day, seconds) = civil(time);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+// year, month, day and second of the day of `time`
+fn civil(time: u64) -> (i64, i64, i64, u64) {
+    let (days, seconds) = (time / 86_400, time % 86_400);
+    // civil from days, Howard Hinnant's algorithm
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day, seconds)
+}
@@ -0,0 +1,124 @@
+//! Typing codes into the window that has the focus, as if on the keyboard, for the
+//! sites that do not let codes be pasted into their fields. The key presses are
+//! sent by one of the tools of the platform, whichever works here.
+
+use serde::Deserialize;
+use std::io;
+use std::process::{Command, Stdio};
+use tracing::{debug, warn};
+
+/// What types the text
+#[derive(Clone, Copy, PartialEq, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backend {
+    /// The first tool that works
+    #[default]
+    Auto,
+    /// wtype on Wayland
+    Wtype,
+    /// xdotool on X11
+    Xdotool,
+    /// ydotool, through the ydotoold daemon, on Wayland or X11
+    Ydotool,
+    /// System Events through osascript on macOS
+    Osascript,
+    /// SendKeys through powershell.exe on Windows, or from WSL
+    Powershell,
+}
+
+// the tools tried in this order by Auto, with how they type; {} is the text, a code of
+// digits or letters that needs no quoting
+const TOOLS: &[(Backend, &[&str])] = &[
+    (Backend::Wtype, &["wtype", "--", "{}"]),
+    (
+        Backend::Xdotool,
+        &["xdotool", "type", "--delay", "50", "--", "{}"],
+    ),
+    (Backend::Ydotool, &["ydotool", "type", "--", "{}"]),
+    (
+        Backend::Osascript,
+        &[
+            "osascript",
+            "-e",
+            "tell application \"System Events\" to keystroke \"{}\"",
+        ],
+    ),
+    (
+        Backend::Powershell,
+        &[
+            "powershell.exe",
+            "-NoProfile",
+            "-Command",
+            "Add-Type -AssemblyName System.Windows.Forms; \
+             [System.Windows.Forms.SendKeys]::SendWait('{}')",
+        ],
+    ),
+];
+
+/// Type `text` into the focused window with `backend`
+pub fn type_text(backend: Backend, text: &str) -> Result<(), String> {
+    let result = match backend {
+        // the first tool installed here; one that ran and failed may have typed some of
+        // the text already, so the next is not tried then
+        Backend::Auto => TOOLS
+            .iter()
+            .find_map(|&(tool, command)| match run(command, text) {
+                Err(Failure::Missing(_)) => None,
+                result => Some(result.map(|()| tool)),
+            })
+            .unwrap_or_else(|| {
+                Err(Failure::Failed(
+                    "no tool to type with found, install wtype, xdotool or ydotool".to_string(),
+                ))
+            }),
+        tool => {
+            let command = TOOLS
+                .iter()
+                .find(|(other, _)| *other == tool)
+                .map(|&(_, command)| command)
+                .expect("every tool has a command");
+            run(command, text).map(|()| tool)
+        }
+    }
+    .map_err(|failure| match failure {
+        Failure::Missing(err) | Failure::Failed(err) => err,
+    });
+    match &result {
+        Ok(backend) => debug!(?backend, "typed into the focused window"),
+        Err(err) => warn!(?backend, error = %err, "cannot type"),
+    }
+    result.map(|_| ())
+}
+
+// why a tool did not type the text
+enum Failure {
+    // it is not installed here
+    Missing(String),
+    Failed(String),
+}
+
+// run `command` with `text` in place of {}
+fn run(command: &[&str], text: &str) -> Result<(), Failure> {
+    let status = Command::new(command[0])
+        .args(command[1..].iter().map(|arg| arg.replace("{}", text)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|err| {
+            let message = format!("cannot run {}: {}", command[0], err);
+            if err.kind() == io::ErrorKind::NotFound {
+                Failure::Missing(message)
+            } else {
+                Failure::Failed(message)
+            }
+        })?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Failure::Failed(format!(
+            "{} failed with {}",
+            command[0], status
+        )))
+    }
+}
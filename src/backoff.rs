@@ -0,0 +1,110 @@
+//! Ever longer waits after wrong passphrases, so that guessing at a terminal left
+//! unattended is slow. The count of failed attempts is kept in the data directory,
+//! starting the app again does not reset it; the first right passphrase does.
+
+use crate::paths;
+use crate::permissions;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+// the longest wait in seconds, reached after 10 failed attempts in a row
+const MAX_DELAY: u64 = 5 * 60;
+
+/// Failed attempts to open a vault since the last one that worked
+#[derive(Debug, Default)]
+pub struct Backoff {
+    failures: u32,
+    // unix time of the last failed attempt
+    last: u64,
+}
+
+impl Backoff {
+    /// The failed attempts recorded so far, none if nothing was recorded
+    pub fn load() -> Backoff {
+        let text = fs::read_to_string(file()).unwrap_or_default();
+        let mut fields = text.split_whitespace().map(|field| field.parse().ok());
+        match (fields.next().flatten(), fields.next().flatten()) {
+            (Some(failures), Some(last)) => Backoff {
+                failures: failures as u32,
+                last,
+            },
+            _ => Backoff::default(),
+        }
+    }
+
+    /// How many attempts failed in a row
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// How long to wait before the next attempt, zero once it may be made
+    pub fn remaining(&self) -> Duration {
+        if self.failures == 0 {
+            return Duration::ZERO;
+        }
+        let delay = 1u64
+            .checked_shl(self.failures - 1)
+            .unwrap_or(MAX_DELAY)
+            .min(MAX_DELAY);
+        Duration::from_secs((self.last + delay).saturating_sub(now()))
+    }
+
+    /// Sleep until the next attempt may be made, telling why on stderr
+    pub fn wait(&self) {
+        let remaining = self.remaining();
+        if !remaining.is_zero() {
+            eprintln!(
+                "{} failed attempts, waiting {}s before the next one",
+                self.failures,
+                remaining.as_secs()
+            );
+            thread::sleep(remaining);
+        }
+    }
+
+    /// Record a wrong passphrase, doubling the wait before the next attempt
+    pub fn fail(&mut self) {
+        self.failures += 1;
+        self.last = now();
+        warn!(failures = self.failures, "wrong passphrase");
+        self.store();
+    }
+
+    /// Forget the failed attempts after the right passphrase
+    pub fn succeed(&mut self) {
+        if self.failures > 0 {
+            info!(
+                failures = self.failures,
+                "right passphrase after failed attempts"
+            );
+            *self = Backoff::default();
+            let _ = fs::remove_file(file());
+        }
+    }
+
+    // failing to keep the count only makes the waits shorter, it is logged and that is all
+    fn store(&self) {
+        let file = file();
+        let stored = file
+            .parent()
+            .map_or(Ok(()), permissions::create_dir)
+            .and_then(|()| fs::write(&file, format!("{} {}\n", self.failures, self.last)));
+        if let Err(err) = stored {
+            warn!(file = %file.display(), error = %err, "cannot keep the count of failed attempts");
+        }
+    }
+}
+
+fn file() -> PathBuf {
+    paths::data_dir().join("unlock-failures")
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
@@ -0,0 +1,112 @@
+//! Copies of the vault file as it was before each save, so a write that went
+//! wrong or a mistaken delete can be undone by copying one back.
+//!
+//! They are kept in `backups/` next to the vault, named after the vault file and
+//! the time they were taken, e.g. `backups/work.vault.20261016-194743.120931544`,
+//! readable by their owner only. The file as it was before it was saved in a newer
+//! version is kept apart from those, e.g. `backups/work.vault-v2`, and never deleted.
+//! A backup never replaces another: one whose name is taken gets `-1`, `-2`... after it.
+
+use crate::permissions;
+use cli_totp::audit;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+/// How many backups of a vault are kept unless the config says otherwise
+pub const DEFAULT_KEEP: usize = 10;
+
+// set from the config at startup
+static KEEP: AtomicUsize = AtomicUsize::new(DEFAULT_KEEP);
+
+/// Keep the last `keep` backups of each vault, 0 takes none
+pub fn set_keep(keep: usize) {
+    KEEP.store(keep, Ordering::Relaxed);
+}
+
+/// Copy the vault at `path` into the backups, if there is one yet, and delete
+/// the oldest backups beyond the number kept
+pub fn take(path: &Path) -> io::Result<()> {
+    let keep = KEEP.load(Ordering::Relaxed);
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Ok(());
+    };
+    if keep == 0 || !path.exists() {
+        return Ok(());
+    }
+    let dir = dir.join("backups");
+    permissions::create_dir(&dir)?;
+    let prefix = format!("{}.", name.to_string_lossy());
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards");
+    // to the nanosecond, as saves can come more than once a second
+    let stamp = format!(
+        "{}.{:09}",
+        audit::format_timestamp(now.as_secs()),
+        now.subsec_nanos()
+    );
+    let backup = copy_new(path, &dir.join(format!("{}{}", prefix, stamp)))?;
+    debug!(backup = %backup.display(), "backup taken");
+
+    let mut backups: Vec<_> = fs::read_dir(&dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|backup| {
+            backup
+                .file_name()
+                .is_some_and(|file| file.to_string_lossy().starts_with(&prefix))
+        })
+        .collect();
+    // the timestamps sort oldest first
+    backups.sort();
+    for old in backups.iter().rev().skip(keep) {
+        debug!(backup = %old.display(), "old backup removed");
+        fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+/// Copy the vault at `path`, a file of `version`, into the backups before it is saved
+/// in a newer version, returning where the copy is
+pub fn take_version(path: &Path, version: u32) -> io::Result<PathBuf> {
+    let (dir, name) = match (path.parent(), path.file_name()) {
+        (Some(dir), Some(name)) => (dir.join("backups"), name.to_string_lossy()),
+        _ => return Err(io::Error::other("the vault is not a file")),
+    };
+    permissions::create_dir(&dir)?;
+    // not a timestamp after the name of the vault and its dot, so never rotated away
+    let backup = copy_new(path, &dir.join(format!("{}-v{}", name, version)))?;
+    debug!(backup = %backup.display(), "backup of the old version taken");
+    Ok(backup)
+}
+
+// copy the vault at `path` to `backup`, or to `backup` and the first free `-N` after it if
+// it is taken; the copy is readable by its owner only before anything is written to it
+fn copy_new(path: &Path, backup: &Path) -> io::Result<PathBuf> {
+    let mut vault = fs::File::open(path)?;
+    let mut n = 0;
+    loop {
+        let mut taken = backup.as_os_str().to_owned();
+        if n > 0 {
+            taken.push(format!("-{}", n));
+        }
+        let taken = PathBuf::from(taken);
+        let mut copy = match permissions::create_new(&taken) {
+            Ok(copy) => copy,
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                n += 1;
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        if let Err(err) = io::copy(&mut vault, &mut copy).and_then(|_| copy.sync_all()) {
+            let _ = fs::remove_file(&taken);
+            return Err(err);
+        }
+        return Ok(taken);
+    }
+}
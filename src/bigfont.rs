@@ -0,0 +1,41 @@
+//! Digits drawn with block characters, five rows high, for codes read from
+//! across the room.
+
+/// Rows of every glyph
+pub const HEIGHT: usize = 5;
+
+// each glyph is HEIGHT rows of four cells, '#' filled
+const DIGITS: [[&str; HEIGHT]; 10] = [
+    ["####", "#  #", "#  #", "#  #", "####"],
+    ["  # ", " ## ", "  # ", "  # ", " ###"],
+    ["####", "   #", "####", "#   ", "####"],
+    ["####", "   #", " ###", "   #", "####"],
+    ["#  #", "#  #", "####", "   #", "   #"],
+    ["####", "#   ", "####", "   #", "####"],
+    ["####", "#   ", "####", "#  #", "####"],
+    ["####", "   #", "  # ", " #  ", " #  "],
+    ["####", "#  #", "####", "#  #", "####"],
+    ["####", "#  #", "####", "   #", "####"],
+];
+const DOT: [&str; HEIGHT] = ["    ", "    ", " ## ", "    ", "    "];
+const BLANK: [&str; HEIGHT] = ["  ", "  ", "  ", "  ", "  "];
+
+/// `text` as HEIGHT lines of block characters; digits and the dots of a
+/// masked code are drawn, anything else leaves a gap
+pub fn render(text: &str) -> Vec<String> {
+    let mut lines = vec![String::new(); HEIGHT];
+    for c in text.chars() {
+        let glyph = match c {
+            '0'..='9' => &DIGITS[c as usize - '0' as usize],
+            '•' => &DOT,
+            _ => &BLANK,
+        };
+        for (line, row) in lines.iter_mut().zip(glyph.iter()) {
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.extend(row.chars().map(|cell| if cell == '#' { '█' } else { ' ' }));
+        }
+    }
+    lines
+}
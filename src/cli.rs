@@ -0,0 +1,458 @@
+//! The command line: the options of the app and the commands it runs instead of the UI.
+
+#[cfg(unix)]
+use crate::native;
+use crate::{compat, formats, merge, migrate, status, template, timesource};
+use clap::{Parser, Subcommand};
+use cli_totp::otp::SecretEncoding;
+use std::path::PathBuf;
+
+/// Time-based One-time Password (TOTP) Authenticator
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Log input events and state transitions to FILE (secrets redacted)
+    #[arg(long, value_name = "FILE", hide = true)]
+    pub record_session: Option<PathBuf>,
+    /// Feed the input events recorded in FILE to the UI before handing over to the keyboard
+    #[arg(long, value_name = "FILE", hide = true)]
+    pub replay: Option<PathBuf>,
+    /// Seconds added to the system clock when computing codes, for clocks that cannot be fixed
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 0,
+        allow_negative_numbers = true
+    )]
+    pub time_offset: i64,
+    /// Hide codes and accounts after MINUTES without input, 0 never locks [default: 5]
+    #[arg(long, value_name = "MINUTES")]
+    pub lock_after: Option<u64>,
+    /// Take the time from the unix timestamp COMMAND prints instead of the system clock
+    #[arg(long, value_name = "COMMAND", conflicts_with = "time_source_nmea")]
+    pub time_source_cmd: Option<String>,
+    /// Take the time from a GPS receiver's NMEA sentences on DEVICE (already configured, e.g. with stty)
+    #[arg(long, value_name = "DEVICE")]
+    pub time_source_nmea: Option<PathBuf>,
+    /// Start with codes masked until revealed with 'r'
+    #[arg(long)]
+    pub mask: bool,
+    /// Mask a revealed code again after SECONDS, 0 keeps it revealed
+    #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+    pub reveal_for: u64,
+    /// Use simple prompts and line-based output instead of the full screen UI, e.g. with a screen reader
+    #[arg(long)]
+    pub plain: bool,
+    /// Disable adding, editing, deleting and exporting accounts and never write the vault,
+    /// e.g. for one synced from the machine that owns it
+    #[arg(long)]
+    pub read_only: bool,
+    /// Open a vault file or directory others than you can read instead of refusing to;
+    /// the UI only warns about it
+    #[arg(long)]
+    pub allow_exposed: bool,
+    /// Keep the accounts in the encrypted vault of PROFILE, or in FILE if it has a dot or slash;
+    /// the passphrase is asked for, or taken from $CLI_TOTP_PASSPHRASE.
+    /// Without it, or a vault in the config, the accounts are gone on exit
+    #[arg(long, value_name = "PROFILE|FILE", conflicts_with = "pass")]
+    pub vault: Option<String>,
+    /// Keep the accounts in the password store instead, one `pass` entry per account under FOLDER
+    #[arg(long, value_name = "FOLDER")]
+    pub pass: Option<String>,
+    /// Create a new vault that also needs the response of this YubiKey challenge-response slot to open
+    #[arg(long, value_name = "SLOT", value_parser = clap::value_parser!(u8).range(1..=2))]
+    pub yubikey_slot: Option<u8>,
+    /// Read the settings from FILE instead of config.toml in the platform's config directory
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Keep the profiles' vaults in DIR instead of the platform's data directory
+    #[arg(long, value_name = "DIR")]
+    pub data_dir: Option<PathBuf>,
+    /// Log what is done to the log file, -v for the main steps, -vv to debug, -vvv for everything;
+    /// $RUST_LOG takes filter directives instead
+    #[arg(long, short, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+    /// Write the log to FILE instead of cli-totp.log in the data directory
+    #[arg(long, value_name = "FILE")]
+    pub log_file: Option<PathBuf>,
+    /// Draw the UI for older Windows consoles: on the normal screen, with 16 colors and no icons;
+    /// auto turns it on in the consoles that need it [default: auto]
+    #[arg(long, value_name = "MODE")]
+    pub compat: Option<compat::Mode>,
+    /// Draw the UI without colors, with bold, reversed and underlined text only, as $NO_COLOR does
+    #[arg(long)]
+    pub no_color: bool,
+}
+
+/// What to do instead of starting the UI
+#[derive(Subcommand)]
+pub enum Command {
+    /// Check the code generator against the RFC 4226 and RFC 6238 test vectors
+    SelfTest,
+    /// Keep the vault open and answer `list` and `get-code ACCOUNT` requests on a Unix socket,
+    /// and those of the browser extension once approved on its terminal or in a notification;
+    /// started by systemd on the socket it was handed, it listens there
+    #[cfg(unix)]
+    Agent {
+        #[command(subcommand)]
+        command: Option<AgentCommand>,
+        /// Listen on SOCKET instead of cli-totp-agent.sock in the runtime directory
+        #[arg(long, value_name = "SOCKET")]
+        socket: Option<PathBuf>,
+        /// Copy a code when KEYS, e.g. ctrl+alt+t, are pressed anywhere on the desktop, picking
+        /// the account with rofi, wofi, fuzzel or dmenu; Linux under X11 only
+        /// [default: hotkey of the config, or none]
+        #[arg(long, value_name = "KEYS")]
+        hotkey: Option<String>,
+        /// Copy the code of ACCOUNT on the hotkey instead of picking one
+        /// [default: hotkey_account of the config]
+        #[arg(long, value_name = "ACCOUNT")]
+        hotkey_account: Option<String>,
+    },
+    /// Encrypt the vault with a new passphrase, asked for twice unless
+    /// $CLI_TOTP_NEW_PASSPHRASE has it; the current one opens it first as usual
+    Passwd,
+    /// Make a second passphrase open a decoy vault instead of the real one, to give up when
+    /// forced to unlock the app; it is asked for twice unless $CLI_TOTP_DECOY_PASSPHRASE has
+    /// it. The decoy starts empty, add harmless accounts to it by opening the app with it.
+    /// The vault file does not tell whether it has a decoy; making one replaces the last
+    Decoy,
+    /// Split the key of the vault into shares to keep apart, or open the vault with them
+    Backup {
+        #[command(subcommand)]
+        command: BackupCommand,
+    },
+    /// Lock the running agent, which forgets the accounts and stops
+    #[cfg(unix)]
+    Lock {
+        /// The agent listening on SOCKET instead of cli-totp-agent.sock in the runtime directory
+        #[arg(long, value_name = "SOCKET")]
+        socket: Option<PathBuf>,
+    },
+    /// Answer the browser extension with the codes of the agent, as the native messaging
+    /// host the browser starts; --install registers it with the browser first
+    #[cfg(unix)]
+    NativeHost {
+        /// Register the host with BROWSER instead of answering
+        #[arg(long, value_enum, value_name = "BROWSER", requires = "extension")]
+        install: Option<native::Browser>,
+        /// ID of the extension allowed to use the host, as the browser shows it
+        #[arg(long, value_name = "ID")]
+        extension: Option<String>,
+        /// Ask the agent listening on SOCKET instead of cli-totp-agent.sock in the runtime
+        /// directory
+        #[arg(long, value_name = "SOCKET")]
+        socket: Option<PathBuf>,
+        /// What the browser starts the host with, the extension that asked for it
+        #[arg(hide = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        caller: Vec<String>,
+    },
+    /// Move the accounts of a plaintext vault file, as the first versions wrote before vaults
+    /// were encrypted, into the vault, check that they give the same codes there, and erase
+    /// the old file
+    Migrate {
+        /// The plaintext vault file
+        file: PathBuf,
+        /// Leave the plaintext file where it is
+        #[arg(long)]
+        keep: bool,
+    },
+    /// Manage where the accounts are kept
+    Storage {
+        #[command(subcommand)]
+        command: StorageCommand,
+    },
+    /// Print when accounts were added, deleted, exported or had their secret shown, oldest first;
+    /// only vault files keep this log
+    Log,
+    /// Print the otpauth:// URI of ACCOUNT, secret included, to enroll it in another authenticator
+    Uri {
+        /// Name of the account
+        account: String,
+        /// Draw the URI as a QR code as well, to scan it with a phone
+        #[arg(long)]
+        qr: bool,
+    },
+    /// Print `ISSUER 123456 (12s)` for ACCOUNT, e.g. for the tmux status line
+    Status {
+        /// Name of the account
+        account: String,
+        /// Print it again every second until stopped
+        #[arg(long)]
+        follow: bool,
+        /// Print TEMPLATE instead, e.g. '{issuer}\t{code}\t{expires_in}', with the fields
+        /// {issuer}, {name}, {label}, {code}, {next}, {expires_in}, {period}, {digits} and
+        /// {algorithm}
+        #[arg(long, value_name = "TEMPLATE")]
+        format: Option<template::Template>,
+    },
+    /// Print the code of ACCOUNT for a desktop bar, once a second as Waybar and Polybar expect
+    Bar {
+        /// Name of the account
+        account: String,
+        /// What the bar reads
+        #[arg(long, value_enum, default_value_t = status::Format::Waybar)]
+        format: status::Format,
+        /// Print it once instead of every second
+        #[arg(long)]
+        once: bool,
+    },
+    /// Print the account names for dmenu or rofi, or with --select copy the code of the one picked:
+    /// `cli-TOTP menu | rofi -dmenu | cli-TOTP menu --select -`
+    Menu {
+        /// Copy the code of NAME, or of the name read from stdin with -
+        #[arg(long, value_name = "NAME")]
+        select: Option<String>,
+        /// Print the code instead of copying it
+        #[arg(long, requires = "select")]
+        print: bool,
+        /// Print TEMPLATE for each account instead of its name, or for the one selected
+        /// instead of its code with --print; see `status --format`
+        #[arg(long, value_name = "TEMPLATE")]
+        format: Option<template::Template>,
+    },
+    /// Print the issuer and name of every account, tab separated, one per line
+    List {
+        /// Print TEMPLATE for each account instead; see `status --format`
+        #[arg(long, value_name = "TEMPLATE")]
+        format: Option<template::Template>,
+    },
+    /// Print the current code of ACCOUNT
+    Gen {
+        /// Name of the account
+        account: String,
+        /// Print TEMPLATE instead; see `status --format`
+        #[arg(long, value_name = "TEMPLATE")]
+        format: Option<template::Template>,
+        /// Print the code at TIME instead, unix seconds or an RFC 3339 date like
+        /// 2024-05-01T12:00:00Z
+        #[arg(long, value_name = "TIME", value_parser = parse_at)]
+        at: Option<u64>,
+        /// Print the N codes from the current one on, each with the time it is valid from
+        /// and until, to write them down before going without this machine
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+        next: Option<u64>,
+        /// Print the N codes before and after the current one too, the way --next does, for
+        /// when the clocks here and at the service disagree by a few seconds
+        #[arg(long, value_name = "N", conflicts_with = "next")]
+        window: Option<u64>,
+    },
+    /// Give ACCOUNT the new secret a service handed out when making you enroll again, keeping
+    /// its notes, recovery codes and history; the secret is read from stdin or a file
+    #[command(group = clap::ArgGroup::new("secret").required(true))]
+    Edit {
+        /// Name of the account
+        account: String,
+        /// Read the new secret from the first line of stdin
+        #[arg(long, group = "secret")]
+        new_secret: bool,
+        /// Read the new secret from FILE
+        #[arg(long, value_name = "FILE", group = "secret")]
+        new_secret_file: Option<PathBuf>,
+        /// How the new secret is written: base32, hex, or raw for characters used as they are
+        #[arg(long, default_value = "base32")]
+        encoding: SecretEncoding,
+        /// Keep showing the codes of the old secret for DAYS, until the service has switched
+        /// to the new one [default: keep_previous_secret of the config, or not kept]
+        #[arg(long, value_name = "DAYS")]
+        keep_previous: Option<u64>,
+    },
+    /// Print the code of ACCOUNT, or of every account, each time it changes
+    Watch {
+        /// Name of the account, every account when left out
+        account: Option<String>,
+        /// Print TEMPLATE for each code instead; see `status --format`
+        #[arg(long, value_name = "TEMPLATE")]
+        format: Option<template::Template>,
+    },
+    /// Add an account to the vault, reading its Base32 secret from stdin or a file so it stays out of argv
+    #[command(group = clap::ArgGroup::new("secret").required(true))]
+    Add {
+        /// Name of the account, e.g. the user name at the service
+        #[arg(long)]
+        name: String,
+        /// The service the account belongs to
+        #[arg(long, default_value = "")]
+        issuer: String,
+        /// Read the secret from the first line of stdin
+        #[arg(long, group = "secret")]
+        secret_stdin: bool,
+        /// Read the secret from FILE
+        #[arg(long, value_name = "FILE", group = "secret")]
+        secret_file: Option<PathBuf>,
+        /// Unix time the TOTP counter starts from
+        #[arg(long, default_value_t = 0)]
+        t0: u64,
+        /// Free text kept with the account
+        #[arg(long, default_value = "")]
+        note: String,
+        /// Emoji or symbol shown with the account, the issuer's if it is a well-known one
+        #[arg(long, default_value = "")]
+        icon: String,
+        /// Domain of the web site the browser extension fills in the codes on, e.g. github.com;
+        /// the issuer's if it is a domain or a well-known service
+        #[arg(long, value_name = "DOMAIN", default_value = "")]
+        site: String,
+        /// How the secret is written: base32, hex, or raw for characters used as they are
+        #[arg(long, default_value = "base32")]
+        encoding: SecretEncoding,
+        /// Make the codes the first versions of this app made, for services enrolled with them;
+        /// the secret is kept as the characters it is written with
+        #[arg(long, conflicts_with = "encoding")]
+        legacy: bool,
+    },
+    /// Write every account, secrets included, to an export file encrypted with age or gpg,
+    /// or in the clear with --insecure-plaintext
+    #[command(group = clap::ArgGroup::new("encryption").required(true))]
+    Export {
+        /// Encrypt the export to the age RECIPIENT (age1...), may be repeated
+        #[arg(long = "age-recipient", value_name = "RECIPIENT", group = "encryption")]
+        age_recipients: Vec<String>,
+        /// Encrypt the export with gpg to the key of RECIPIENT, may be repeated
+        #[arg(long = "gpg-recipient", value_name = "RECIPIENT", group = "encryption")]
+        gpg_recipients: Vec<String>,
+        /// Do not encrypt the export: anyone who can read it can generate your codes
+        #[arg(long, group = "encryption")]
+        insecure_plaintext: bool,
+        /// Write the accounts as the vault's JSON, or for other tools; accounts a format
+        /// cannot hold are left out and told about
+        #[arg(long, value_enum, default_value_t = formats::Format::Json)]
+        format: formats::Format,
+        /// Write the export to FILE instead of stdout
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Add the accounts of an export file, or the TOTP entries of a KeePass database, to the vault
+    #[command(group = clap::ArgGroup::new("source"))]
+    Import {
+        /// The export file or KeePass database
+        file: PathBuf,
+        /// Decrypt the export with the age identities in FILE
+        #[arg(long, value_name = "FILE", group = "source")]
+        age_identity: Option<PathBuf>,
+        /// Decrypt the export with gpg
+        #[arg(long, group = "source")]
+        gpg: bool,
+        /// What the export holds; CSV needs a header naming at least the name and secret
+        /// columns (issuer, algorithm, digits, period and the encoding of the secret, base32,
+        /// hex or raw, are optional)
+        #[arg(long, value_enum, default_value_t = formats::Format::Json, conflicts_with = "kdbx")]
+        format: formats::Format,
+        /// Read the export with the importer plugin totp-import-NAME, found on $PATH or in the
+        /// plugins directory of the data directory, for formats this app does not know
+        #[arg(long, value_name = "NAME", conflicts_with_all = ["format", "kdbx"])]
+        plugin: Option<String>,
+        /// Read a KeePass or KeePassXC database, asking for its password
+        #[arg(long, group = "source")]
+        kdbx: bool,
+        /// Open the KeePass database with the key file FILE as well
+        #[arg(long, value_name = "FILE", requires = "kdbx")]
+        kdbx_keyfile: Option<PathBuf>,
+        /// What to keep of an account named like one in the vault but giving other codes;
+        /// those giving the same codes are skipped
+        #[arg(long, value_enum, default_value_t = merge::Resolution::Ask)]
+        on_conflict: merge::Resolution,
+        /// Import every account that could be read without listing them first to pick from;
+        /// they are not listed either when stdin is not a terminal
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Check the clock, the vault file, the clipboard, the terminal and the config, telling
+    /// what to do about what is wrong; exits with 1 if something does not work
+    Doctor {
+        /// Do not open the vault to check it is whole, which needs its passphrase
+        #[arg(long)]
+        no_unlock: bool,
+        /// Make the vault and its directory readable by you only first, if others can
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Bring the vault file and its copy on the WebDAV server of `[sync]` in the config in
+    /// step, copying the one that changed since the last sync to the other
+    Sync {
+        /// Keep the vault file if both changed, overwriting the copy on the server
+        #[arg(long, conflicts_with = "pull")]
+        push: bool,
+        /// Keep the copy on the server if both changed, the vault file is backed up first
+        #[arg(long)]
+        pull: bool,
+    },
+    /// List the accounts added, removed or changed from one vault or export to another,
+    /// without showing their secrets; exits with 1 if there are any, as diff does
+    Diff {
+        /// The vault or export before
+        before: PathBuf,
+        /// The vault or export after
+        after: PathBuf,
+        /// What the exports hold, vaults are told apart on their own
+        #[arg(long, value_enum, default_value_t = formats::Format::Json)]
+        format: formats::Format,
+        /// Decrypt the exports with the age identities in FILE
+        #[arg(long, value_name = "FILE", conflicts_with = "gpg")]
+        age_identity: Option<PathBuf>,
+        /// Decrypt the exports with gpg
+        #[arg(long)]
+        gpg: bool,
+    },
+}
+
+#[cfg(unix)]
+#[derive(Subcommand)]
+pub enum AgentCommand {
+    /// Write the systemd user units starting the agent for the vault on the first request
+    /// to its socket, asking for the passphrase with systemd-ask-password; it stops, and
+    /// forgets the accounts, with the session or once locked
+    Install,
+}
+
+#[derive(Subcommand)]
+pub enum BackupCommand {
+    /// Split the key of the vault into SHARES shares, any THRESHOLD of which open the vault
+    /// without its passphrase or YubiKey, printed one after the other to keep each in its
+    /// own place; they stop working once the passphrase is changed
+    Shard {
+        /// How many shares are needed to open the vault
+        #[arg(long, default_value_t = 2)]
+        threshold: u8,
+        /// How many shares to make
+        #[arg(long, default_value_t = 3)]
+        shares: u8,
+        /// Write each share to its own file in DIR instead of printing them
+        #[arg(long, short, value_name = "DIR")]
+        output: Option<PathBuf>,
+    },
+    /// Open the vault with shares from `backup shard` when its passphrase is lost, and
+    /// encrypt it with a new one, asked for twice unless $CLI_TOTP_NEW_PASSPHRASE has it
+    Recover {
+        /// Files holding the shares, read from stdin one per line when there are none
+        files: Vec<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum StorageCommand {
+    /// Move every account from the vault file to the password store or back, checking that
+    /// each gives the same codes once moved before the old place is emptied
+    Migrate {
+        /// Where the accounts are now
+        #[arg(long, value_enum)]
+        from: migrate::Backend,
+        /// Where they go, which must not have accounts yet
+        #[arg(long, value_enum)]
+        to: migrate::Backend,
+        /// The folder of the password store, the vault file is the one of --vault or the config
+        #[arg(long, value_name = "FOLDER")]
+        folder: String,
+        /// Leave the accounts where they were as well
+        #[arg(long)]
+        keep_source: bool,
+    },
+}
+
+// the time of `gen --at`
+fn parse_at(text: &str) -> Result<u64, String> {
+    timesource::parse_time(text)
+        .ok_or_else(|| "expected unix seconds or an RFC 3339 date like 2024-05-01T12:00:00Z".into())
+}
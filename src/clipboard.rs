@@ -0,0 +1,210 @@
+//! Copying codes to the system clipboard, with the native clipboard of the
+//! platform or one of the clipboard tools, whichever works here.
+
+use serde::Deserialize;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use tracing::{debug, warn};
+
+/// What puts text on the clipboard
+#[derive(Clone, Copy, PartialEq, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backend {
+    /// The native clipboard, then the first tool that works
+    #[default]
+    Auto,
+    /// The native clipboard of X11, macOS or Windows
+    Native,
+    /// wl-copy and wl-paste of Wayland
+    WlCopy,
+    /// xclip on X11
+    Xclip,
+    /// pbcopy and pbpaste on macOS
+    Pbcopy,
+    /// clip.exe, e.g. from WSL
+    #[serde(rename = "clip.exe")]
+    ClipExe,
+}
+
+// the tools tried in this order by Auto, with how they copy and paste
+const TOOLS: &[(Backend, &[&str], &[&str])] = &[
+    (Backend::WlCopy, &["wl-copy"], &["wl-paste", "--no-newline"]),
+    (
+        Backend::Xclip,
+        &["xclip", "-selection", "clipboard"],
+        &["xclip", "-selection", "clipboard", "-o"],
+    ),
+    (Backend::Pbcopy, &["pbcopy"], &["pbpaste"]),
+    (
+        Backend::ClipExe,
+        &["clip.exe"],
+        &["powershell.exe", "-NoProfile", "-Command", "Get-Clipboard"],
+    ),
+];
+
+/// The system clipboard, connected to on first use
+#[derive(Default)]
+pub struct Clipboard {
+    backend: Backend,
+    // kept for as long as the app runs, on X11 what was copied is gone once it is dropped
+    native: Option<arboard::Clipboard>,
+    // the backend the last copy went through, what clearing uses
+    used: Option<Backend>,
+}
+
+impl Clipboard {
+    /// A clipboard copying with `backend`
+    pub fn new(backend: Backend) -> Clipboard {
+        Clipboard {
+            backend,
+            ..Clipboard::default()
+        }
+    }
+
+    /// Put `text` on the clipboard
+    pub fn copy(&mut self, text: &str) -> Result<(), String> {
+        let result = match self.backend {
+            // the first tool that takes it, tools not installed here fail too
+            Backend::Auto => self
+                .copy_native(text)
+                .map(|()| Backend::Native)
+                .or_else(|err| {
+                    TOOLS
+                        .iter()
+                        .find(|(_, copy, _)| run(copy, text).is_ok())
+                        .map(|&(tool, _, _)| tool)
+                        .ok_or(err)
+                }),
+            Backend::Native => self.copy_native(text).map(|()| Backend::Native),
+            tool => run(tool_commands(tool).0, text)
+                .map(|()| tool)
+                .map_err(|err| format!("cannot run {}: {}", tool_commands(tool).0[0], err)),
+        };
+        match &result {
+            Ok(backend) => debug!(?backend, "copied to the clipboard"),
+            Err(err) => warn!(backend = ?self.backend, error = %err, "cannot copy"),
+        }
+        self.used = result.as_ref().ok().copied();
+        result.map(|_| ())
+    }
+
+    /// Whether what was copied last is gone once this process exits, as with the native
+    /// clipboard of X11 and Wayland where the program that copied serves the paste
+    pub fn needs_serving(&self) -> bool {
+        self.used == Some(Backend::Native) && cfg!(all(unix, not(target_os = "macos")))
+    }
+
+    /// Empty the clipboard if it still holds `text`, leaving anything copied since alone
+    pub fn clear_if(&mut self, text: &str) {
+        match self.used {
+            Some(Backend::Native) => {
+                if let Some(native) = &mut self.native {
+                    if native.get_text().is_ok_and(|current| current == text) {
+                        debug!("clipboard cleared");
+                        let _ = native.clear();
+                    } else {
+                        debug!("clipboard changed since the copy, left alone");
+                    }
+                }
+            }
+            Some(tool) => {
+                let (copy, paste) = tool_commands(tool);
+                let current = Command::new(paste[0]).args(&paste[1..]).output();
+                // some tools end what they paste with a newline
+                if current.is_ok_and(|current| {
+                    String::from_utf8_lossy(&current.stdout).trim_end() == text
+                }) {
+                    debug!(tool = copy[0], "clipboard cleared");
+                    let _ = run(copy, "");
+                } else {
+                    debug!(
+                        tool = copy[0],
+                        "clipboard changed since the copy, left alone"
+                    );
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn copy_native(&mut self, text: &str) -> Result<(), String> {
+        let native = match &mut self.native {
+            Some(native) => native,
+            None => self
+                .native
+                .insert(arboard::Clipboard::new().map_err(|err| err.to_string())?),
+        };
+        native.set_text(text).map_err(|err| err.to_string())
+    }
+}
+
+/// What copies with `backend` here, without copying anything: the native clipboard or
+/// the tool, the first that works for `Backend::Auto`
+pub fn check(backend: Backend) -> Result<&'static str, String> {
+    let native = || {
+        arboard::Clipboard::new()
+            .map(|_| "the native clipboard")
+            .map_err(|err| format!("no native clipboard: {}", err))
+    };
+    match backend {
+        Backend::Auto => native().or_else(|err| {
+            TOOLS
+                .iter()
+                .map(|(_, copy, _)| copy[0])
+                .find(|tool| installed(tool))
+                .ok_or(format!(
+                    "{}, and none of the clipboard tools is installed",
+                    err
+                ))
+        }),
+        Backend::Native => native(),
+        tool => {
+            let copy = tool_commands(tool).0[0];
+            if installed(copy) {
+                Ok(copy)
+            } else {
+                Err(format!("{} is not installed", copy))
+            }
+        }
+    }
+}
+
+// whether `program` is on $PATH
+fn installed(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+// how the tool `backend` copies and pastes
+fn tool_commands(backend: Backend) -> (&'static [&'static str], &'static [&'static str]) {
+    TOOLS
+        .iter()
+        .find(|(tool, _, _)| *tool == backend)
+        .map(|&(_, copy, paste)| (copy, paste))
+        .expect("every tool has commands")
+}
+
+// run `command`, feeding it `text`; tools that keep serving the clipboard in the
+// background must not hold on to our output
+fn run(command: &[&str], text: &str) -> io::Result<()> {
+    let mut child = Command::new(command[0])
+        .args(&command[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(text.as_bytes())?;
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "{} failed with {}",
+            command[0], status
+        )))
+    }
+}
@@ -0,0 +1,822 @@
+//! The commands run instead of the UI, each printing what it was asked for or did.
+
+#[cfg(unix)]
+use crate::cli::AgentCommand;
+use crate::cli::{BackupCommand, Cli, Command, StorageCommand};
+use crate::config::Config;
+use crate::formats::Importer;
+use crate::store::{self, Store};
+use crate::unix_time_f64;
+#[cfg(unix)]
+use crate::{agent, hotkey, native, systemd};
+use crate::{
+    clipboard, diff, doctor, export, formats, hooks, kdbx, merge, migrate, notify, preview, qr,
+    selftest, shamir, status, watch, webdav, yubikey,
+};
+use cli_totp::audit::{self, Action};
+use cli_totp::{ntp, otpauth, Account, Vault};
+use std::error::Error;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, info};
+
+// how long `menu` keeps serving a code copied to the native clipboard of X11 or Wayland
+// when the config does not say when to clear it
+const NATIVE_CLIPBOARD_HOLD: Duration = Duration::from_secs(60);
+
+/// Run `command` with the options of `cli` and the config read from `config_path`, on
+/// the vault file at `vault_path` or the password store of `cli`
+pub fn run(
+    command: Command,
+    cli: &Cli,
+    config: Config,
+    config_path: &Path,
+    vault_path: Option<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::SelfTest => std::process::exit(if selftest::run() { 0 } else { 1 }),
+        #[cfg(unix)]
+        Command::Agent {
+            command: Some(AgentCommand::Install),
+            socket,
+            ..
+        } => {
+            if cli.pass.is_some() {
+                return Err("the agent started by systemd opens vault files only".into());
+            }
+            let vault = vault_path.ok_or("tell which vault the agent opens with --vault")?;
+            let socket = socket.unwrap_or_else(agent::default_socket);
+            for unit in systemd::install(&vault, &socket)? {
+                eprintln!("Wrote {}", unit.display());
+            }
+            eprintln!(
+                "Start it on demand with `systemctl --user daemon-reload && systemctl --user \
+                 enable --now {}.socket`",
+                systemd::UNIT
+            );
+            Ok(())
+        }
+        #[cfg(unix)]
+        Command::Agent {
+            command: None,
+            socket,
+            hotkey,
+            hotkey_account,
+        } => {
+            let (vault, _) = store::open(vault_path, cli.pass.as_deref(), cli.yubikey_slot, false)?;
+            let socket = socket.unwrap_or_else(agent::default_socket);
+            let hotkey = hotkey.or(config.hotkey).map(|keys| hotkey::Settings {
+                keys,
+                account: hotkey_account.or(config.hotkey_account),
+                picker: config.hotkey_picker,
+                clipboard: config.clipboard,
+            });
+            agent::run(vault, &socket, cli.time_offset, hotkey)?;
+            Ok(())
+        }
+        Command::Passwd => {
+            store::check_writable("change the passphrase")?;
+            if cli.pass.is_some() {
+                return Err("the password store has no passphrase of its own".into());
+            }
+            let (mut vault, mut store) = store::open(vault_path, None, cli.yubikey_slot, false)?;
+            let passphrase = new_passphrase("CLI_TOTP_NEW_PASSPHRASE", "New passphrase")?;
+            let kdf = store::kdf();
+            store.set_passphrase(&mut vault, &passphrase, kdf, &yubikey::respond)?;
+            eprintln!("Changed the passphrase");
+            Ok(())
+        }
+        Command::Decoy => {
+            store::check_writable("make a decoy")?;
+            if cli.pass.is_some() {
+                return Err("only vault files can have a decoy".into());
+            }
+            let (mut vault, mut store) = store::open(vault_path, None, cli.yubikey_slot, false)?;
+            let passphrase = new_passphrase("CLI_TOTP_DECOY_PASSPHRASE", "Decoy passphrase")?;
+            store.make_decoy(&mut vault, &passphrase, &yubikey::respond)?;
+            eprintln!("Made the decoy, open the app with its passphrase to add accounts to it");
+            Ok(())
+        }
+        Command::Backup {
+            command:
+                BackupCommand::Shard {
+                    threshold,
+                    shares,
+                    output,
+                },
+        } => {
+            if cli.pass.is_some() {
+                return Err("only vault files have a key to split".into());
+            }
+            let (mut vault, mut store) = store::open(vault_path, None, cli.yubikey_slot, false)?;
+            let Store::Vault { path, key } = &store else {
+                unreachable!("a vault file was opened")
+            };
+            let shares = shamir::split(&key.to_bytes(), threshold, shares)?;
+            match output {
+                Some(dir) => {
+                    fs::create_dir_all(&dir)?;
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    for share in &shares {
+                        let file = dir.join(format!("{}.share{}", name, share.x));
+                        fs::write(&file, format!("{}\n", share))?;
+                        eprintln!("Wrote share {} to {}", share.x, file.display());
+                    }
+                }
+                None => {
+                    for share in &shares {
+                        println!("Share {} of {}:\n{}\n", share.x, shares.len(), share);
+                    }
+                }
+            }
+            eprintln!(
+                "Any {} of the {} shares open the vault with `backup recover`, keep them apart",
+                threshold,
+                shares.len()
+            );
+            vault.record(Action::KeySplit, "the vault");
+            store.save(&vault)?;
+            Ok(())
+        }
+        Command::Backup {
+            command: BackupCommand::Recover { files },
+        } => {
+            store::check_writable("recover the vault")?;
+            let path = vault_path.ok_or("tell which vault to recover with --vault")?;
+            let mut lines = vec![];
+            for file in &files {
+                let text = fs::read_to_string(file)
+                    .map_err(|err| format!("cannot read {}: {}", file.display(), err))?;
+                lines.extend(text.lines().map(str::to_string));
+            }
+            if files.is_empty() {
+                eprintln!("Paste the shares, one per line, then an empty line:");
+                for line in io::stdin().lines() {
+                    let line = line?;
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    lines.push(line);
+                }
+            }
+            let shares = lines
+                .iter()
+                .filter(|line| !line.trim().is_empty() && !line.starts_with("Share "))
+                .map(|line| line.parse())
+                .collect::<Result<Vec<shamir::Share>, _>>()?;
+            let key = to_key(&shamir::combine(&shares)?)?;
+            let (mut vault, key) = match Vault::load_with_key(&path, key) {
+                Err(cli_totp::Error::Passphrase) => {
+                    return Err(
+                        "the shares are of another vault, or of an older passphrase, \
+                         or one of them is damaged"
+                            .into(),
+                    )
+                }
+                opened => opened?,
+            };
+            store::upgrade(&path, &mut vault, &key)?;
+            let mut store = Store::Vault { path, key };
+            let passphrase = new_passphrase("CLI_TOTP_NEW_PASSPHRASE", "New passphrase")?;
+            let kdf = store::kdf();
+            store.set_passphrase(&mut vault, &passphrase, kdf, &yubikey::respond)?;
+            eprintln!("Recovered the vault with a new passphrase, the shares no longer open it");
+            Ok(())
+        }
+        #[cfg(unix)]
+        Command::Lock { socket } => {
+            let socket = socket.unwrap_or_else(agent::default_socket);
+            let answer = agent::request(&socket, "lock")?;
+            if answer.trim() != "locked" {
+                return Err(format!("the agent did not lock: {}", answer.trim()).into());
+            }
+            Ok(())
+        }
+        #[cfg(unix)]
+        Command::NativeHost {
+            install,
+            extension,
+            socket,
+            caller,
+        } => {
+            if let Some(browser) = install {
+                let extension = extension.expect("--install requires --extension");
+                let manifest = native::install(browser, &extension, socket.as_deref())?;
+                eprintln!(
+                    "Registered the native messaging host in {}",
+                    manifest.display()
+                );
+                return Ok(());
+            }
+            debug!(?caller, "started by the browser");
+            native::run(&socket.unwrap_or_else(agent::default_socket))?;
+            Ok(())
+        }
+        Command::Migrate { file, keep } => {
+            store::check_writable("migrate the plaintext vault")?;
+            let accounts = migrate::read_plaintext(&file)?;
+            let same = |vault: &Path| fs::canonicalize(vault).ok() == fs::canonicalize(&file).ok();
+            if cli.pass.is_none() && vault_path.as_deref().is_some_and(same) {
+                return Err(format!(
+                    "{} is the plaintext vault, give the encrypted one another path with --vault",
+                    file.display()
+                )
+                .into());
+            }
+            let (mut vault, mut store) =
+                store::open(vault_path, cli.pass.as_deref(), cli.yubikey_slot, true)?;
+            if !vault.accounts.is_empty() {
+                return Err(format!(
+                    "the vault has {} accounts already, move them into an empty one",
+                    vault.accounts.len()
+                )
+                .into());
+            }
+            let count = accounts.len();
+            vault.accounts = accounts;
+            vault.record(
+                Action::Imported,
+                format!("{} accounts from {}", count, file.display()),
+            );
+            store.save(&vault)?;
+            let now = (unix_time_f64() as u64).saturating_add_signed(cli.time_offset);
+            migrate::verify(&vault, &store.load()?, now)
+                .map_err(|err| format!("{}, {} is left as it was", err, file.display()))?;
+            eprintln!(
+                "Moved {} accounts from {} to the vault",
+                count,
+                file.display()
+            );
+            if !keep {
+                migrate::erase(&file)?;
+                eprintln!(
+                    "Erased {}; copies the filesystem or the disk kept elsewhere may remain",
+                    file.display()
+                );
+            }
+            Ok(())
+        }
+        Command::Storage {
+            command:
+                StorageCommand::Migrate {
+                    from,
+                    to,
+                    folder,
+                    keep_source,
+                },
+        } => {
+            store::check_writable("move the accounts")?;
+            if from == to {
+                return Err("the accounts are there already".into());
+            }
+            let open = |backend, create| match backend {
+                migrate::Backend::File => {
+                    store::open(vault_path.clone(), None, cli.yubikey_slot, create)
+                }
+                migrate::Backend::Pass => store::open(None, Some(&folder), None, create),
+            };
+            let (vault, mut source) = open(from, false)?;
+            let (existing, mut target) = open(to, true)?;
+            if !existing.accounts.is_empty() {
+                return Err(format!(
+                    "{} has {} accounts already, move them into an empty one",
+                    to.name(),
+                    existing.accounts.len()
+                )
+                .into());
+            }
+            target.save(&vault)?;
+            let now = (unix_time_f64() as u64).saturating_add_signed(cli.time_offset);
+            migrate::verify(&vault, &target.load()?, now)
+                .map_err(|err| format!("{}, the accounts are still in {} too", err, from.name()))?;
+            eprintln!("Moved {} accounts to {}", vault.accounts.len(), to.name());
+            if !keep_source {
+                // the log of a vault file stays with it
+                let mut vault = vault;
+                vault.accounts.clear();
+                source.save(&vault)?;
+            }
+            Ok(())
+        }
+        Command::Log => {
+            let (vault, _) = store::open(vault_path, cli.pass.as_deref(), cli.yubikey_slot, false)?;
+            for entry in vault.log.iter() {
+                println!("{}", entry);
+            }
+            Ok(())
+        }
+        Command::Uri { account, qr } => {
+            let (mut vault, mut store) =
+                store::open(vault_path, cli.pass.as_deref(), cli.yubikey_slot, false)?;
+            let uri = match vault.accounts.iter().find(|a| a.name == account) {
+                Some(found) if found.legacy => {
+                    return Err(format!(
+                        "{} makes the codes of the first versions of this app, other authenticators cannot",
+                        account
+                    )
+                    .into())
+                }
+                Some(found) => otpauth::to_uri(found),
+                None => return Err(format!("there is no account {}", account).into()),
+            };
+            if qr {
+                for line in qr::render(&uri)? {
+                    println!("{}", line);
+                }
+            }
+            println!("{}", uri);
+            // the URI holds the secret
+            vault.record(Action::Revealed, account);
+            store.save(&vault)?;
+            Ok(())
+        }
+        Command::Status {
+            account,
+            follow,
+            format,
+        } => {
+            let (vault, _) = store::open(vault_path, cli.pass.as_deref(), cli.yubikey_slot, false)?;
+            match format {
+                Some(template) => {
+                    print_status(&vault, &account, cli.time_offset, follow, &|a, now| {
+                        template.render(a, now)
+                    })
+                }
+                None => print_status(&vault, &account, cli.time_offset, follow, &status::line),
+            }
+        }
+        Command::Bar {
+            account,
+            format,
+            once,
+        } => {
+            let (vault, _) = store::open(vault_path, cli.pass.as_deref(), cli.yubikey_slot, false)?;
+            print_status(&vault, &account, cli.time_offset, !once, &|a, now| {
+                status::format(a, now, format)
+            })
+        }
+        Command::List { format } => {
+            let (vault, _) = store::open(vault_path, cli.pass.as_deref(), cli.yubikey_slot, false)?;
+            let now = (unix_time_f64() as u64).saturating_add_signed(cli.time_offset);
+            for account in vault.accounts.iter().filter(|a| !a.archived) {
+                match &format {
+                    Some(template) => println!("{}", template.render(account, now)),
+                    None => println!("{}\t{}", account.issuer, account.name),
+                }
+            }
+            Ok(())
+        }
+        Command::Gen {
+            account,
+            format,
+            at,
+            next,
+            window,
+        } => {
+            let (mut vault, mut store) =
+                store::open(vault_path, cli.pass.as_deref(), cli.yubikey_slot, false)?;
+            let now = (unix_time_f64() as u64).saturating_add_signed(cli.time_offset);
+            let Some(found) = vault.accounts.iter_mut().find(|a| a.name == account) else {
+                return Err(format!("there is no account {}", account).into());
+            };
+            let time = at.unwrap_or(now);
+            let code = |time| match &format {
+                Some(template) => template.render(found, time),
+                None => found.code_at(time),
+            };
+            let step = found.step_at(time);
+            let steps = match (next, window) {
+                (Some(count), _) => step..=step.saturating_add(count - 1),
+                (None, Some(window)) => step.saturating_sub(window)..=step.saturating_add(window),
+                (None, None) => {
+                    println!("{}", code(time));
+                    // a code of another time was not typed anywhere
+                    if at.is_none() {
+                        found.record_use(now);
+                        store.save(&vault)?;
+                    }
+                    return Ok(());
+                }
+            };
+            let period = found.period.max(1);
+            for start in steps.map(|step| found.t0.saturating_add(step.saturating_mul(period))) {
+                println!(
+                    "{}  {}  {}",
+                    code(start),
+                    audit::format_rfc3339(start),
+                    audit::format_rfc3339(start.saturating_add(period))
+                );
+            }
+            Ok(())
+        }
+        Command::Menu {
+            select,
+            print,
+            format,
+        } => {
+            let (mut vault, mut store) =
+                store::open(vault_path, cli.pass.as_deref(), cli.yubikey_slot, false)?;
+            let Some(name) = select else {
+                let now = (unix_time_f64() as u64).saturating_add_signed(cli.time_offset);
+                for account in vault.accounts.iter().filter(|a| !a.archived) {
+                    match &format {
+                        Some(template) => println!("{}", template.render(account, now)),
+                        None => println!("{}", account.name),
+                    }
+                }
+                return Ok(());
+            };
+            let name = if name == "-" {
+                let mut line = String::new();
+                io::stdin().read_line(&mut line)?;
+                line.trim().to_string()
+            } else {
+                name
+            };
+            // nothing picked, e.g. the menu was closed with <Esc>
+            if name.is_empty() {
+                return Ok(());
+            }
+            let now = (unix_time_f64() as u64).saturating_add_signed(cli.time_offset);
+            let Some(account) = vault.accounts.iter_mut().find(|a| a.name == name) else {
+                return Err(format!("there is no account {}", name).into());
+            };
+            let (code, left) = (account.code_at(now), account.totp().seconds_remaining(now));
+            let printed = match &format {
+                Some(template) => template.render(account, now),
+                None => code.clone(),
+            };
+            account.record_use(now);
+            store.save(&vault)?;
+            if print {
+                println!("{}", printed);
+                return Ok(());
+            }
+            let mut clipboard = clipboard::Clipboard::new(config.clipboard);
+            clipboard.copy(&code)?;
+            if let Some(account) = vault.accounts.iter().find(|a| a.name == name) {
+                hooks::account(hooks::Event::Copy, account);
+            }
+            // stay around until the code is about to expire to tell so
+            let notice = config.notify_before_expiry.map(|before| {
+                thread::spawn(move || {
+                    thread::sleep(Duration::from_secs(left.saturating_sub(before)));
+                    let _ = notify::expiring(&name, left.min(before)).join();
+                })
+            });
+            // and to serve the paste or to take the code off the clipboard again
+            let hold = config
+                .clipboard_clear_after
+                .map(Duration::from_secs)
+                .or(clipboard.needs_serving().then_some(NATIVE_CLIPBOARD_HOLD));
+            if let Some(hold) = hold {
+                thread::sleep(hold);
+                clipboard.clear_if(&code);
+            }
+            if let Some(notice) = notice {
+                let _ = notice.join();
+            }
+            Ok(())
+        }
+        Command::Watch { account, format } => {
+            let (vault, _) = store::open(vault_path, cli.pass.as_deref(), cli.yubikey_slot, false)?;
+            let accounts: Vec<&Account> = match &account {
+                Some(name) => vault.accounts.iter().filter(|a| &a.name == name).collect(),
+                None => vault.accounts.iter().collect(),
+            };
+            match (&account, accounts.is_empty()) {
+                (Some(name), true) => return Err(format!("there is no account {}", name).into()),
+                (None, true) => return Err("there are no accounts".into()),
+                _ => {}
+            }
+            match watch::run(&accounts, cli.time_offset, format.as_ref()) {
+                // the reader went away
+                Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                result => Ok(result?),
+            }
+        }
+        Command::Edit {
+            account,
+            new_secret: _,
+            new_secret_file,
+            encoding,
+            keep_previous,
+        } => {
+            store::check_writable("edit accounts")?;
+            let secret = match new_secret_file {
+                Some(file) => fs::read_to_string(file)?,
+                None => {
+                    let mut line = String::new();
+                    io::stdin().read_line(&mut line)?;
+                    line
+                }
+            };
+            let secret = encoding
+                .to_base32(secret.trim())
+                .filter(|secret| !secret.is_empty())
+                .ok_or_else(|| format!("the new secret is not {}", encoding.name()))?;
+            let (mut vault, mut store) =
+                store::open(vault_path, cli.pass.as_deref(), cli.yubikey_slot, false)?;
+            let now = unix_time_f64() as u64;
+            let Some(found) = vault.accounts.iter_mut().find(|a| a.name == account) else {
+                return Err(format!("there is no account {}", account).into());
+            };
+            let keep_until = keep_previous
+                .or(config.keep_previous_secret)
+                .filter(|&days| days > 0)
+                .map(|days| now + days * 86_400);
+            found.reenroll(secret, keep_until);
+            found.forget_previous(now);
+            vault.record(Action::Reenrolled, account.as_str());
+            store.save(&vault)?;
+            Ok(())
+        }
+        Command::Add {
+            name,
+            issuer,
+            secret_stdin: _,
+            secret_file,
+            t0,
+            note,
+            icon,
+            site,
+            encoding,
+            legacy,
+        } => {
+            store::check_writable("add accounts")?;
+            let secret = match secret_file {
+                Some(file) => fs::read_to_string(file)?,
+                None => {
+                    let mut line = String::new();
+                    io::stdin().read_line(&mut line)?;
+                    line
+                }
+            };
+            let secret = secret.trim();
+            if secret.is_empty() {
+                return Err("the secret is empty".into());
+            }
+            let secret = if legacy {
+                secret.to_string()
+            } else {
+                encoding
+                    .to_base32(secret)
+                    .ok_or_else(|| format!("the secret is not {}", encoding.name()))?
+            };
+            let (mut vault, mut store) =
+                store::open(vault_path, cli.pass.as_deref(), cli.yubikey_slot, true)?;
+            vault.record(Action::Added, name.as_str());
+            vault.accounts.push(Account {
+                t0,
+                note,
+                icon,
+                site: site.trim().to_lowercase(),
+                legacy,
+                ..config.defaults.account(issuer, name, secret)
+            });
+            store.save(&vault)?;
+            hooks::account(
+                hooks::Event::Add,
+                vault.accounts.last().expect("just added"),
+            );
+            Ok(())
+        }
+        Command::Export {
+            age_recipients,
+            gpg_recipients,
+            insecure_plaintext,
+            format,
+            output,
+        } => {
+            store::check_writable("export accounts")?;
+            let (mut vault, mut store) =
+                store::open(vault_path, cli.pass.as_deref(), cli.yubikey_slot, false)?;
+            let (plain, skipped) = format.exporter().export(&vault.accounts)?;
+            info!(
+                ?format,
+                accounts = vault.accounts.len(),
+                left_out = skipped.len(),
+                "export"
+            );
+            for reason in skipped.iter() {
+                eprintln!("left out {}", reason);
+            }
+            let export = if insecure_plaintext {
+                eprintln!(
+                    "Warning: the export is not encrypted, it holds every secret in the clear"
+                );
+                plain
+            } else if gpg_recipients.is_empty() {
+                export::to_age(&plain, &age_recipients)?
+            } else {
+                export::to_gpg(&plain, &gpg_recipients)?
+            };
+            match output {
+                Some(file) => fs::write(file, export)?,
+                None => io::stdout().write_all(&export)?,
+            }
+            let count = vault.accounts.len() - skipped.len();
+            vault.record(Action::Exported, format!("{} accounts", count));
+            store.save(&vault)?;
+            Ok(())
+        }
+        Command::Import {
+            file,
+            age_identity,
+            gpg,
+            format,
+            plugin,
+            kdbx,
+            kdbx_keyfile,
+            on_conflict,
+            yes,
+        } => {
+            store::check_writable("import accounts")?;
+            let (imported, skipped) = if kdbx {
+                let password = rpassword::prompt_password("KeePass password: ")?;
+                kdbx::import(&file, &password, kdbx_keyfile.as_deref())?
+            } else {
+                let data = fs::read(&file)?;
+                let plain = match age_identity {
+                    Some(identity) => export::from_age(&data, &identity)?,
+                    None if gpg => export::from_gpg(&data)?,
+                    None => data,
+                };
+                match plugin {
+                    Some(name) => formats::plugin::find(&name)?.import(&plain)?,
+                    None => format.importer().import(&plain)?,
+                }
+            };
+            info!(file = %file.display(), read = imported.len(), unreadable = skipped.len(), "import");
+            for reason in skipped.iter() {
+                info!(%reason, "not importing");
+            }
+            let (mut vault, mut store) =
+                store::open(vault_path, cli.pass.as_deref(), cli.yubikey_slot, true)?;
+            let read = imported.len();
+            let imported = if yes || !io::stdin().is_terminal() {
+                for reason in skipped.iter() {
+                    eprintln!("skipped {}", reason);
+                }
+                imported
+            } else {
+                match preview::choose(&vault, imported, &skipped)? {
+                    Some(chosen) => chosen,
+                    None => {
+                        println!("nothing imported");
+                        return Ok(());
+                    }
+                }
+            };
+            // unreadable, or left out in the list
+            let skipped = skipped.len() + read - imported.len();
+            debug!(chosen = imported.len(), "accounts to merge");
+            let summary = merge::merge(&mut vault, imported, on_conflict)?;
+            let count = summary.added + summary.replaced + summary.renamed;
+            vault.record(
+                Action::Imported,
+                format!("{} accounts from {}", count, file.display()),
+            );
+            store.save(&vault)?;
+            hooks::synced(count);
+            println!("{}, skipped {}", summary, skipped);
+            Ok(())
+        }
+        Command::Doctor { no_unlock, fix } => {
+            let mut findings = vec![doctor::config(config_path)];
+            findings.push(doctor::clock(
+                ntp::clock_drift(ntp::SERVER),
+                cli.time_offset,
+                ntp::SERVER,
+                ntp::DRIFT_THRESHOLD,
+            ));
+            match &vault_path {
+                Some(path) => {
+                    if fix {
+                        findings.extend(doctor::fix_permissions(path));
+                    }
+                    findings.extend(doctor::vault_file(path));
+                    if !no_unlock && path.exists() {
+                        findings.push(match store::unlock(path, cli.yubikey_slot) {
+                            Ok((vault, _)) => doctor::Finding::ok(
+                                "vault",
+                                format!("opens, with {} accounts", vault.accounts.len()),
+                            ),
+                            Err(err) => doctor::Finding::problem(
+                                "vault",
+                                format!("cannot open it: {}", err),
+                            ),
+                        });
+                    }
+                }
+                None if cli.pass.is_some() => {}
+                None => findings.push(doctor::Finding::warning(
+                    "vault",
+                    "none given with --vault or in the config, the accounts are gone on exit",
+                )),
+            }
+            findings.push(doctor::clipboard(config.clipboard));
+            findings.extend(doctor::terminal());
+            std::process::exit(if doctor::report(&findings) { 0 } else { 1 });
+        }
+        Command::Sync { push, pull } => {
+            let remote = config
+                .sync
+                .ok_or("there is no WebDAV server to sync with, set [sync] in the config")?;
+            if cli.pass.is_some() {
+                return Err("only vault files are synced, not the password store".into());
+            }
+            let path = vault_path.ok_or("tell which vault to sync with --vault")?;
+            let direction = match (push, pull) {
+                (true, _) => webdav::Direction::Push,
+                (_, true) => webdav::Direction::Pull,
+                _ => webdav::Direction::Both,
+            };
+            let outcome = remote.sync(&path, direction)?;
+            println!("{}: {}", path.display(), outcome);
+            Ok(())
+        }
+        Command::Diff {
+            before,
+            after,
+            format,
+            age_identity,
+            gpg,
+        } => {
+            let read = |path: &Path| -> Result<Vec<Account>, Box<dyn Error>> {
+                let data = fs::read(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+                if Vault::is_vault(&data) {
+                    return Ok(store::unlock(path, cli.yubikey_slot)?.0.accounts);
+                }
+                let plain = match &age_identity {
+                    Some(identity) => export::from_age(&data, identity)?,
+                    None if gpg => export::from_gpg(&data)?,
+                    None => data,
+                };
+                let (accounts, skipped) = format
+                    .importer()
+                    .import(&plain)
+                    .map_err(|err| format!("{}: {}", path.display(), err))?;
+                for reason in skipped {
+                    eprintln!("{}: cannot read {}", path.display(), reason);
+                }
+                Ok(accounts)
+            };
+            let lines = diff::lines(&read(&before)?, &read(&after)?);
+            if lines.is_empty() {
+                println!("no differences");
+                return Ok(());
+            }
+            for line in lines {
+                println!("{}", line);
+            }
+            std::process::exit(1);
+        }
+    }
+}
+
+// the line `line` gives for the account `name`, once or each time the code changes
+fn print_status(
+    vault: &Vault,
+    name: &str,
+    time_offset: i64,
+    follow: bool,
+    line: &dyn Fn(&Account, u64) -> String,
+) -> Result<(), Box<dyn Error>> {
+    let Some(account) = vault.accounts.iter().find(|a| a.name == name) else {
+        return Err(format!("there is no account {}", name).into());
+    };
+    match status::run(account, time_offset, follow, line) {
+        // the reader went away
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+// a new passphrase from $`var`, or else typed twice after `prompt`
+fn new_passphrase(var: &str, prompt: &str) -> Result<String, Box<dyn Error>> {
+    let passphrase = match std::env::var(var) {
+        Ok(passphrase) => passphrase,
+        Err(_) => {
+            let passphrase = rpassword::prompt_password(format!("{}: ", prompt))?;
+            if rpassword::prompt_password("Repeat it: ")? != passphrase {
+                return Err("the passphrases differ".into());
+            }
+            passphrase
+        }
+    };
+    if passphrase.is_empty() {
+        return Err("the new passphrase is empty".into());
+    }
+    Ok(passphrase)
+}
+
+// the key of a vault put back together from shares
+fn to_key(bytes: &[u8]) -> Result<[u8; 32], Box<dyn Error>> {
+    Ok(bytes
+        .try_into()
+        .map_err(|_| "the shares are not of a vault key")?)
+}
@@ -0,0 +1,103 @@
+//! A compatibility mode for the older Windows consoles, those of conhost before
+//! Windows Terminal, which misbehave on the alternate screen, with colors beyond
+//! their 16 and with emoji. In it the UI is drawn on the normal screen, cleared
+//! on exit, account colors are brought down to the nearest of the 16, and icons
+//! are left out. It is picked on its own unless --compat or the config says.
+
+use ratatui::style::Color;
+use serde::Deserialize;
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// set from --compat or the config at startup
+static ON: AtomicBool = AtomicBool::new(false);
+
+/// Whether to use the compatibility mode
+#[derive(Clone, Copy, PartialEq, Debug, Default, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// On in the consoles known to need it
+    #[default]
+    Auto,
+    On,
+    Off,
+}
+
+/// Turn the compatibility mode on or off, `Auto` looks at the console it runs in
+pub fn init(mode: Mode) {
+    let on = match mode {
+        Mode::Auto => detect(),
+        Mode::On => true,
+        Mode::Off => false,
+    };
+    ON.store(on, Ordering::Relaxed);
+}
+
+/// Whether the compatibility mode is on
+pub fn on() -> bool {
+    ON.load(Ordering::Relaxed)
+}
+
+// a Windows console that is not one of the terminals that do without the compatibility mode:
+// Windows Terminal, ConEmu with its ANSI support, or one that names itself like VS Code,
+// WezTerm and mintty do
+fn detect() -> bool {
+    cfg!(windows)
+        && env::var_os("WT_SESSION").is_none()
+        && env::var_os("TERM_PROGRAM").is_none()
+        && env::var_os("TERM").is_none()
+        && env::var("ConEmuANSI").as_deref() != Ok("ON")
+}
+
+/// `color` as drawn: itself, or the nearest of the 16 console colors in compatibility mode
+pub fn color(color: Color) -> Color {
+    if !on() {
+        return color;
+    }
+    let rgb = match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(i) if i < 16 => return BASIC[i as usize].0,
+        Color::Indexed(i) => indexed_rgb(i),
+        color => return color,
+    };
+    let distance = |(r, g, b): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, rgb.0) + d(g, rgb.1) + d(b, rgb.2)
+    };
+    BASIC
+        .iter()
+        .min_by_key(|(_, basic)| distance(*basic))
+        .map(|(color, _)| *color)
+        .expect("there are basic colors")
+}
+
+// the 16 console colors as xterm draws them, in the order of their indexes
+const BASIC: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+// the color of index `i` of the 256, past the 16: a 6x6x6 cube, then 24 grays
+fn indexed_rgb(i: u8) -> (u8, u8, u8) {
+    if i >= 232 {
+        let gray = 8 + (i - 232) * 10;
+        return (gray, gray, gray);
+    }
+    let i = i - 16;
+    let level = |n: u8| if n == 0 { 0 } else { 55 + n * 40 };
+    (level(i / 36), level(i / 6 % 6), level(i % 6))
+}
@@ -0,0 +1,291 @@
+//! Settings read from `config.toml` at startup, for what would otherwise have to
+//! be given on the command line every time. Flags given anyway win.
+//!
+//! ```toml
+//! theme = "light"
+//! vault = "work"
+//! lock_after = 10
+//! clipboard = "wl-copy"
+//! clipboard_clear_after = 30
+//! notify_before_expiry = 5
+//! autotype = "xdotool"
+//! autotype_delay = 5
+//! backups = 20
+//! compat = "on"
+//! language = "fr"
+//! keep_previous_secret = 7
+//! hotkey = "ctrl+alt+t"
+//!
+//! [defaults]
+//! algorithm = "SHA256"
+//! digits = 8
+//!
+//! [kdf]
+//! memory = 131072
+//! iterations = 4
+//!
+//! [keys]
+//! quit = "Q"
+//!
+//! [hooks]
+//! post_add = "notify-send \"Added $CLI_TOTP_ACCOUNT\""
+//!
+//! [sync]
+//! url = "https://cloud.example.com/remote.php/dav/files/me/cli-totp/"
+//! user = "me"
+//! ```
+
+use crate::autotype;
+use crate::clipboard;
+use crate::compat;
+use crate::hooks::Hooks;
+use crate::paths;
+use crate::theme::ThemeName;
+use crate::webdav;
+use cli_totp::otp::{self, Algorithm};
+use cli_totp::{Account, KdfParams};
+use crossterm::event::KeyCode;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The actions of the Codes and Home tabs that can be given another key, with their usual key
+const ACTIONS: &[(&str, char)] = &[
+    ("quit", 'q'),
+    ("home", 'h'),
+    ("codes", 'c'),
+    ("add", 'a'),
+    ("edit", 'e'),
+    ("delete", 'd'),
+    ("clock", 't'),
+    ("mask", 'm'),
+    ("lock", 'L'),
+    ("reveal", 'r'),
+    ("secret", 'k'),
+    ("qr", 's'),
+    ("recovery", 'b'),
+    ("profiles", 'p'),
+    ("previous_vault", '['),
+    ("next_vault", ']'),
+    ("close_vault", 'W'),
+    ("passwd", 'P'),
+    ("window", 'w'),
+    ("sort", 'o'),
+    ("zoom", 'z'),
+    ("table", 'v'),
+    ("log", 'l'),
+    ("stats", 'S'),
+    ("autotype", 'T'),
+    ("archive", 'x'),
+    ("archive_view", 'X'),
+    ("restore", 'u'),
+    // also marks a recovery code used while they are shown
+    ("mark", ' '),
+    ("pin", 'f'),
+    ("sync", 'R'),
+];
+
+// the actions that change the vault, which a read-only one refuses; marking does only
+// while recovery codes are shown
+const CHANGING: &[&str] = &[
+    "add", "edit", "delete", "passwd", "archive", "restore", "pin", "sync",
+];
+
+/// Everything `config.toml` can set, each setting is optional
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Colors of the UI: dark, light or high-contrast
+    pub theme: ThemeName,
+    /// Vault profile or file used without --vault or --pass
+    pub vault: Option<String>,
+    /// Minutes without input before the UI locks, 0 never locks
+    pub lock_after: Option<u64>,
+    /// How codes are copied: auto, native, wl-copy, xclip, pbcopy or clip.exe
+    pub clipboard: clipboard::Backend,
+    /// Seconds after which a copied code is taken off the clipboard, if it is still there
+    pub clipboard_clear_after: Option<u64>,
+    /// Seconds before a copied code expires to tell so with a desktop notification, e.g. 5;
+    /// none when left out
+    pub notify_before_expiry: Option<u64>,
+    /// How codes are typed into the focused window: auto, wtype, xdotool, ydotool,
+    /// osascript or powershell
+    pub autotype: autotype::Backend,
+    /// Seconds to go to the window a code is typed into before it is, 3 when left out
+    pub autotype_delay: Option<u64>,
+    /// How many copies of the vault from before the last saves are kept, 0 keeps none
+    pub backups: Option<usize>,
+    /// List the most recently used accounts first
+    pub recent_first: bool,
+    /// Never change the vault, as with --read-only
+    pub read_only: bool,
+    /// Draw the UI for older Windows consoles, as with --compat
+    pub compat: compat::Mode,
+    /// Language of the UI, e.g. "fr", instead of the one of the locale
+    pub language: Option<String>,
+    /// Days the codes of the old secret are still shown after an account is enrolled again,
+    /// as with `edit --keep-previous`; not kept without it
+    pub keep_previous_secret: Option<u64>,
+    /// Keys copying a code anywhere on the desktop while the agent runs, as with
+    /// `agent --hotkey`
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pub hotkey: Option<String>,
+    /// Account whose code the hotkey copies, as with `agent --hotkey-account`
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pub hotkey_account: Option<String>,
+    /// Command picking the account on the hotkey, reading the names from stdin and printing
+    /// the one picked, e.g. "rofi -dmenu"
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pub hotkey_picker: Option<String>,
+    /// Settings of accounts added by hand
+    pub defaults: Defaults,
+    /// Argon2id parameters of new vaults, existing ones keep those they were made with
+    pub kdf: KdfParams,
+    /// Action name to the key that does it instead of its usual one
+    keys: HashMap<String, char>,
+    /// Shell commands run after accounts are added, deleted, copied or synced
+    pub hooks: Hooks,
+    /// WebDAV folder the vault files are synced to, none when left out
+    pub sync: Option<webdav::Remote>,
+}
+
+/// Settings new accounts get unless they come with their own, e.g. from an import
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Defaults {
+    pub algorithm: Algorithm,
+    pub digits: u32,
+    pub period: u64,
+}
+
+impl Default for Defaults {
+    fn default() -> Defaults {
+        Defaults {
+            algorithm: Algorithm::default(),
+            digits: otp::DIGITS,
+            period: otp::PERIOD,
+        }
+    }
+}
+
+impl Defaults {
+    /// An account with these settings
+    pub fn account(self, issuer: String, name: String, secret: String) -> Account {
+        Account {
+            algorithm: self.algorithm,
+            digits: self.digits,
+            period: self.period,
+            ..Account::new(issuer, name, secret)
+        }
+    }
+}
+
+/// Where the config is read from without --config, config.toml in the config directory
+/// of the platform, e.g. ~/.config/cli-totp
+pub fn default_path() -> PathBuf {
+    paths::config_dir().join("config.toml")
+}
+
+impl Config {
+    /// Read the config at `path`, the defaults if there is no such file
+    pub fn load(path: &Path) -> Result<Config, Box<dyn Error>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(err) => return Err(format!("cannot read {}: {}", path.display(), err).into()),
+        };
+        let config: Config =
+            toml::from_str(&text).map_err(|err| format!("in {}: {}", path.display(), err))?;
+        if !otp::valid_digits(config.defaults.digits) || config.defaults.period == 0 {
+            return Err(format!(
+                "in {}: digits must be 1 to 9 and period more than 0",
+                path.display()
+            )
+            .into());
+        }
+        if !config.kdf.is_safe() {
+            let min = KdfParams::MIN;
+            return Err(format!(
+                "in {}: the kdf needs at least {} KiB of memory, {} iterations and parallelism {}",
+                path.display(),
+                min.memory,
+                min.iterations,
+                min.parallelism
+            )
+            .into());
+        }
+        // a vault derived with more could not be opened
+        if !config.kdf.is_bounded() {
+            let max = KdfParams::MAX;
+            return Err(format!(
+                "in {}: the kdf takes at most {} KiB of memory, {} iterations and parallelism {}",
+                path.display(),
+                max.memory,
+                max.iterations,
+                max.parallelism
+            )
+            .into());
+        }
+        config
+            .keymap()
+            .map_err(|err| format!("in {}: {}", path.display(), err))?;
+        Ok(config)
+    }
+
+    /// The keys as set in the `[keys]` table
+    pub fn keymap(&self) -> Result<Keymap, String> {
+        let mut moved = vec![];
+        for (action, &key) in self.keys.iter() {
+            let usual = match ACTIONS.iter().find(|(name, _)| name == action) {
+                Some(&(_, usual)) => usual,
+                None => return Err(format!("there is no action {} to set a key for", action)),
+            };
+            if self.keys.values().filter(|&&other| other == key).count() > 1 {
+                return Err(format!("{} is set for more than one action", key));
+            }
+            moved.push((usual, key));
+        }
+        // the usual key of a moved action does nothing, unless another action moved there
+        let mut keys: HashMap<char, Option<char>> =
+            moved.iter().map(|&(usual, _)| (usual, None)).collect();
+        keys.extend(moved.iter().map(|&(usual, key)| (key, Some(usual))));
+        Ok(Keymap { keys })
+    }
+}
+
+/// Which action a key press means once the `[keys]` table is applied
+#[derive(Debug, Default)]
+pub struct Keymap {
+    // pressed key to the usual key of its action, None for keys that do nothing now
+    keys: HashMap<char, Option<char>>,
+}
+
+impl Keymap {
+    /// The usual key of the action `code` does, KeyCode::Null if it does nothing
+    pub fn translate(&self, code: KeyCode) -> KeyCode {
+        match code {
+            KeyCode::Char(key) => match self.keys.get(&key) {
+                Some(Some(usual)) => KeyCode::Char(*usual),
+                Some(None) => KeyCode::Null,
+                None => code,
+            },
+            _ => code,
+        }
+    }
+}
+
+/// The action whose usual key is `usual`, as [`Keymap::translate`] gives it
+pub fn action(usual: char) -> Option<&'static str> {
+    ACTIONS
+        .iter()
+        .find(|&&(_, key)| key == usual)
+        .map(|&(action, _)| action)
+}
+
+/// Whether the action whose usual key is `usual` changes the vault
+pub fn changes_vault(usual: char) -> bool {
+    action(usual).is_some_and(|action| CHANGING.contains(&action))
+}
@@ -0,0 +1,83 @@
+//! What changed between the accounts of two vaults or exports, to check that a
+//! sync or an import did what it should. Accounts are matched by name; what they
+//! hold is compared setting by setting, the secrets included but never shown, and
+//! when they were used is left out as it changes all the time.
+
+use cli_totp::Account;
+
+/// A line for each account added, removed or changed from `before` to `after`:
+/// `+ ISSUER NAME`, `- ISSUER NAME` or `~ NAME: what changed`
+pub fn lines(before: &[Account], after: &[Account]) -> Vec<String> {
+    let mut lines = vec![];
+    for old in before {
+        match after.iter().find(|new| new.name == old.name) {
+            None => lines.push(format!("- {}", describe(old))),
+            Some(new) => {
+                let changes = changes(old, new);
+                if !changes.is_empty() {
+                    lines.push(format!("~ {}: {}", old.name, changes.join(", ")));
+                }
+            }
+        }
+    }
+    for new in after {
+        if !before.iter().any(|old| old.name == new.name) {
+            lines.push(format!("+ {}", describe(new)));
+        }
+    }
+    lines
+}
+
+fn describe(account: &Account) -> String {
+    if account.issuer.is_empty() {
+        account.name.clone()
+    } else {
+        format!("{} {}", account.issuer, account.name)
+    }
+}
+
+// what differs between `old` and `new`, each as `setting old -> new`
+fn changes(old: &Account, new: &Account) -> Vec<String> {
+    let mut changes = vec![];
+    let mut compare = |setting: &str, old: String, new: String| {
+        if old != new {
+            changes.push(format!("{} {:?} -> {:?}", setting, old, new));
+        }
+    };
+    compare("issuer", old.issuer.clone(), new.issuer.clone());
+    compare(
+        "algorithm",
+        old.algorithm.name().to_string(),
+        new.algorithm.name().to_string(),
+    );
+    compare("digits", old.digits.to_string(), new.digits.to_string());
+    compare("period", old.period.to_string(), new.period.to_string());
+    compare("t0", old.t0.to_string(), new.t0.to_string());
+    compare("note", old.note.clone(), new.note.clone());
+    compare("icon", old.icon.clone(), new.icon.clone());
+    compare("color", old.color.clone(), new.color.clone());
+    compare(
+        "archived",
+        old.archived.to_string(),
+        new.archived.to_string(),
+    );
+    compare("pinned", old.pinned.to_string(), new.pinned.to_string());
+    compare("legacy", old.legacy.to_string(), new.legacy.to_string());
+    let recovery = |account: &Account| {
+        let used = account
+            .recovery_codes
+            .iter()
+            .filter(|code| code.used)
+            .count();
+        format!("{} ({} used)", account.recovery_codes.len(), used)
+    };
+    compare("recovery codes", recovery(old), recovery(new));
+    if old.secret != new.secret {
+        changes.push("secret".to_string());
+    }
+    let previous = |account: &Account| account.previous.as_ref().map(|p| p.secret.clone());
+    if previous(old) != previous(new) {
+        changes.push("previous secret".to_string());
+    }
+    changes
+}
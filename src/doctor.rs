@@ -0,0 +1,318 @@
+//! `doctor`: the checks behind most questions about codes being rejected or the
+//! app misbehaving, the clock, the vault file, the clipboard, the terminal and the
+//! config, each telling what to do about what it found.
+
+use crate::clipboard;
+use crate::compat;
+use crate::config::Config;
+use crate::permissions;
+use cli_totp::vault::VERSION;
+use cli_totp::Vault;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+
+// the smallest terminal the Codes tab fits in
+const MIN_COLUMNS: u16 = 60;
+const MIN_ROWS: u16 = 15;
+
+/// How bad a finding is
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Level {
+    Ok,
+    /// Works, but may not as expected
+    Warning,
+    /// Does not work
+    Problem,
+}
+
+/// What a check found, with what to do about it unless all is well
+pub struct Finding {
+    pub check: &'static str,
+    pub level: Level,
+    pub message: String,
+}
+
+impl Finding {
+    pub fn ok(check: &'static str, message: impl Into<String>) -> Finding {
+        Finding {
+            check,
+            level: Level::Ok,
+            message: message.into(),
+        }
+    }
+
+    pub fn warning(check: &'static str, message: impl Into<String>) -> Finding {
+        Finding {
+            check,
+            level: Level::Warning,
+            message: message.into(),
+        }
+    }
+
+    pub fn problem(check: &'static str, message: impl Into<String>) -> Finding {
+        Finding {
+            check,
+            level: Level::Problem,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let level = match self.level {
+            Level::Ok => "ok",
+            Level::Warning => "warning",
+            Level::Problem => "PROBLEM",
+        };
+        write!(f, "{:<8} {:<10} {}", level, self.check, self.message)
+    }
+}
+
+/// Print `findings`, one a line, and tell whether none of them is a problem
+pub fn report(findings: &[Finding]) -> bool {
+    for finding in findings {
+        println!("{}", finding);
+    }
+    let count = |level| findings.iter().filter(|f| f.level == level).count();
+    println!(
+        "{} problems, {} warnings",
+        count(Level::Problem),
+        count(Level::Warning)
+    );
+    count(Level::Problem) == 0
+}
+
+/// Whether the config at `path` reads, as it must for the app to start
+pub fn config(path: &Path) -> Finding {
+    match Config::load(path) {
+        Ok(_) if !path.exists() => Finding::ok(
+            "config",
+            format!("no config at {}, using the defaults", path.display()),
+        ),
+        Ok(_) => Finding::ok("config", format!("{} is valid", path.display())),
+        Err(err) => Finding::problem("config", format!("{}; fix it or move it away", err)),
+    }
+}
+
+/// How far the clock is off once `offset` is added, from the `drift` measured against
+/// `server`; codes are rejected once it is off by about a period
+pub fn clock(drift: io::Result<f64>, offset: i64, server: &str, threshold: f64) -> Finding {
+    match drift {
+        Ok(drift) if (drift + offset as f64).abs() > threshold => Finding::problem(
+            "clock",
+            format!(
+                "off by {:+.1}s from {}, codes may be rejected; have the system clock synced \
+                 with NTP, or pass --time-offset {}",
+                drift + offset as f64,
+                server,
+                offset - drift.round() as i64
+            ),
+        ),
+        Ok(drift) => Finding::ok(
+            "clock",
+            format!("off by {:+.1}s from {}", drift + offset as f64, server),
+        ),
+        Err(err) => Finding::warning(
+            "clock",
+            format!(
+                "cannot ask {}: {}; check the clock by hand if codes are rejected",
+                server, err
+            ),
+        ),
+    }
+}
+
+/// Whether the vault file at `path` is there and is one, and who else can read it
+pub fn vault_file(path: &Path) -> Vec<Finding> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return vec![Finding::warning(
+                "vault",
+                format!(
+                    "there is no vault at {}, it is made the first time the app opens it",
+                    path.display()
+                ),
+            )]
+        }
+        Err(err) => {
+            return vec![Finding::problem(
+                "vault",
+                format!("cannot read {}: {}", path.display(), err),
+            )]
+        }
+    };
+    let mut findings = vec![];
+    match Vault::version_of(&data) {
+        Some(version) if version > VERSION => findings.push(Finding::problem(
+            "vault",
+            format!(
+                "{} is of version {}, made by a newer cli-TOTP than this one of version {}; \
+                 update it",
+                path.display(),
+                version,
+                VERSION
+            ),
+        )),
+        Some(version) if version < VERSION => findings.push(Finding::warning(
+            "vault",
+            format!(
+                "{} is of version {}, it is upgraded to {} the next time it is opened, \
+                 keeping a copy in the backups",
+                path.display(),
+                version,
+                VERSION
+            ),
+        )),
+        Some(version) => findings.push(Finding::ok(
+            "vault",
+            format!(
+                "{} is a vault of version {} and {} bytes",
+                path.display(),
+                version,
+                data.len()
+            ),
+        )),
+        None => findings.push(Finding::problem(
+            "vault",
+            format!(
+                "{} is not a vault, or is damaged; copy one back from the backups next to it",
+                path.display()
+            ),
+        )),
+    }
+    findings.extend(permissions(path));
+    findings
+}
+
+// whether others than the owner can get at the vault file at `path` or its directory
+fn permissions(path: &Path) -> Vec<Finding> {
+    let exposed = permissions::exposed(path);
+    if exposed.is_empty() {
+        return vec![Finding::ok(
+            "permission",
+            "only you can read the vault and its directory",
+        )];
+    }
+    exposed
+        .iter()
+        .map(|exposed| {
+            Finding::warning(
+                "permission",
+                format!(
+                    "{} is {:o}, others than you can read it; run doctor --fix, or chmod {:o} {}",
+                    exposed.path.display(),
+                    exposed.mode,
+                    exposed.wanted,
+                    exposed.path.display()
+                ),
+            )
+        })
+        .collect()
+}
+
+/// Make the vault file at `path` and its directory readable by their owner only
+pub fn fix_permissions(path: &Path) -> Vec<Finding> {
+    match permissions::fix(path) {
+        Ok(fixed) => fixed
+            .iter()
+            .map(|fixed| {
+                Finding::ok(
+                    "permission",
+                    format!(
+                        "{} was {:o}, made it {:o}",
+                        fixed.path.display(),
+                        fixed.mode,
+                        fixed.wanted
+                    ),
+                )
+            })
+            .collect(),
+        Err(err) => vec![Finding::problem(
+            "permission",
+            format!("cannot restrict who reads {}: {}", path.display(), err),
+        )],
+    }
+}
+
+/// Whether codes can be copied with `backend`
+pub fn clipboard(backend: clipboard::Backend) -> Finding {
+    match clipboard::check(backend) {
+        Ok(used) => Finding::ok("clipboard", format!("copying with {}", used)),
+        Err(err) => Finding::problem(
+            "clipboard",
+            format!(
+                "{}; install wl-copy, xclip or pbcopy, or set clipboard in the config",
+                err
+            ),
+        ),
+    }
+}
+
+/// What the terminal the app runs in can do, for the full screen UI
+pub fn terminal() -> Vec<Finding> {
+    let mut findings = vec![];
+    if !io::stdout().is_terminal() || !io::stdin().is_terminal() {
+        findings.push(Finding::warning(
+            "terminal",
+            "not run in a terminal, the UI needs one; the other commands work without",
+        ));
+        return findings;
+    }
+    match env::var("TERM").as_deref() {
+        Ok("dumb") => findings.push(Finding::warning(
+            "terminal",
+            "$TERM is dumb, the UI cannot be drawn; use --plain",
+        )),
+        Err(_) if cfg!(unix) => findings.push(Finding::warning(
+            "terminal",
+            "$TERM is not set, the UI may be drawn wrong",
+        )),
+        term => findings.push(Finding::ok(
+            "terminal",
+            format!("$TERM is {}", term.unwrap_or("not set")),
+        )),
+    }
+    match crossterm::terminal::size() {
+        Ok((columns, rows)) if columns < MIN_COLUMNS || rows < MIN_ROWS => {
+            findings.push(Finding::warning(
+                "terminal",
+                format!(
+                    "{}x{} is small, the UI needs at least {}x{} to show everything",
+                    columns, rows, MIN_COLUMNS, MIN_ROWS
+                ),
+            ))
+        }
+        Ok((columns, rows)) => {
+            findings.push(Finding::ok("terminal", format!("{}x{}", columns, rows)))
+        }
+        Err(err) => findings.push(Finding::warning(
+            "terminal",
+            format!("cannot tell its size: {}", err),
+        )),
+    }
+    let truecolor = env::var("COLORTERM").is_ok_and(|c| c == "truecolor" || c == "24bit");
+    if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        findings.push(Finding::ok(
+            "terminal",
+            "$NO_COLOR is set, drawn without colors",
+        ));
+    } else if !truecolor {
+        findings.push(Finding::warning(
+            "terminal",
+            "$COLORTERM does not tell of 24-bit colors, account colors may look off; \
+             try --compat on or --no-color",
+        ));
+    }
+    if compat::on() {
+        findings.push(Finding::ok(
+            "terminal",
+            "drawn in the compatibility mode of older consoles",
+        ));
+    }
+    findings
+}
@@ -0,0 +1,79 @@
+//! Export files, the accounts of a vault encrypted for somewhere else than this
+//! machine. The accounts are written down in one of the formats, then encrypted
+//! to age recipients or to GPG keys.
+
+use age::armor::{ArmoredReader, ArmoredWriter, Format as Armor};
+use age::{Decryptor, Encryptor, Identity, IdentityFile, Recipient};
+use std::error::Error;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::thread;
+
+/// `plain` encrypted to every one of the age `recipients` (age1...), ASCII armored
+pub fn to_age(plain: &[u8], recipients: &[String]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let recipients = recipients
+        .iter()
+        .map(|recipient| {
+            age::x25519::Recipient::from_str(recipient)
+                .map_err(|err| format!("invalid age recipient {}: {}", recipient, err))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let encryptor = Encryptor::with_recipients(recipients.iter().map(|r| r as &dyn Recipient))?;
+
+    let mut output = vec![];
+    let armor = ArmoredWriter::wrap_output(&mut output, Armor::AsciiArmor)?;
+    let mut writer = encryptor.wrap_output(armor)?;
+    writer.write_all(plain)?;
+    writer.finish()?.finish()?;
+    Ok(output)
+}
+
+/// An age encrypted export, armored or not, decrypted with the identities in the
+/// file `identity`
+pub fn from_age(data: &[u8], identity: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+    let identities =
+        IdentityFile::from_file(identity.to_string_lossy().into_owned())?.into_identities()?;
+    let decryptor = Decryptor::new(ArmoredReader::new(data))?;
+    let mut reader = decryptor.decrypt(identities.iter().map(|i| i.as_ref() as &dyn Identity))?;
+    let mut plain = vec![];
+    reader.read_to_end(&mut plain)?;
+    Ok(plain)
+}
+
+/// `plain` encrypted by gpg to every one of the keys in `recipients`, ASCII armored
+pub fn to_gpg(plain: &[u8], recipients: &[String]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut args = vec!["--batch", "--yes", "--armor", "--encrypt"];
+    for recipient in recipients {
+        args.extend(["--recipient", recipient.as_str()]);
+    }
+    gpg(&args, plain)
+}
+
+/// A gpg encrypted export, decrypted by gpg with whichever of the user's keys it
+/// was encrypted to
+pub fn from_gpg(data: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    gpg(&["--quiet", "--decrypt"], data)
+}
+
+// run gpg with `args`, feeding it `input` and returning what it prints;
+// its own messages go straight to the terminal
+fn gpg(args: &[&str], input: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut child = Command::new("gpg")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("cannot run gpg: {}", err))?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    // written from another thread so a large output cannot block gpg while we write
+    let output = thread::scope(|scope| {
+        scope.spawn(move || stdin.write_all(input));
+        child.wait_with_output()
+    })?;
+    if !output.status.success() {
+        return Err(format!("gpg failed with {}", output.status).into());
+    }
+    Ok(output.stdout)
+}
@@ -0,0 +1,94 @@
+//! The formats accounts are exported to and imported from. Each one is an
+//! `Exporter` and an `Importer`, picked with --format, so `export` and `import`
+//! read and write them all the same way. Formats this app does not know can be
+//! imported with plugins, see [`plugin`].
+
+mod aegis;
+mod andotp;
+mod csv;
+mod json;
+mod migration;
+pub mod plugin;
+mod uri;
+
+use cli_totp::Account;
+use std::error::Error;
+
+/// Writes accounts down in one format
+pub trait Exporter {
+    /// `accounts` written down, not encrypted yet, and why those the format cannot
+    /// hold were left out
+    fn export(&self, accounts: &[Account]) -> Result<(Vec<u8>, Vec<String>), Box<dyn Error>>;
+}
+
+/// Reads accounts written down in one format
+pub trait Importer {
+    /// The accounts in `plain`, already decrypted, and why the entries that could not
+    /// be read were skipped
+    fn import(&self, plain: &[u8]) -> Result<(Vec<Account>, Vec<String>), Box<dyn Error>>;
+}
+
+/// How the accounts are written down in an export
+#[derive(Clone, Copy, PartialEq, Debug, clap::ValueEnum)]
+pub enum Format {
+    /// The vault's own JSON, everything an account has
+    Json,
+    /// issuer,name,secret,algorithm,digits,period with a header, for other tools
+    Csv,
+    /// One otpauth:// URI per line, what authenticator apps scan
+    #[value(alias = "uri-list")]
+    Uri,
+    /// The unencrypted JSON export of Aegis
+    Aegis,
+    /// The unencrypted JSON backup of andOTP
+    Andotp,
+    /// otpauth-migration:// URIs, what Google Authenticator transfers accounts with
+    Migration,
+}
+
+impl Format {
+    /// What writes accounts down in this format
+    pub fn exporter(self) -> &'static dyn Exporter {
+        match self {
+            Format::Json => &json::Json,
+            Format::Csv => &csv::Csv,
+            Format::Uri => &uri::Uri,
+            Format::Aegis => &aegis::Aegis,
+            Format::Andotp => &andotp::AndOtp,
+            Format::Migration => &migration::Migration,
+        }
+    }
+
+    /// What reads accounts written down in this format
+    pub fn importer(self) -> &'static dyn Importer {
+        match self {
+            Format::Json => &json::Json,
+            Format::Csv => &csv::Csv,
+            Format::Uri => &uri::Uri,
+            Format::Aegis => &aegis::Aegis,
+            Format::Andotp => &andotp::AndOtp,
+            Format::Migration => &migration::Migration,
+        }
+    }
+}
+
+// the accounts other authenticators give the same codes for, and why the others are left out
+fn portable(accounts: &[Account]) -> (Vec<&Account>, Vec<String>) {
+    let (mut portable, mut skipped) = (vec![], vec![]);
+    for account in accounts {
+        if account.legacy {
+            skipped.push(format!(
+                "{}: it makes the codes of the first versions of this app",
+                account.name
+            ));
+        } else if account.t0 != 0 {
+            skipped.push(format!(
+                "{}: it counts time steps from T0, other authenticators from the epoch",
+                account.name
+            ));
+        } else {
+            portable.push(account);
+        }
+    }
+    (portable, skipped)
+}
@@ -0,0 +1,180 @@
+//! The JSON export of Aegis, without its encryption: Aegis exports encrypted
+//! with a password are not read, exported from Aegis in the clear they can be
+//! encrypted with age or gpg instead.
+//!
+//! ```text
+//! {"version": 1, "header": {"slots": null, "params": null},
+//!  "db": {"version": 2, "entries": [{"type": "totp", "uuid": "...", "name": "me",
+//!   "issuer": "GitHub", "note": "", "info": {"secret": "BASE32", "algo": "SHA1",
+//!   "digits": 6, "period": 30}}]}}
+//! ```
+
+use super::{portable, Exporter, Importer};
+use cli_totp::otp::{self, Algorithm};
+use cli_totp::Account;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::error::Error;
+
+pub struct Aegis;
+
+#[derive(Serialize, Deserialize)]
+struct Export {
+    version: u32,
+    header: Header,
+    // the entries, or the encrypted entries as Base64 text
+    db: Value,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    slots: Option<Value>,
+    params: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Db {
+    version: u32,
+    entries: Vec<Entry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    uuid: String,
+    name: String,
+    #[serde(default)]
+    issuer: String,
+    #[serde(default)]
+    note: String,
+    info: Info,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Info {
+    secret: String,
+    #[serde(default)]
+    algo: String,
+    #[serde(default = "default_digits")]
+    digits: u32,
+    #[serde(default = "default_period")]
+    period: u64,
+}
+
+fn default_digits() -> u32 {
+    otp::DIGITS
+}
+
+fn default_period() -> u64 {
+    otp::PERIOD
+}
+
+impl Exporter for Aegis {
+    fn export(&self, accounts: &[Account]) -> Result<(Vec<u8>, Vec<String>), Box<dyn Error>> {
+        let (accounts, skipped) = portable(accounts);
+        let mut entries = vec![];
+        for account in accounts {
+            entries.push(Entry {
+                kind: "totp".to_string(),
+                uuid: uuid()?,
+                name: account.name.clone(),
+                issuer: account.issuer.clone(),
+                note: account.note.clone(),
+                info: Info {
+                    secret: account.secret.replace([' ', '-', '='], "").to_uppercase(),
+                    algo: account.algorithm.name().to_string(),
+                    digits: account.digits,
+                    period: account.period,
+                },
+            });
+        }
+        let export = Export {
+            version: 1,
+            header: Header {
+                slots: None,
+                params: None,
+            },
+            db: serde_json::to_value(Db {
+                version: 2,
+                entries,
+            })?,
+        };
+        Ok((serde_json::to_vec_pretty(&export)?, skipped))
+    }
+}
+
+impl Importer for Aegis {
+    fn import(&self, plain: &[u8]) -> Result<(Vec<Account>, Vec<String>), Box<dyn Error>> {
+        let export: Export =
+            serde_json::from_slice(plain).map_err(|err| format!("not an Aegis export: {}", err))?;
+        if export.header.slots.is_some() || export.db.is_string() {
+            return Err(
+                "the Aegis export is encrypted, export it from Aegis without encryption".into(),
+            );
+        }
+        let db: Db = serde_json::from_value(export.db)
+            .map_err(|err| format!("not an Aegis export: {}", err))?;
+        let (mut accounts, mut skipped) = (vec![], vec![]);
+        for entry in db.entries {
+            if entry.kind != "totp" {
+                skipped.push(format!(
+                    "{}: {} codes are not supported",
+                    entry.name, entry.kind
+                ));
+                continue;
+            }
+            let algorithm = match entry.info.algo.as_str() {
+                "" => Algorithm::default(),
+                algo => match Algorithm::from_name(algo) {
+                    Some(algorithm) => algorithm,
+                    None => {
+                        skipped.push(format!("{}: unknown algorithm {}", entry.name, algo));
+                        continue;
+                    }
+                },
+            };
+            if otp::decode_base32(&entry.info.secret).is_none() {
+                skipped.push(format!("{}: the secret is not Base32", entry.name));
+                continue;
+            }
+            if !otp::valid_digits(entry.info.digits) {
+                skipped.push(format!(
+                    "{}: invalid digits {}",
+                    entry.name, entry.info.digits
+                ));
+                continue;
+            }
+            accounts.push(Account {
+                algorithm,
+                digits: entry.info.digits,
+                period: entry.info.period.max(1),
+                note: entry.note,
+                ..Account::new(entry.issuer, entry.name, entry.info.secret)
+            });
+        }
+        Ok((accounts, skipped))
+    }
+}
+
+// a random UUID, what Aegis tells its entries apart with
+fn uuid() -> Result<String, Box<dyn Error>> {
+    let mut bytes = [0u8; 16];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| "no random numbers available")?;
+    // version 4, variant 1
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    Ok(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    ))
+}
@@ -0,0 +1,123 @@
+//! The JSON backup of andOTP, without its encryption: backups encrypted with a
+//! password or with OpenPGP are not read.
+//!
+//! ```text
+//! [{"secret": "BASE32", "issuer": "GitHub", "label": "me", "digits": 6, "type": "TOTP",
+//!   "algorithm": "SHA1", "thumbnail": "Default", "last_used": 0, "used_frequency": 0,
+//!   "period": 30, "tags": []}]
+//! ```
+
+use super::{portable, Exporter, Importer};
+use cli_totp::otp::{self, Algorithm};
+use cli_totp::Account;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+pub struct AndOtp;
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    secret: String,
+    #[serde(default)]
+    issuer: String,
+    label: String,
+    #[serde(default = "default_digits")]
+    digits: u32,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    algorithm: String,
+    #[serde(default = "default_thumbnail")]
+    thumbnail: String,
+    /// Unix time in milliseconds
+    #[serde(default)]
+    last_used: u64,
+    #[serde(default)]
+    used_frequency: u64,
+    #[serde(default = "default_period")]
+    period: u64,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+fn default_digits() -> u32 {
+    otp::DIGITS
+}
+
+fn default_period() -> u64 {
+    otp::PERIOD
+}
+
+fn default_thumbnail() -> String {
+    "Default".to_string()
+}
+
+impl Exporter for AndOtp {
+    fn export(&self, accounts: &[Account]) -> Result<(Vec<u8>, Vec<String>), Box<dyn Error>> {
+        let (accounts, skipped) = portable(accounts);
+        let entries: Vec<Entry> = accounts
+            .into_iter()
+            .map(|account| Entry {
+                secret: account.secret.replace([' ', '-', '='], "").to_uppercase(),
+                issuer: account.issuer.clone(),
+                label: account.name.clone(),
+                digits: account.digits,
+                kind: "TOTP".to_string(),
+                algorithm: account.algorithm.name().to_string(),
+                thumbnail: default_thumbnail(),
+                last_used: account.last_used.unwrap_or(0) * 1000,
+                used_frequency: 0,
+                period: account.period,
+                tags: vec![],
+            })
+            .collect();
+        Ok((serde_json::to_vec_pretty(&entries)?, skipped))
+    }
+}
+
+impl Importer for AndOtp {
+    fn import(&self, plain: &[u8]) -> Result<(Vec<Account>, Vec<String>), Box<dyn Error>> {
+        let entries: Vec<Entry> = serde_json::from_slice(plain)
+            .map_err(|err| format!("not an unencrypted andOTP backup: {}", err))?;
+        let (mut accounts, mut skipped) = (vec![], vec![]);
+        for entry in entries {
+            // backups of older versions have the issuer in the label
+            let (issuer, name) = match entry.label.split_once(':') {
+                Some((issuer, name)) if entry.issuer.is_empty() => {
+                    (issuer.trim().to_string(), name.trim().to_string())
+                }
+                _ => (entry.issuer, entry.label),
+            };
+            if !entry.kind.eq_ignore_ascii_case("totp") {
+                skipped.push(format!("{}: {} codes are not supported", name, entry.kind));
+                continue;
+            }
+            let algorithm = match entry.algorithm.as_str() {
+                "" => Algorithm::default(),
+                algorithm => match Algorithm::from_name(algorithm) {
+                    Some(algorithm) => algorithm,
+                    None => {
+                        skipped.push(format!("{}: unknown algorithm {}", name, algorithm));
+                        continue;
+                    }
+                },
+            };
+            if otp::decode_base32(&entry.secret).is_none() {
+                skipped.push(format!("{}: the secret is not Base32", name));
+                continue;
+            }
+            if !otp::valid_digits(entry.digits) {
+                skipped.push(format!("{}: invalid digits {}", name, entry.digits));
+                continue;
+            }
+            accounts.push(Account {
+                algorithm,
+                digits: entry.digits,
+                period: entry.period.max(1),
+                last_used: (entry.last_used > 0).then_some(entry.last_used / 1000),
+                ..Account::new(issuer, name, entry.secret)
+            });
+        }
+        Ok((accounts, skipped))
+    }
+}
@@ -0,0 +1,129 @@
+//! CSV with a header, for other tools and spreadsheets. Reading needs at least
+//! the name and secret columns; issuer, algorithm, digits, period and the encoding
+//! of the secret, base32, hex or raw, are optional.
+
+use super::{portable, Exporter, Importer};
+use cli_totp::otp::{self, Algorithm, SecretEncoding};
+use cli_totp::Account;
+use serde::Serialize;
+use std::error::Error;
+
+pub struct Csv;
+
+// a row of a CSV export
+#[derive(Serialize)]
+struct Row<'a> {
+    issuer: &'a str,
+    name: &'a str,
+    secret: &'a str,
+    algorithm: Algorithm,
+    digits: u32,
+    period: u64,
+}
+
+impl Exporter for Csv {
+    fn export(&self, accounts: &[Account]) -> Result<(Vec<u8>, Vec<String>), Box<dyn Error>> {
+        let (accounts, skipped) = portable(accounts);
+        let mut writer = csv::Writer::from_writer(vec![]);
+        for account in accounts {
+            writer.serialize(Row {
+                issuer: &account.issuer,
+                name: &account.name,
+                secret: &account.secret,
+                algorithm: account.algorithm,
+                digits: account.digits,
+                period: account.period,
+            })?;
+        }
+        Ok((writer.into_inner().map_err(|err| err.to_string())?, skipped))
+    }
+}
+
+impl Importer for Csv {
+    fn import(&self, plain: &[u8]) -> Result<(Vec<Account>, Vec<String>), Box<dyn Error>> {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            // a short row leaves its last columns empty
+            .flexible(true)
+            .from_reader(plain);
+        let headers = reader.headers()?.clone();
+        let column = |name: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(name));
+        let (name, secret) = match (column("name"), column("secret")) {
+            (Some(name), Some(secret)) => (name, secret),
+            _ => return Err("the CSV needs a header with name and secret columns".into()),
+        };
+        let (issuer, algorithm) = (column("issuer"), column("algorithm"));
+        let (digits, period) = (column("digits"), column("period"));
+        let encoding = column("encoding");
+
+        let (mut accounts, mut skipped) = (vec![], vec![]);
+        for (row, record) in reader.records().enumerate() {
+            // the header is line 1
+            let line = row + 2;
+            let record = match record {
+                Ok(record) => record,
+                Err(err) => {
+                    skipped.push(format!("line {}: {}", line, err));
+                    continue;
+                }
+            };
+            let field =
+                |column: Option<usize>| column.and_then(|c| record.get(c)).unwrap_or_default();
+            match csv_account(
+                field(issuer),
+                field(Some(name)),
+                field(Some(secret)),
+                field(algorithm),
+                field(digits),
+                field(period),
+                field(encoding),
+            ) {
+                Ok(account) => accounts.push(account),
+                Err(reason) => skipped.push(format!("line {}: {}", line, reason)),
+            }
+        }
+        Ok((accounts, skipped))
+    }
+}
+
+// the account of a CSV row, empty optional fields take the usual values; the secret is
+// kept as Base32 whatever its encoding
+fn csv_account(
+    issuer: &str,
+    name: &str,
+    secret: &str,
+    algorithm: &str,
+    digits: &str,
+    period: &str,
+    encoding: &str,
+) -> Result<Account, String> {
+    if name.is_empty() {
+        return Err("no name".to_string());
+    }
+    let encoding = match encoding {
+        "" => SecretEncoding::default(),
+        encoding => encoding.parse()?,
+    };
+    let secret = match encoding.to_base32(secret) {
+        Some(secret) if !secret.is_empty() => secret,
+        _ => return Err(format!("the secret of {} is not {}", name, encoding.name())),
+    };
+    let mut account = Account::new(issuer.to_string(), name.to_string(), secret);
+    if !algorithm.is_empty() {
+        account.algorithm = Algorithm::from_name(algorithm)
+            .ok_or_else(|| format!("unknown algorithm {}", algorithm))?;
+    }
+    if !digits.is_empty() {
+        account.digits = match digits.parse() {
+            Ok(digits) if otp::valid_digits(digits) => digits,
+            _ => return Err(format!("invalid digits {}", digits)),
+        };
+    }
+    if !period.is_empty() {
+        account.period = match period.parse() {
+            Ok(period) if period > 0 => period,
+            _ => return Err(format!("invalid period {}", period)),
+        };
+    }
+    Ok(account)
+}
@@ -0,0 +1,30 @@
+//! The vault's own JSON, the only format that keeps everything an account has.
+
+use super::{Exporter, Importer};
+use cli_totp::{otp, Account, Vault};
+use std::error::Error;
+
+pub struct Json;
+
+impl Exporter for Json {
+    fn export(&self, accounts: &[Account]) -> Result<(Vec<u8>, Vec<String>), Box<dyn Error>> {
+        // the log stays in the vault
+        let json = serde_json::to_vec(&serde_json::json!({ "accounts": accounts }))?;
+        Ok((json, vec![]))
+    }
+}
+
+impl Importer for Json {
+    fn import(&self, plain: &[u8]) -> Result<(Vec<Account>, Vec<String>), Box<dyn Error>> {
+        let (accounts, invalid): (Vec<Account>, Vec<Account>) =
+            serde_json::from_slice::<Vault>(plain)?
+                .accounts
+                .into_iter()
+                .partition(|account| otp::valid_digits(account.digits));
+        let skipped = invalid
+            .iter()
+            .map(|account| format!("{}: invalid digits {}", account.name, account.digits))
+            .collect();
+        Ok((accounts, skipped))
+    }
+}
@@ -0,0 +1,246 @@
+//! The otpauth-migration:// URIs Google Authenticator transfers accounts with,
+//! one per line. The data parameter is a Base64 protobuf `MigrationPayload`
+//! whose `otp_parameters` are the accounts:
+//!
+//! ```text
+//! message OtpParameters {
+//!   bytes secret = 1; string name = 2; string issuer = 3;
+//!   Algorithm algorithm = 4;  // 1 SHA1, 2 SHA256, 3 SHA512, 4 MD5
+//!   DigitCount digits = 5;    // 1 six, 2 eight
+//!   OtpType type = 6;         // 1 HOTP, 2 TOTP
+//! }
+//! ```
+//!
+//! Google Authenticator only has codes of 30 seconds.
+
+use super::{portable, Exporter, Importer};
+use cli_totp::otp::{self, Algorithm};
+use cli_totp::{otpauth, Account};
+use std::error::Error;
+
+pub struct Migration;
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+impl Exporter for Migration {
+    fn export(&self, accounts: &[Account]) -> Result<(Vec<u8>, Vec<String>), Box<dyn Error>> {
+        let (accounts, mut skipped) = portable(accounts);
+        let mut payload = vec![];
+        for account in accounts {
+            let digits = match account.digits {
+                6 => 1,
+                8 => 2,
+                digits => {
+                    skipped.push(format!(
+                        "{}: {} digits cannot be moved",
+                        account.name, digits
+                    ));
+                    continue;
+                }
+            };
+            if account.period != otp::PERIOD {
+                skipped.push(format!(
+                    "{}: codes of {}s cannot be moved",
+                    account.name, account.period
+                ));
+                continue;
+            }
+            let Some(secret) = otp::decode_base32(&account.secret) else {
+                skipped.push(format!("{}: the secret is not Base32", account.name));
+                continue;
+            };
+            let algorithm = match account.algorithm {
+                Algorithm::Sha1 => 1,
+                Algorithm::Sha256 => 2,
+                Algorithm::Sha512 => 3,
+            };
+            let mut parameters = vec![];
+            put_bytes(&mut parameters, 1, &secret);
+            put_bytes(&mut parameters, 2, account.name.as_bytes());
+            put_bytes(&mut parameters, 3, account.issuer.as_bytes());
+            put_varint_field(&mut parameters, 4, algorithm);
+            put_varint_field(&mut parameters, 5, digits);
+            put_varint_field(&mut parameters, 6, 2);
+            put_bytes(&mut payload, 1, &parameters);
+        }
+        // version 1 of the payload, the only batch of one
+        put_varint_field(&mut payload, 2, 1);
+        put_varint_field(&mut payload, 3, 1);
+        put_varint_field(&mut payload, 4, 0);
+        let uri = format!(
+            "otpauth-migration://offline?data={}\n",
+            otpauth::percent_encode(&encode_base64(&payload))
+        );
+        Ok((uri.into_bytes(), skipped))
+    }
+}
+
+impl Importer for Migration {
+    fn import(&self, plain: &[u8]) -> Result<(Vec<Account>, Vec<String>), Box<dyn Error>> {
+        let (mut accounts, mut skipped) = (vec![], vec![]);
+        for (row, line) in String::from_utf8_lossy(plain).lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some(payload) = payload(line) else {
+                skipped.push(format!("line {}: not an otpauth-migration:// URI", row + 1));
+                continue;
+            };
+            let Some(fields) = fields(&payload) else {
+                skipped.push(format!("line {}: the data is damaged", row + 1));
+                continue;
+            };
+            for (field, value) in fields {
+                if let (1, Value::Bytes(parameters)) = (field, value) {
+                    match account(parameters) {
+                        Ok(account) => accounts.push(account),
+                        Err(reason) => skipped.push(format!("line {}: {}", row + 1, reason)),
+                    }
+                }
+            }
+        }
+        Ok((accounts, skipped))
+    }
+}
+
+// the protobuf payload in the data parameter of `uri`
+fn payload(uri: &str) -> Option<Vec<u8>> {
+    let query = uri.strip_prefix("otpauth-migration://")?.split_once('?')?.1;
+    let data = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("data="))?;
+    // a + left unescaped is part of the Base64, not a space
+    decode_base64(&otpauth::percent_decode(data).replace(' ', "+"))
+}
+
+// the account of an encoded OtpParameters
+fn account(parameters: &[u8]) -> Result<Account, String> {
+    let mut account = Account::new(String::new(), String::new(), String::new());
+    let (mut secret, mut kind) = (vec![], 2);
+    for (field, value) in fields(parameters).ok_or("damaged account")? {
+        match (field, value) {
+            (1, Value::Bytes(bytes)) => secret = bytes.to_vec(),
+            (2, Value::Bytes(name)) => account.name = String::from_utf8_lossy(name).into_owned(),
+            (3, Value::Bytes(issuer)) => {
+                account.issuer = String::from_utf8_lossy(issuer).into_owned()
+            }
+            (4, Value::Varint(algorithm)) => {
+                account.algorithm = match algorithm {
+                    0 | 1 => Algorithm::Sha1,
+                    2 => Algorithm::Sha256,
+                    3 => Algorithm::Sha512,
+                    _ => return Err(format!("{}: MD5 codes are not supported", account.name)),
+                }
+            }
+            (5, Value::Varint(digits)) => account.digits = if digits == 2 { 8 } else { 6 },
+            (6, Value::Varint(otp_type)) => kind = otp_type,
+            _ => {}
+        }
+    }
+    // Google Authenticator puts the issuer in the name as well
+    let prefix = format!("{}:", account.issuer);
+    if let Some(name) = account.name.strip_prefix(&prefix) {
+        account.name = name.trim().to_string();
+    }
+    if kind == 1 {
+        return Err(format!("{}: HOTP codes are not supported", account.name));
+    }
+    if secret.is_empty() {
+        return Err(format!("{}: no secret", account.name));
+    }
+    account.secret = otp::encode_base32(&secret);
+    Ok(account)
+}
+
+// a field of a protobuf message, only the wire types the payload uses
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+// the fields of the protobuf message `data` with their numbers, None if it is damaged
+fn fields(mut data: &[u8]) -> Option<Vec<(u64, Value<'_>)>> {
+    let mut fields = vec![];
+    while !data.is_empty() {
+        let key = varint(&mut data)?;
+        let value = match key & 7 {
+            0 => Value::Varint(varint(&mut data)?),
+            2 => {
+                let len = varint(&mut data)? as usize;
+                let bytes = data.get(..len)?;
+                data = &data[len..];
+                Value::Bytes(bytes)
+            }
+            _ => return None,
+        };
+        fields.push((key >> 3, value));
+    }
+    Some(fields)
+}
+
+// read a varint off the front of `data`
+fn varint(data: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for (i, &byte) in data.iter().enumerate().take(10) {
+        value |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            *data = &data[i + 1..];
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn put_varint_field(out: &mut Vec<u8>, field: u64, value: u64) {
+    put_varint(out, field << 3);
+    put_varint(out, value);
+}
+
+fn put_bytes(out: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    put_varint(out, field << 3 | 2);
+    put_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+// standard Base64 (RFC 4648) with padding
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | (byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+// standard Base64, padded or not, None if it is not Base64
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let (mut bytes, mut buffer, mut bits) = (vec![], 0u32, 0);
+    for c in text.trim().trim_end_matches('=').bytes() {
+        let value = BASE64.iter().position(|&b| b == c)? as u32;
+        buffer = (buffer << 6 | value) & 0xffff;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
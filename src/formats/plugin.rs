@@ -0,0 +1,216 @@
+//! Importers of other formats added without changing this app: executables named
+//! `totp-import-NAME`, found on $PATH or in the plugins directory of the data
+//! directory, picked with `import --plugin NAME`.
+//!
+//! The plugin is given the export on stdin, already decrypted, with
+//! $CLI_TOTP_IMPORT_PROTOCOL set to the version of this protocol, and prints the
+//! accounts it read on stdout:
+//!
+//! ```text
+//! {"version": 1,
+//!  "accounts": [{"issuer": "GitHub", "name": "me", "secret": "BASE32",
+//!                "algorithm": "SHA1", "digits": 6, "period": 30, "note": ""}],
+//!  "skipped": ["why an entry could not be read"]}
+//! ```
+//!
+//! Only `name` and `secret` are needed, the others default to the usual values. A
+//! plugin that cannot read the export exits with a failure and says why on stderr.
+
+use super::Importer;
+use crate::paths;
+use cli_totp::otp::{self, Algorithm};
+use cli_totp::Account;
+use serde::Deserialize;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// The version of the protocol spoken with plugins, changed only in ways older plugins
+/// can tell from the version
+const PROTOCOL: u32 = 1;
+
+const PREFIX: &str = "totp-import-";
+
+/// An importer plugin
+pub struct Plugin {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct Output {
+    version: u32,
+    accounts: Vec<Entry>,
+    #[serde(default)]
+    skipped: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    #[serde(default)]
+    issuer: String,
+    name: String,
+    secret: String,
+    #[serde(default)]
+    algorithm: String,
+    #[serde(default = "default_digits")]
+    digits: u32,
+    #[serde(default = "default_period")]
+    period: u64,
+    #[serde(default)]
+    note: String,
+}
+
+fn default_digits() -> u32 {
+    otp::DIGITS
+}
+
+fn default_period() -> u64 {
+    otp::PERIOD
+}
+
+/// The plugins found, by name, those on $PATH first; of two with the same name the first
+/// found is used
+pub fn plugins() -> Vec<Plugin> {
+    let mut dirs: Vec<PathBuf> = env::var_os("PATH")
+        .map(|path| env::split_paths(&path).collect())
+        .unwrap_or_default();
+    dirs.push(paths::data_dir().join("plugins"));
+    let mut plugins: Vec<Plugin> = vec![];
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let Some(name) = file_name.strip_prefix(PREFIX) else {
+                continue;
+            };
+            // totp-import-NAME.exe on Windows
+            let name = name
+                .strip_suffix(std::env::consts::EXE_SUFFIX)
+                .unwrap_or(name);
+            let executable = entry
+                .path()
+                .metadata()
+                .is_ok_and(|meta| is_executable(&meta));
+            if executable && !plugins.iter().any(|plugin| plugin.name == name) {
+                plugins.push(Plugin {
+                    name: name.to_string(),
+                    path: entry.path(),
+                });
+            }
+        }
+    }
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+    plugins
+}
+
+/// The plugin called `name`
+pub fn find(name: &str) -> Result<Plugin, Box<dyn Error>> {
+    let plugins = plugins();
+    let found: Vec<&str> = plugins.iter().map(|plugin| plugin.name.as_str()).collect();
+    let message = if found.is_empty() {
+        format!(
+            "no importer plugin {}{} on $PATH or in {}, and no others either",
+            PREFIX,
+            name,
+            paths::data_dir().join("plugins").display()
+        )
+    } else {
+        format!(
+            "no importer plugin {}{}, there are {}",
+            PREFIX,
+            name,
+            found.join(", ")
+        )
+    };
+    plugins
+        .into_iter()
+        .find(|plugin| plugin.name == name)
+        .ok_or_else(|| message.into())
+}
+
+impl Importer for Plugin {
+    fn import(&self, plain: &[u8]) -> Result<(Vec<Account>, Vec<String>), Box<dyn Error>> {
+        let mut child = Command::new(&self.path)
+            .env("CLI_TOTP_IMPORT_PROTOCOL", PROTOCOL.to_string())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| format!("cannot run {}: {}", self.path.display(), err))?;
+        // fed from a thread of its own, or a plugin answering before it read everything
+        // would wait on us as we wait on it
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let plain = plain.to_vec();
+        let feeder = thread::spawn(move || stdin.write_all(&plain));
+        let output = child.wait_with_output()?;
+        // a plugin that does not read it all is fine
+        let _ = feeder.join();
+        if !output.status.success() {
+            return Err(format!(
+                "the {} plugin cannot read it: {}",
+                self.name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+        let output: Output = serde_json::from_slice(&output.stdout)
+            .map_err(|err| format!("the {} plugin answered nonsense: {}", self.name, err))?;
+        if output.version != PROTOCOL {
+            return Err(format!(
+                "the {} plugin speaks version {} of the protocol, not {}",
+                self.name, output.version, PROTOCOL
+            )
+            .into());
+        }
+        let (mut accounts, mut skipped) = (vec![], output.skipped);
+        for entry in output.accounts {
+            let algorithm = match entry.algorithm.as_str() {
+                "" => Algorithm::default(),
+                algorithm => match Algorithm::from_name(algorithm) {
+                    Some(algorithm) => algorithm,
+                    None => {
+                        skipped.push(format!("{}: unknown algorithm {}", entry.name, algorithm));
+                        continue;
+                    }
+                },
+            };
+            if otp::decode_base32(&entry.secret).is_none() {
+                skipped.push(format!("{}: the secret is not Base32", entry.name));
+                continue;
+            }
+            if !otp::valid_digits(entry.digits) || entry.period == 0 {
+                skipped.push(format!(
+                    "{}: {} digits every {}s cannot be",
+                    entry.name, entry.digits, entry.period
+                ));
+                continue;
+            }
+            accounts.push(Account {
+                algorithm,
+                digits: entry.digits,
+                period: entry.period,
+                note: entry.note,
+                ..Account::new(entry.issuer, entry.name, entry.secret)
+            });
+        }
+        Ok((accounts, skipped))
+    }
+}
+
+// whether `meta` is of a file that can be run
+fn is_executable(meta: &fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        meta.is_file() && meta.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    meta.is_file()
+}
@@ -0,0 +1,40 @@
+//! One otpauth:// URI per line, what authenticator apps scan; blank lines and
+//! lines starting with # are left alone.
+
+use super::{portable, Exporter, Importer};
+use cli_totp::{otpauth, Account};
+use std::error::Error;
+
+pub struct Uri;
+
+impl Exporter for Uri {
+    fn export(&self, accounts: &[Account]) -> Result<(Vec<u8>, Vec<String>), Box<dyn Error>> {
+        let (accounts, skipped) = portable(accounts);
+        let uris = accounts
+            .into_iter()
+            .map(|account| otpauth::to_uri(account) + "\n")
+            .collect::<String>();
+        Ok((uris.into_bytes(), skipped))
+    }
+}
+
+impl Importer for Uri {
+    fn import(&self, plain: &[u8]) -> Result<(Vec<Account>, Vec<String>), Box<dyn Error>> {
+        let (mut accounts, mut skipped) = (vec![], vec![]);
+        for (row, line) in String::from_utf8_lossy(plain).lines().enumerate() {
+            // blank lines and comments are not accounts
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match otpauth::parse(line) {
+                Ok(account) if account.name.is_empty() => {
+                    skipped.push(format!("line {}: the URI has no account name", row + 1))
+                }
+                Ok(account) => accounts.push(account),
+                Err(err) => skipped.push(format!("line {}: {}", row + 1, err)),
+            }
+        }
+        Ok((accounts, skipped))
+    }
+}
@@ -0,0 +1,128 @@
+//! Shell commands run after something happens to the accounts, set in the `[hooks]`
+//! table of the config, e.g. to commit the vault to git or to tell another program:
+//!
+//! ```toml
+//! [hooks]
+//! post_add = "notify-send \"Added $CLI_TOTP_ACCOUNT\""
+//! post_sync = "cd ~/.local/share/cli-totp && git commit -qam sync"
+//! ```
+//!
+//! The command is run with `sh -c` from a thread of its own, so a slow one does not
+//! hold up the UI, and is told what happened in environment variables:
+//!
+//! - `CLI_TOTP_EVENT`: add, delete, copy or sync
+//! - `CLI_TOTP_ACCOUNT` and `CLI_TOTP_ISSUER`: the account, for all but sync
+//! - `CLI_TOTP_COUNT`: how many accounts were brought in, for sync
+//! - `CLI_TOTP_SYNC`: pushed or pulled, for a sync with the WebDAV server instead
+//!
+//! Secrets and codes are never given to hooks.
+
+use cli_totp::Account;
+use serde::Deserialize;
+use std::mem;
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::thread::{self, JoinHandle};
+use tracing::{debug, warn};
+
+/// The commands of the `[hooks]` table, none by default
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hooks {
+    /// Run after an account is added
+    pub post_add: Option<String>,
+    /// Run after an account is deleted for good
+    pub post_delete: Option<String>,
+    /// Run after the code of an account is copied
+    pub post_copy: Option<String>,
+    /// Run after accounts from elsewhere are merged into the vault, as by `import`, and
+    /// after the vault is pushed to or pulled from the WebDAV server
+    pub post_sync: Option<String>,
+}
+
+/// What happened
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Event {
+    Add,
+    Delete,
+    Copy,
+    Sync,
+}
+
+// set from the config at startup
+static HOOKS: OnceLock<Hooks> = OnceLock::new();
+
+// the hooks still running, waited for before exiting
+static RUNNING: Mutex<Vec<JoinHandle<()>>> = Mutex::new(vec![]);
+
+/// Run the commands of `hooks` from now on
+pub fn init(hooks: Hooks) {
+    let _ = HOOKS.set(hooks);
+}
+
+/// Run the hook of `event`, if there is one, about `account`
+pub fn account(event: Event, account: &Account) {
+    run(
+        event,
+        vec![
+            ("CLI_TOTP_ACCOUNT", account.name.clone()),
+            ("CLI_TOTP_ISSUER", account.issuer.clone()),
+        ],
+    );
+}
+
+/// Run the hook of a sync that brought in `count` accounts, if there is one
+pub fn synced(count: usize) {
+    run(Event::Sync, vec![("CLI_TOTP_COUNT", count.to_string())]);
+}
+
+/// Run the hook of a sync with the WebDAV server that `outcome` the vault, if there is one
+pub fn pushed_or_pulled(outcome: &str) {
+    run(Event::Sync, vec![("CLI_TOTP_SYNC", outcome.to_string())]);
+}
+
+/// Wait for the hooks still running, so exiting does not cut them short
+pub fn wait() {
+    let running = mem::take(&mut *RUNNING.lock().expect("no hook panics holding it"));
+    for hook in running {
+        let _ = hook.join();
+    }
+}
+
+fn run(event: Event, vars: Vec<(&'static str, String)>) {
+    let Some(hooks) = HOOKS.get() else {
+        return;
+    };
+    let (name, command) = match event {
+        Event::Add => ("add", &hooks.post_add),
+        Event::Delete => ("delete", &hooks.post_delete),
+        Event::Copy => ("copy", &hooks.post_copy),
+        Event::Sync => ("sync", &hooks.post_sync),
+    };
+    let Some(command) = command.clone() else {
+        return;
+    };
+    let hook = thread::spawn(move || {
+        // the terminal belongs to the UI, what the hook prints goes to the log
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(&command)
+            .env("CLI_TOTP_EVENT", name)
+            .envs(vars)
+            .stdin(Stdio::null())
+            .output();
+        match output {
+            Ok(output) if output.status.success() => debug!(event = name, "hook ran"),
+            Ok(output) => warn!(
+                event = name,
+                status = %output.status,
+                stderr = %String::from_utf8_lossy(&output.stderr).trim(),
+                "hook failed"
+            ),
+            Err(err) => warn!(event = name, %err, "cannot run the hook"),
+        }
+    });
+    let mut running = RUNNING.lock().expect("no hook panics holding it");
+    running.retain(|hook| !hook.is_finished());
+    running.push(hook);
+}
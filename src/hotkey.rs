@@ -0,0 +1,149 @@
+//! The global hotkey of the agent: pressed anywhere on the desktop, it copies the
+//! code of the account the config names, or else pops up a picker of the accounts,
+//! rofi, wofi, fuzzel or dmenu, and copies the code of the one picked, without going
+//! to the terminal. Global hotkeys are only available on Linux under X11.
+
+use crate::clipboard::{self, Clipboard};
+use crate::hooks;
+use cli_totp::Vault;
+use std::error::Error;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// The pickers tried in turn without `hotkey_picker` in the config, each reading the
+/// account names from stdin and printing the one picked
+const PICKERS: &[&str] = &[
+    "rofi -dmenu -i -p TOTP",
+    "wofi --dmenu",
+    "fuzzel --dmenu",
+    "dmenu -i",
+];
+
+/// What the hotkey does
+pub struct Settings {
+    /// The keys, e.g. "ctrl+alt+t"
+    pub keys: String,
+    /// The account whose code is copied instead of picking one
+    pub account: Option<String>,
+    /// The command picking an account, instead of the first of [`PICKERS`] found
+    pub picker: Option<String>,
+    /// How the code is copied
+    pub clipboard: clipboard::Backend,
+}
+
+/// Register the hotkey and answer it from a thread of its own for as long as the agent runs
+#[cfg(target_os = "linux")]
+pub fn spawn(
+    vault: Arc<Vault>,
+    settings: Settings,
+    time_offset: i64,
+) -> Result<(), Box<dyn Error>> {
+    use global_hotkey::hotkey::HotKey;
+    use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+
+    let hotkey: HotKey = settings
+        .keys
+        .parse()
+        .map_err(|err| format!("bad hotkey {:?}: {}", settings.keys, err))?;
+    let manager = GlobalHotKeyManager::new()
+        .map_err(|err| format!("cannot use global hotkeys here: {}", err))?;
+    manager
+        .register(hotkey)
+        .map_err(|err| format!("cannot register the hotkey {}: {}", settings.keys, err))?;
+    eprintln!("agent copying codes on {}", settings.keys);
+    std::thread::spawn(move || {
+        // unregistered when dropped
+        let _manager = manager;
+        // kept for as long as the code may have to be served to a paste
+        let mut clipboard = Clipboard::new(settings.clipboard);
+        for event in GlobalHotKeyEvent::receiver().iter() {
+            if event.id() == hotkey.id() && event.state() == HotKeyState::Pressed {
+                if let Err(err) = answer(&vault, &settings, &mut clipboard, time_offset) {
+                    warn!(%err, "hotkey");
+                    eprintln!("agent: {}", err);
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Global hotkeys need X11, which is only found on Linux here
+#[cfg(not(target_os = "linux"))]
+pub fn spawn(_: Arc<Vault>, _: Settings, _: i64) -> Result<(), Box<dyn Error>> {
+    Err("global hotkeys are only available on Linux under X11".into())
+}
+
+// copy the code of the account of the settings, or of the one picked
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn answer(
+    vault: &Vault,
+    settings: &Settings,
+    clipboard: &mut Clipboard,
+    time_offset: i64,
+) -> Result<(), Box<dyn Error>> {
+    let name = match &settings.account {
+        Some(name) => name.clone(),
+        None => match pick(vault, settings.picker.as_deref())? {
+            Some(name) => name,
+            // the picker was closed
+            None => return Ok(()),
+        },
+    };
+    let Some(account) = vault.accounts.iter().find(|a| a.name == name) else {
+        return Err(format!("there is no account {}", name).into());
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+        .saturating_add_signed(time_offset);
+    clipboard.copy(&account.code_at(now))?;
+    info!(account = %name, "code copied on the hotkey");
+    hooks::account(hooks::Event::Copy, account);
+    Ok(())
+}
+
+// the account name picked with `picker`, or the first of the usual pickers found
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn pick(vault: &Vault, picker: Option<&str>) -> Result<Option<String>, Box<dyn Error>> {
+    let names: String = vault
+        .accounts
+        .iter()
+        .filter(|account| !account.archived)
+        .map(|account| format!("{}\n", account.name))
+        .collect();
+    let candidates = match picker {
+        Some(picker) => vec![picker],
+        None => PICKERS.to_vec(),
+    };
+    for candidate in candidates {
+        let mut words = candidate.split_whitespace();
+        let Some(program) = words.next() else {
+            continue;
+        };
+        let child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            // not installed, try the next
+            Err(_) if picker.is_none() => continue,
+            Err(err) => return Err(format!("cannot run {}: {}", program, err).into()),
+        };
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(names.as_bytes())?;
+        let output = child.wait_with_output()?;
+        let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        return Ok((!name.is_empty()).then_some(name));
+    }
+    Err("no picker found, install rofi, wofi, fuzzel or dmenu or set hotkey_picker".into())
+}
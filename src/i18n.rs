@@ -0,0 +1,122 @@
+//! The language of the UI. The strings of the UI are written in English in the
+//! code and looked up in the catalog of the language picked at startup, a PO file
+//! in assets/locale; a string the catalog does not have stays in English.
+//!
+//! The language comes from `language` in the config, or else from $LC_ALL,
+//! $LC_MESSAGES or $LANG, e.g. `fr_FR.UTF-8`.
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Display;
+use std::sync::OnceLock;
+use tracing::{debug, warn};
+
+/// The languages the UI is translated to, by their ISO 639-1 code, with their catalogs
+const CATALOGS: &[(&str, &str)] = &[("fr", include_str!("../assets/locale/fr.po"))];
+
+// English to the language picked, empty for English
+static CATALOG: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Pick the language of the UI, `language` or else the one of the locale
+pub fn init(language: Option<&str>) {
+    let locale = language.map(str::to_string).or_else(|| {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+    });
+    // fr_FR.UTF-8 and fr-FR are French
+    let code = locale
+        .as_deref()
+        .and_then(|locale| locale.split(['_', '-', '.', '@']).next())
+        .unwrap_or_default()
+        .to_lowercase();
+    let catalog = match CATALOGS.iter().find(|(name, _)| *name == code) {
+        Some((_, po)) => {
+            debug!(language = %code, "translating the UI");
+            parse(po)
+        }
+        None => {
+            if language.is_some() && code != "en" {
+                warn!(language = %code, "no translation, the UI stays in English");
+            }
+            HashMap::new()
+        }
+    };
+    let _ = CATALOG.set(catalog);
+}
+
+/// The translation of `message`, `message` itself if there is none
+pub fn tr(message: &'static str) -> &'static str {
+    match CATALOG.get().and_then(|catalog| catalog.get(message)) {
+        Some(translation) => translation,
+        None => message,
+    }
+}
+
+/// The translation of `message` with each {} replaced by the next of `args`
+pub fn trf(message: &'static str, args: &[&dyn Display]) -> String {
+    let mut args = args.iter();
+    let mut parts = tr(message).split("{}");
+    let mut text = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        if let Some(arg) = args.next() {
+            text += &arg.to_string();
+        }
+        text += part;
+    }
+    text
+}
+
+// the msgid to msgstr pairs of a PO file, leaving out those not translated yet
+fn parse(po: &str) -> HashMap<String, String> {
+    let mut catalog = HashMap::new();
+    let (mut id, mut translation) = (String::new(), String::new());
+    // which of the two a "..." line on its own continues
+    let mut in_translation = false;
+    for line in po.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("msgid ") {
+            if !id.is_empty() && !translation.is_empty() {
+                catalog.insert(std::mem::take(&mut id), std::mem::take(&mut translation));
+            }
+            (id, translation) = (unquote(rest), String::new());
+            in_translation = false;
+        } else if let Some(rest) = line.strip_prefix("msgstr ") {
+            translation = unquote(rest);
+            in_translation = true;
+        } else if line.starts_with('"') {
+            if in_translation {
+                translation += &unquote(line);
+            } else {
+                id += &unquote(line);
+            }
+        }
+    }
+    if !id.is_empty() && !translation.is_empty() {
+        catalog.insert(id, translation);
+    }
+    catalog
+}
+
+// the text of a quoted PO string, with its escapes undone
+fn unquote(quoted: &str) -> String {
+    let inner = quoted
+        .trim()
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .unwrap_or_default();
+    let mut text = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => text.push('\n'),
+            Some('t') => text.push('\t'),
+            Some(c) => text.push(c),
+            None => {}
+        }
+    }
+    text
+}
@@ -0,0 +1,119 @@
+//! Imports the TOTP settings KeePass and KeePassXC keep with their entries.
+//!
+//! Three ways of storing them are understood:
+//!
+//! - an `otp` field with an otpauth:// URI, what KeePassXC writes today
+//! - an `otp` field like `key=BASE32&step=30&size=6`, from the KeeOtp plugin
+//! - the `TOTP Seed` and `TOTP Settings` fields of older KeePassXC versions
+//!
+//! Entries without any of them are not TOTP entries and are left out.
+
+use cli_totp::otp::{self, Algorithm, SecretEncoding};
+use cli_totp::{otpauth, Account};
+use keepass::{Database, DatabaseKey};
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+
+/// The accounts in the database at `path`, and why the TOTP entries that could
+/// not be imported were skipped
+pub fn import(
+    path: &Path,
+    password: &str,
+    keyfile: Option<&Path>,
+) -> Result<(Vec<Account>, Vec<String>), Box<dyn Error>> {
+    let mut key = DatabaseKey::new().with_password(password);
+    if let Some(keyfile) = keyfile {
+        key = key.with_keyfile(&mut File::open(keyfile)?)?;
+    }
+    let db = Database::open(&mut File::open(path)?, key)?;
+
+    let (mut accounts, mut skipped) = (vec![], vec![]);
+    for entry in db.iter_all_entries() {
+        let title = entry.get_title().unwrap_or_default();
+        let username = entry.get_username().unwrap_or_default();
+        let account = if let Some(otp) = entry.get("otp") {
+            if otp.trim_start().starts_with("otpauth://") {
+                otpauth::parse(otp).map_err(|err| err.to_string())
+            } else {
+                keeotp(otp)
+            }
+        } else if let Some(seed) = entry.get("TOTP Seed") {
+            keepassxc(seed, entry.get("TOTP Settings").unwrap_or("30;6"))
+        } else {
+            continue;
+        };
+
+        match account {
+            Ok(mut account) => {
+                if account.issuer.is_empty() {
+                    account.issuer = title.to_string();
+                }
+                if account.name.is_empty() {
+                    account.name = if username.is_empty() { title } else { username }.to_string();
+                }
+                accounts.push(account);
+            }
+            Err(reason) => skipped.push(format!("{}: {}", title, reason)),
+        }
+    }
+    Ok((accounts, skipped))
+}
+
+// `key=BASE32&step=30&size=6&otpHashMode=Sha256` as written by KeeOtp, the key may be
+// hex or UTF-8 too with `encoding=`
+fn keeotp(otp: &str) -> Result<Account, String> {
+    let mut account = Account::new(String::new(), String::new(), String::new());
+    let mut encoding = SecretEncoding::Base32;
+    for pair in otp.trim().split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        match key {
+            "key" => account.secret = value.to_string(),
+            "encoding" if value.eq_ignore_ascii_case("utf8") => encoding = SecretEncoding::Raw,
+            "encoding" => encoding = value.parse()?,
+            "step" => account.period = value.parse().map_err(|_| "invalid step")?,
+            "size" => {
+                account.digits = value
+                    .parse()
+                    .ok()
+                    .filter(|&digits| otp::valid_digits(digits))
+                    .ok_or("invalid size")?
+            }
+            "otpHashMode" => {
+                account.algorithm = Algorithm::from_name(value).ok_or("unknown hash mode")?
+            }
+            "type" if !value.eq_ignore_ascii_case("totp") => {
+                return Err(format!("{} codes are not supported", value))
+            }
+            _ => {}
+        }
+    }
+    if account.secret.is_empty() {
+        return Err("no key".to_string());
+    }
+    account.secret = encoding
+        .to_base32(&account.secret)
+        .ok_or_else(|| format!("the key is not {}", encoding.name()))?;
+    Ok(account)
+}
+
+// the seed and `period;digits` settings of older KeePassXC versions
+fn keepassxc(seed: &str, settings: &str) -> Result<Account, String> {
+    let mut account = Account::new(String::new(), String::new(), seed.trim().to_string());
+    let mut settings = settings.split(';');
+    if let Some(period) = settings.next() {
+        account.period = period.trim().parse().map_err(|_| "invalid TOTP Settings")?;
+    }
+    match settings.next().map(str::trim) {
+        Some("S") => return Err("Steam codes are not supported".to_string()),
+        Some(digits) => {
+            account.digits = digits
+                .parse()
+                .ok()
+                .filter(|&digits| otp::valid_digits(digits))
+                .ok_or("invalid TOTP Settings")?
+        }
+        None => {}
+    }
+    Ok(account)
+}
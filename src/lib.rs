@@ -0,0 +1,44 @@
+//! The core of the authenticator, without the terminal UI: the code generator,
+//! the account model, the vault the accounts are stored in and the clock check.
+//!
+//! ```no_run
+//! use cli_totp::Vault;
+//!
+//! let (vault, _key) = Vault::load("work.vault", "correct horse battery staple")?;
+//! for account in &vault.accounts {
+//!     println!("{} {}", account.name, account.code_at(1_700_000_000));
+//! }
+//! # Ok::<(), cli_totp::Error>(())
+//! ```
+
+pub mod account;
+pub mod audit;
+pub mod ntp;
+pub mod otp;
+pub mod otpauth;
+pub mod vault;
+
+pub use account::{Account, RecoveryCode};
+pub use otp::Totp;
+pub use vault::{KdfParams, Key, Responder, Vault};
+
+/// Why the vault could not be read or written
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("cannot access the vault: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("the vault is damaged: {0}")]
+    Format(#[from] serde_json::Error),
+    #[error("not a vault file")]
+    NotAVault,
+    #[error("wrong passphrase, or the vault is damaged")]
+    Passphrase,
+    #[error("{0}")]
+    Crypto(String),
+    #[error("YubiKey challenge-response failed: {0}")]
+    YubiKey(String),
+    #[error("the decoy needs a passphrase other than the vault's")]
+    SamePassphrase,
+    #[error("the vault is of version {0}, made by a newer cli-TOTP; update it to open the vault")]
+    TooNew(u32),
+}
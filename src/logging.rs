@@ -0,0 +1,48 @@
+//! What the app does, written to a log file for debugging: the terminal is in
+//! raw mode and belongs to the UI, so nothing is logged there. Off unless
+//! --verbose or $RUST_LOG asks for it; secrets and codes are never logged.
+
+use crate::paths;
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing_subscriber::EnvFilter;
+
+/// Where the log goes without --log-file, cli-totp.log in the data directory
+pub fn default_file() -> PathBuf {
+    paths::data_dir().join("cli-totp.log")
+}
+
+/// Start logging to `file` at the level of `verbose` (1 info, 2 debug, 3 trace),
+/// or as $RUST_LOG says when it is set; nothing is logged when neither asks for it
+pub fn init(verbose: u8, file: Option<PathBuf>) -> Result<(), Box<dyn Error>> {
+    let filter = match (std::env::var("RUST_LOG"), verbose) {
+        (Ok(directives), _) => EnvFilter::try_new(directives)?,
+        (Err(_), 0) => return Ok(()),
+        (Err(_), verbose) => {
+            let level = match verbose {
+                1 => "info",
+                2 => "debug",
+                _ => "trace",
+            };
+            // the logs of this app, not those of the libraries it uses
+            EnvFilter::new(format!("cli_TOTP={0},cli_totp={0}", level))
+        }
+    };
+    let file = file.unwrap_or_else(default_file);
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&file)
+        .map_err(|err| format!("cannot open the log {}: {}", file.display(), err))?;
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(Mutex::new(log))
+        .with_ansi(false)
+        .init();
+    Ok(())
+}
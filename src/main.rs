@@ -1,62 +1,209 @@
-use anyhow::{Context, Result};
-use byteorder::{BigEndian, ReadBytesExt};
-use crossterm::{
-    event::{self, Event as CEvent, KeyCode},
-    terminal::{disable_raw_mode, enable_raw_mode},
+// the agent, and what starts it or talks to it, listen on Unix sockets
+#[cfg(unix)]
+mod agent;
+mod autotype;
+mod backoff;
+mod backup;
+mod bigfont;
+mod cli;
+mod clipboard;
+mod commands;
+mod compat;
+mod config;
+mod diff;
+mod doctor;
+mod export;
+mod formats;
+mod hooks;
+#[cfg(unix)]
+mod hotkey;
+mod i18n;
+mod kdbx;
+mod logging;
+mod merge;
+mod migrate;
+#[cfg(unix)]
+mod native;
+mod notify;
+mod pass;
+mod paths;
+mod permissions;
+mod plain;
+mod preview;
+mod profile;
+mod qr;
+mod screen;
+mod selftest;
+mod session;
+mod shamir;
+mod status;
+mod store;
+#[cfg(unix)]
+mod systemd;
+mod template;
+mod theme;
+mod timesource;
+mod watch;
+mod webdav;
+mod yubikey;
+
+use anyhow::Result;
+use clap::Parser;
+use cli::{Cli, Command};
+use cli_totp::account::USAGE_DAYS;
+use cli_totp::audit::{self, Action};
+use cli_totp::otp::{SecretEncoding, PERIOD};
+use cli_totp::{ntp, otpauth, Account, RecoveryCode, Vault};
+use config::Config;
+use crossterm::event::{
+    Event as CEvent, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers,
 };
-use ring::hmac;
-use std::error::Error;
-use std::io;
-use std::sync::mpsc;
-use std::thread;
-use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
-use std::vec;
-use tui::{
+use futures_util::StreamExt;
+use i18n::{tr, trf};
+use ratatui::{
     backend::CrosstermBackend,
+    layout::Margin,
+    layout::Rect,
     layout::{Alignment, Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
-    text::{Span, Spans},
+    symbols,
+    text::{Line, Span, Text},
+    widgets::Clear,
     widgets::{
-        Block, BorderType, Borders, Cell, Gauge, List, ListItem, ListState, Paragraph, Row, Table,
-        Tabs,
+        Block, BorderType, Borders, Cell, Gauge, List, ListItem, ListState, Paragraph, Row,
+        Scrollbar, ScrollbarOrientation, ScrollbarState, Sparkline, Table, Tabs,
     },
-    Terminal,
+    Frame, Terminal,
 };
+use std::collections::HashMap;
+use std::error::Error;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::vec;
+use store::Store;
+use theme::{parse_color, Theme};
+use timesource::TimeSource;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+fn main() {
+    let result = run(Cli::parse());
+    // the hooks of what was done last may still be running
+    hooks::wait();
+    // errors are for people, not the Debug output returning them from main would print
+    if let Err(err) = result {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run(mut cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(dir) = cli.data_dir.take() {
+        paths::set_data_dir(dir);
+    }
+    logging::init(cli.verbose, cli.log_file.take())?;
+    info!(version = env!("CARGO_PKG_VERSION"), "starting");
+    let config_path = cli.config.take().unwrap_or_else(config::default_path);
+    let mut config = match Config::load(&config_path) {
+        // told about with the rest
+        Err(_) if matches!(cli.command, Some(Command::Doctor { .. })) => Config::default(),
+        config => config?,
+    };
+    let keymap = config.keymap()?;
+    backup::set_keep(config.backups.unwrap_or(backup::DEFAULT_KEEP));
+    store::set_kdf(config.kdf);
+    store::set_read_only(cli.read_only || config.read_only);
+    // the UI shows a banner instead, the plain one has none
+    store::set_allow_exposed(cli.allow_exposed || cli.command.is_none() && !cli.plain);
+    compat::init(cli.compat.unwrap_or(config.compat));
+    i18n::init(config.language.as_deref());
+    hooks::init(config.hooks.clone());
+    if cli.pass.is_none() {
+        cli.vault = cli.vault.or(config.vault.take());
+    }
+    let lock_after = cli.lock_after.or(config.lock_after).unwrap_or(5);
+    let mut recorder = match &cli.record_session {
+        Some(path) => Some(session::Recorder::create(path)?),
+        None => None,
+    };
+    let replay = match &cli.replay {
+        Some(path) => session::load_replay(path)?,
+        None => vec![],
+    };
+    let (profile_name, vault_path) = match cli.vault.as_deref().map(profile::resolve) {
+        Some((name, path)) => (name, Some(path)),
+        None => (None, None),
+    };
+    if let Some(command) = cli.command.take() {
+        return commands::run(command, &cli, config, &config_path, vault_path);
+    }
+    let opened = match (&vault_path, &cli.pass) {
+        (None, None) => None,
+        _ => Some(store::open(
+            vault_path,
+            cli.pass.as_deref(),
+            cli.yubikey_slot,
+            true,
+        )?),
+    };
+    if cli.plain {
+        let (vault, store) = match opened {
+            Some((vault, store)) => (vault, Some(store)),
+            None => (Vault::default(), None),
+        };
+        return plain::run(vault, store, cli.time_offset, config.defaults);
+    }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // tui Gui
-    enable_raw_mode().expect("can run in raw mode");
-
-    // channel to communicate between input and rendering loop we want a channel and a thread for a loop to not block the main thread
-    // create multiproducer, single consumer channel
-    let (tx, rx) = mpsc::channel();
-    // the tick rate
-    let tick_rate = Duration::from_millis(200);
-    thread::spawn(move || {
-        // start counting from now
-        let mut last_tick = Instant::now();
-        //input loop
-        loop {
-            // calculate the next tick by subtracting tick_rate from last tick elapsed if the value is positive that value will be the timeout before sending an event else set it to 0 which mean no timeout
-            let timeout = tick_rate
-                .checked_sub(last_tick.elapsed()) // Duration subtraction. Computes self - other, returning None if the result would be negative or if overflow occurred.
-                .unwrap_or_else(|| Duration::from_secs(0));
-            //use event::poll to wait until that time for an event and if there is one,
-            //send that input event through our channel with the key the user pressed.
-            if event::poll(timeout).expect("poll works") {
-                // read the event key
-                if let CEvent::Key(key) = event::read().expect("can read events") {
-                    tx.send(Event::Input(key)).expect("can send events");
-                }
-            }
-            // if last tick elapsed is greter than tick rate send a tick ans start again
-            if last_tick.elapsed() >= tick_rate {
-                if let Ok(_) = tx.send(Event::Tick) {
-                    last_tick = Instant::now();
+    // tui Gui, the terminal is given back when this returns or panics
+    let screen = screen::Screen::take()?;
+
+    // keys and resizes come from the terminal's event stream, what background tasks find out
+    // comes over this channel; the UI thread runs them while it waits for the next event
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()?;
+    let mut input = EventStream::new();
+    let (background_tx, mut background_rx) = mpsc::unbounded_channel();
+    // sample the external clock, if any, now and then from the background
+    let time_source = match (cli.time_source_cmd, cli.time_source_nmea) {
+        (Some(command), _) => Some(TimeSource::Command(command)),
+        (_, Some(device)) => Some(TimeSource::Nmea(device)),
+        _ => None,
+    };
+    let time_source_name = time_source.as_ref().map(TimeSource::name);
+    if let Some(source) = time_source {
+        let tx = background_tx.clone();
+        let source = Arc::new(source);
+        runtime.spawn(async move {
+            loop {
+                let source = source.clone();
+                let sample = tokio::task::spawn_blocking(move || source.read())
+                    .await
+                    .map_err(|err| err.to_string())
+                    .and_then(|read| read.map_err(|err| err.to_string()))
+                    .map(|time| time - unix_time_f64());
+                if tx.send(Event::TimeSource(sample)).is_err() {
+                    break;
                 }
+                tokio::time::sleep(TIME_SOURCE_INTERVAL).await;
             }
-        }
-    });
+        });
+    }
+    // recorded events are sent at the same offset from here as they had in the recording
+    if !replay.is_empty() {
+        let tx = background_tx.clone();
+        runtime.spawn(async move {
+            let replay_start = tokio::time::Instant::now();
+            for (at, key) in replay {
+                tokio::time::sleep_until(replay_start + at).await;
+                if tx.send(Event::Input(key)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
     // create a terminal from crossterm backend
     let stdout = io::stdout();
     let backend = CrosstermBackend::new(stdout);
@@ -64,12 +211,43 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     terminal.clear()?;
 
     //Menu titles
-    let menu_titles = vec!["Home", "Codes", "Add", "Delete", "Quit"];
+    let menu_titles = [
+        ('h', "Home"),
+        ('c', "Codes"),
+        ('a', "Add"),
+        ('d', "Delete"),
+        ('q', "Quit"),
+    ];
     // active Menu ->Home
     let mut active_menu_item = MenuItem::Home;
-    let mut app = App::default();
-    let mut key_input_flag = false;
-    let mut active_menu_keys = true;
+    let mut app = App {
+        time_offset: cli.time_offset,
+        lock_after: (lock_after > 0).then(|| Duration::from_secs(lock_after * 60)),
+        clipboard: clipboard::Clipboard::new(config.clipboard),
+        clear_clipboard_after: config.clipboard_clear_after.map(Duration::from_secs),
+        notify_before_expiry: config.notify_before_expiry,
+        autotype: config.autotype,
+        autotype_delay: config.autotype_delay.unwrap_or(AUTOTYPE_DELAY),
+        time_source: time_source_name,
+        mask: cli.mask,
+        reveal_for: (cli.reveal_for > 0).then(|| Duration::from_secs(cli.reveal_for)),
+        theme: config.theme.theme(),
+        // set to anything but empty, see no-color.org
+        no_color: cli.no_color
+            || std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()),
+        recent_first: config.recent_first,
+        defaults: config.defaults,
+        keep_previous_secret: config
+            .keep_previous_secret
+            .filter(|&days| days > 0)
+            .map(|days| days * 86_400),
+        sync: config.sync,
+        ..App::default()
+    };
+    if let Some((vault, store)) = opened {
+        app.open_vault(vault, Some(store), profile_name);
+    }
+    let mut focused_field = InputField::Issuer;
     //creare a list
     let mut code_list_state = ListState::default();
     code_list_state.select(Some(0));
@@ -78,11 +256,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     loop {
         // draw a rect / direc: vertical/margin 2
         terminal.draw(|rect| {
-            let size = rect.size(); // this returns Terminal size
+            let size = rect.area(); // this returns Terminal size
+            let theme = app.theme;
 
+            // small terminals cannot spare the margin
             let chunks_codes = Layout::default()
                 .direction(Direction::Vertical)
-                .margin(2)
+                .margin(if size.width < NARROW || size.height < SHORT {
+                    0
+                } else {
+                    2
+                })
                 .constraints(
                     [
                         // Menu
@@ -101,428 +285,2296 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .margin(2)
                 .constraints(
                     [
+                        Constraint::Length(3), //three lines stay constant
+                        Constraint::Length(3), //three lines stay constant
+                        Constraint::Length(3), //three lines stay constant
+                        Constraint::Length(3), //three lines stay constant
+                        Constraint::Length(3), //three lines stay constant
+                        Constraint::Length(3), //three lines stay constant
                         Constraint::Length(3), //three lines stay constant
                         Constraint::Length(3), //three lines stay constant
                         Constraint::Length(3), //three lines stay constant
                         Constraint::Length(4),
-                        Constraint::Length(3), // three lines stay constant
                     ]
                     .as_ref(),
                 )
                 .split(size);
-            // prepare the footer
-            let copyright = Paragraph::new("TOTP-CLI 2022 - Authenticator")
-                .style(Style::default().fg(Color::LightCyan))
+            // prepare the footer, the clock check result takes the place of the copyright once there is one
+            // the drift that matters is the one left after applying the configured offset
+            let offset = app.clock() - unix_time_f64();
+            let (footer_text, footer_color) = match &app.clock_check {
+                _ if app.save_error.is_some() => (
+                    trf(
+                        "Cannot save the vault: {}",
+                        &[&app.save_error.as_deref().unwrap_or_default()],
+                    ),
+                    theme.error,
+                ),
+                _ if app
+                    .flash
+                    .as_ref()
+                    .is_some_and(|(_, _, at)| at.elapsed() < FLASH_FOR) =>
+                {
+                    let (text, color, _) = app.flash.as_ref().expect("there is a message");
+                    (text.clone(), *color)
+                }
+                _ if app.exposed.is_some() => (
+                    trf(
+                        "Others than you can read {}, run `doctor --fix`",
+                        &[&app.exposed.as_deref().unwrap_or_default()],
+                    ),
+                    theme.error,
+                ),
+                None => match (app.time_source, &app.source_offset) {
+                    (Some(name), Some(Ok(drift))) => (
+                        trf(
+                            "Time from {}, system clock is off by {}s",
+                            &[&tr(name), &format!("{:+.2}", -drift)],
+                        ),
+                        theme.info,
+                    ),
+                    (Some(name), Some(Err(err))) => {
+                        (trf("Cannot read the {}: {}", &[&tr(name), err]), theme.error)
+                    }
+                    (Some(name), None) => (trf("Reading the {}...", &[&tr(name)]), theme.info),
+                    (None, _) => (
+                        tr("TOTP-CLI 2022 - Authenticator").to_string(),
+                        theme.info,
+                    ),
+                },
+                Some(ClockCheck::Pending) => (
+                    trf("Checking the clock against {}...", &[&ntp::SERVER]),
+                    theme.info,
+                ),
+                Some(ClockCheck::Drift(drift)) if (drift + offset).abs() > ntp::DRIFT_THRESHOLD => (
+                    trf(
+                        "Clock is off by {}s from {}, codes may be rejected",
+                        &[&format!("{:+.1}", drift + offset), &ntp::SERVER],
+                    ),
+                    theme.error,
+                ),
+                Some(ClockCheck::Drift(drift)) => (
+                    trf(
+                        "Clock is within {}s of {}",
+                        &[&format!("{:+.2}", drift + offset), &ntp::SERVER],
+                    ),
+                    theme.accent,
+                ),
+                Some(ClockCheck::Failed(err)) => {
+                    (trf("Clock check failed: {}", &[err]), theme.warning)
+                }
+            };
+            let copyright = Paragraph::new(footer_text)
+                .style(Style::default().fg(footer_color))
                 .alignment(Alignment::Center)
                 .block(
                     // put the copyright paragraph in this block
                     Block::default()
                         .borders(Borders::ALL)
-                        .style(Style::default().fg(Color::White))
-                        .title("TOTP")
+                        .style(Style::default().fg(theme.text))
+                        .title(tr("TOTP"))
                         .border_type(BorderType::Plain),
                 );
 
             // create the Menu
-            let menu = menu_titles
+            let menu: Vec<Line> = menu_titles
                 .iter()
-                .map(|t| {
-                    let (first, rest) = t.split_at(1);
-                    Spans::from(vec![
-                        Span::styled(
-                            first,
-                            Style::default()
-                                .fg(Color::Green)
-                                .add_modifier(Modifier::UNDERLINED),
-                        ),
-                        Span::styled(rest, Style::default().fg(Color::White)),
+                .map(|&(key, title)| {
+                    let key_style = Style::default()
+                        .fg(theme.key)
+                        .add_modifier(Modifier::UNDERLINED);
+                    let text_style = Style::default().fg(theme.text);
+                    let title = tr(title);
+                    // a translated title may not start with its key, the key follows it then
+                    if !title.to_lowercase().starts_with(key) {
+                        return Line::from(vec![
+                            Span::styled(format!("{} (", title), text_style),
+                            Span::styled(key.to_string(), key_style),
+                            Span::styled(")", text_style),
+                        ]);
+                    }
+                    let (first, rest) = title.split_at(1);
+                    Line::from(vec![
+                        Span::styled(first, key_style),
+                        Span::styled(rest, text_style),
                     ])
                 })
                 .collect();
 
             let tabs = Tabs::new(menu)
-                .select(active_menu_item.into())
-                .block(Block::default().title("Menu").borders(Borders::ALL))
-                .style(Style::default().fg(Color::White))
-                .highlight_style(Style::default().fg(Color::Yellow))
+                .select(usize::from(active_menu_item))
+                .block(Block::default().title(tr("Menu")).borders(Borders::ALL))
+                .style(Style::default().fg(theme.text))
+                .highlight_style(Style::default().fg(theme.highlight))
                 .divider(Span::raw("|"));
 
-            rect.render_widget(tabs, chunks_codes[0]);
+            if app.other_vaults.is_empty() {
+                rect.render_widget(tabs, chunks_codes[0]);
+            } else {
+                let names = app.vault_tabs();
+                // each name with its divider and padding, in the borders
+                let width = names.iter().map(|name| name.chars().count() as u16 + 3).sum::<u16>() + 1;
+                let [menu_area, vaults_area] = Layout::horizontal([
+                    Constraint::Min(0),
+                    Constraint::Length(width.min(chunks_codes[0].width / 2)),
+                ])
+                .areas(chunks_codes[0]);
+                rect.render_widget(tabs, menu_area);
+                let vaults = Tabs::new(names)
+                    .select(app.vault_tab)
+                    .block(Block::default().title(tr("Vaults")).borders(Borders::ALL))
+                    .style(Style::default().fg(theme.text))
+                    .highlight_style(Style::default().fg(theme.highlight))
+                    .divider(Span::raw("|"));
+                rect.render_widget(vaults, vaults_area);
+            }
             match active_menu_item {
-                MenuItem::Home => rect.render_widget(render_home(), chunks_codes[1]),
+                _ if app.locked => {
+                    let needs_passphrase = matches!(app.store, Some(Store::Vault { .. }));
+                    let locked = render_locked(&app.unlock, needs_passphrase, theme);
+                    rect.render_widget(locked, chunks_codes[1])
+                }
+                MenuItem::Home => rect.render_widget(render_home(theme), chunks_codes[1]),
+                MenuItem::Codes if app.view == CodesView::Zoom => {
+                    let area = chunks_codes[1];
+                    let zoomed = render_zoomed(&app, code_list_state.selected(), area.height);
+                    rect.render_widget(zoomed, area);
+                }
+                MenuItem::Codes if app.view == CodesView::Archive => {
+                    let mut state = ListState::default();
+                    state.select(Some(app.archive_cursor));
+                    rect.render_stateful_widget(render_archive(&app), chunks_codes[1], &mut state);
+                }
+                MenuItem::Codes if app.view == CodesView::Log => {
+                    rect.render_widget(render_log(&app), chunks_codes[1]);
+                }
+                MenuItem::Codes if app.view == CodesView::Stats => {
+                    render_stats(rect, chunks_codes[1], &app);
+                }
+                MenuItem::Codes if app.view == CodesView::Table => {
+                    let area = chunks_codes[1];
+                    // borders and the header take three rows
+                    let visible_rows = area.height.saturating_sub(3) as usize;
+                    app.list_rows = visible_rows.max(1);
+                    app.list_offset = scroll_offset(
+                        app.list_offset,
+                        code_list_state.selected(),
+                        visible_rows,
+                        app.messages.len(),
+                    );
+                    let table = render_all_codes(&app, code_list_state.selected(), visible_rows);
+                    rect.render_widget(table, area);
+                    // below the header
+                    let rows_area = Rect {
+                        y: area.y + 2,
+                        height: area.height.saturating_sub(3),
+                        ..area
+                    };
+                    render_scrollbar(rect, rows_area, &app, visible_rows);
+                }
                 MenuItem::Codes => {
-                    let codes_chunks = Layout::default()
-                        .direction(Direction::Horizontal)
-                        .constraints(
-                            [
-                                Constraint::Percentage(20),
-                                Constraint::Percentage(40),
-                                Constraint::Percentage(40),
-                            ]
-                            .as_ref(),
-                        )
-                        .split(chunks_codes[1]);
-                    let bar_chunks = Layout::default()
-                        .direction(Direction::Vertical)
-                        .margin(4)
-                        .constraints([Constraint::Percentage(10)].as_ref())
-                        .split(codes_chunks[2]);
-                    let (left, right) = render_code(&code_list_state, &app);
-                    rect.render_stateful_widget(left, codes_chunks[0], &mut code_list_state);
-                    rect.render_widget(right, codes_chunks[1]);
+                    let (list_area, detail_area, bar_area) = codes_layout(chunks_codes[1]);
+                    // only the rows that fit between the list's borders are built and drawn
+                    let visible_rows = list_area.height.saturating_sub(2) as usize;
+                    app.list_rows = visible_rows.max(1);
+                    app.list_offset = scroll_offset(
+                        app.list_offset,
+                        code_list_state.selected(),
+                        visible_rows,
+                        app.messages.len(),
+                    );
+                    let (left, right) = render_code(
+                        &code_list_state,
+                        &app,
+                        visible_rows,
+                        detail_area.map(|area| area.width),
+                    );
+                    let mut visible_state = ListState::default();
+                    visible_state.select(
+                        code_list_state
+                            .selected()
+                            .and_then(|selected| selected.checked_sub(app.list_offset)),
+                    );
+                    rect.render_stateful_widget(left, list_area, &mut visible_state);
+                    let rows_area = list_area.inner(Margin {
+                        vertical: 1,
+                        horizontal: 0,
+                    });
+                    render_scrollbar(rect, rows_area, &app, visible_rows);
+                    if let Some(detail_area) = detail_area {
+                        rect.render_widget(right, detail_area);
+                    }
                     //progress bar
-                    if app.keys.len() > 0 {
+                    if !app.vault.accounts.is_empty() {
+                        let selected = app.selected_account(code_list_state.selected());
+                        let period = selected.map_or(PERIOD, |account| account.period);
                         let gauge = Gauge::default()
-                            .block(Block::default().title("30s Timer").borders(Borders::ALL))
-                            .gauge_style(Style::default().fg(Color::Green))
-                            .ratio(app.progress);
-                        rect.render_widget(gauge, bar_chunks[0]);
+                            .block(
+                                Block::default()
+                                    .title(if app.time_offset == 0 {
+                                        trf("{}s Timer", &[&period])
+                                    } else {
+                                        trf(
+                                            "{}s Timer (clock {}s)",
+                                            &[&period, &format!("{:+}", app.time_offset)],
+                                        )
+                                    })
+                                    .borders(Borders::ALL),
+                            )
+                            .gauge_style(Style::default().fg(theme.key))
+                            .ratio(selected.map_or(0.0, |account| app.progress(account)));
+                        rect.render_widget(gauge, bar_area);
                     }
                 }
                 MenuItem::AddCode => {
-                    // input for gen code
-                    let account = Paragraph::new(app.account.as_ref())
-                        .style(match app.input_mode {
-                            InputMode::Normal => Style::default(),
-                            InputMode::Editing => Style::default().fg(Color::Yellow),
+                    // the focused field is yellow while typing into it and cyan otherwise
+                    let field_style = |field| match (field == focused_field, app.input_mode) {
+                        (false, _) => Style::default(),
+                        (true, InputMode::Insert) => Style::default().fg(theme.highlight),
+                        (true, InputMode::Normal) => Style::default().fg(theme.info),
+                    };
+                    // issuer, the service the account belongs to
+                    let issuer = Paragraph::new(app.issuer.as_str())
+                        .style(field_style(InputField::Issuer))
+                        .block(Block::default().borders(Borders::ALL).title(tr("issuer")));
+                    rect.render_widget(issuer, chunks[1]);
+                    // icon shown with the account, the issuer's when left empty
+                    let icon_title = match cli_totp::account::suggest_icon(&app.issuer) {
+                        Some(icon) => trf("icon (optional, {} for this issuer)", &[&icon]),
+                        None => tr("icon (optional, e.g. an emoji)").to_string(),
+                    };
+                    let iconinput = Paragraph::new(app.icon.as_str())
+                        .style(field_style(InputField::Icon))
+                        .block(Block::default().borders(Borders::ALL).title(icon_title));
+                    let looks = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                        .split(chunks[2]);
+                    rect.render_widget(iconinput, looks[0]);
+                    // color the account is shown in, checked as it is typed
+                    let color_valid = app.color.trim().is_empty() || parse_color(&app.color).is_some();
+                    let colorinput = Paragraph::new(app.color.as_str())
+                        .style(if color_valid {
+                            field_style(InputField::Color)
+                        } else {
+                            Style::default().fg(theme.error)
                         })
-                        .block(Block::default().borders(Borders::ALL).title("address"));
-                    rect.render_widget(account, chunks[1]);
+                        .block(Block::default().borders(Borders::ALL).title(if color_valid {
+                            tr("color (optional, a name like red or #rrggbb)")
+                        } else {
+                            tr("color: not a name like red or #rrggbb")
+                        }));
+                    rect.render_widget(colorinput, looks[1]);
+                    // input for gen code
+                    let account = Paragraph::new(app.account.as_str())
+                        .style(field_style(InputField::Account))
+                        .block(Block::default().borders(Borders::ALL).title(tr("address")));
+                    rect.render_widget(account, chunks[3]);
                     // address
-                    let keyinput = Paragraph::new(app.key.as_ref())
-                        .style(match app.input_mode {
-                            InputMode::Normal => Style::default(),
-                            InputMode::Editing => Style::default().fg(Color::Yellow),
-                        })
-                        .block(Block::default().borders(Borders::ALL).title("secrectkey"));
-                    rect.render_widget(keyinput, chunks[2]);
+                    // the secret is checked as it is typed, a wrong one would only give wrong codes
+                    let key_error = secret_error(&app.key, app.encoding).filter(|_| !app.legacy);
+                    let keyinput =
+                        Paragraph::new(app.key.as_str())
+                            .style(match &key_error {
+                                Some(_) => Style::default().fg(theme.error),
+                                None => field_style(InputField::Key),
+                            })
+                            .block(Block::default().borders(Borders::ALL).title(
+                                match &key_error {
+                                    Some(error) => {
+                                        trf("secrectkey ({}): {}", &[&app.encoding.name(), error])
+                                    }
+                                    None if app.legacy && app.editing.is_some() => tr(
+                                        "secrectkey (legacy codes, <Ctrl+L> changes, empty keeps the current one)",
+                                    )
+                                    .to_string(),
+                                    None if app.legacy => {
+                                        tr("secrectkey (legacy codes, <Ctrl+L> changes)").to_string()
+                                    }
+                                    None if app.editing.is_some() => trf(
+                                        "secrectkey ({}, empty keeps the current one, <Ctrl+L> for legacy codes)",
+                                        &[&app.encoding.name()],
+                                    ),
+                                    None => trf(
+                                        "secrectkey ({}, <Ctrl+E> changes, <Ctrl+L> for legacy codes)",
+                                        &[&app.encoding.name()],
+                                    ),
+                                },
+                            ));
+                    rect.render_widget(keyinput, chunks[4]);
+                    // T0, the unix time the counter starts from
+                    let t0input = Paragraph::new(app.t0.as_str())
+                        .style(field_style(InputField::T0))
+                        .block(
+                            Block::default()
+                                .borders(Borders::ALL)
+                                .title(tr("t0 (optional)")),
+                        );
+                    rect.render_widget(t0input, chunks[5]);
+                    // free text kept with the account, e.g. where the backup codes are
+                    let noteinput = Paragraph::new(app.note.as_str())
+                        .style(field_style(InputField::Note))
+                        .block(
+                            Block::default()
+                                .borders(Borders::ALL)
+                                .title(tr("note (optional)")),
+                        );
+                    rect.render_widget(noteinput, chunks[6]);
+                    // recovery codes handed out at enrollment
+                    let recoveryinput = Paragraph::new(app.recovery.as_str())
+                        .style(field_style(InputField::Recovery))
+                        .block(
+                            Block::default()
+                                .borders(Borders::ALL)
+                                .title(tr("recovery codes (optional, separated by spaces)")),
+                        );
+                    rect.render_widget(recoveryinput, chunks[7]);
+                    // a picture of the enrollment QR code, read on <Enter> to fill in the rest
+                    let qrinput = Paragraph::new(app.qr_path.as_str())
+                        .style(field_style(InputField::QrPath))
+                        .block(Block::default().borders(Borders::ALL).title(tr(
+                            "QR image path (optional, <Enter> fills in the form from it)",
+                        )));
+                    rect.render_widget(qrinput, chunks[8]);
 
-                    let instructions = Paragraph::new(vec![
-                        Spans::from(vec![Span::raw("Press <Tab> To change Input")]),
-                        Spans::from(vec![Span::raw("Press <Esc> to access the Menu")]),
-                    ])
-                    .block(
+                    // which mode the keys are in, typing or commands
+                    let (mode, instructions) = match app.input_mode {
+                        InputMode::Insert => (
+                            tr("-- INSERT --"),
+                            vec![
+                                Line::from(vec![Span::raw(match &app.editing {
+                                    Some(name) => trf(
+                                        "Editing {}, <Tab> or <Shift+Tab> change Input and <Enter> saves it",
+                                        &[name],
+                                    ),
+                                    None => tr("Press <Tab> or <Shift+Tab> To change Input and <Enter> to add the account").to_string(),
+                                })]),
+                                Line::from(vec![Span::raw(tr("Press <Esc> to access the Menu"))]),
+                            ],
+                        ),
+                        InputMode::Normal => (
+                            tr("-- NORMAL --"),
+                            vec![
+                                Line::from(vec![Span::raw(tr(
+                                    "Press 'i' or <Enter> to type into the form again",
+                                ))]),
+                                Line::from(vec![Span::raw(tr(
+                                    "Press <Tab> or the arrows to change tabs",
+                                ))]),
+                            ],
+                        ),
+                    };
+                    let instructions = Paragraph::new(instructions).block(
                         Block::default()
                             .borders(Borders::ALL)
-                            .style(Style::default().fg(Color::LightCyan))
-                            .title("Instructions")
+                            .style(Style::default().fg(theme.info))
+                            .title(mode)
                             .border_type(BorderType::Plain),
                     );
-                    rect.render_widget(instructions, chunks[3]);
+                    rect.render_widget(instructions, chunks[9]);
                 }
             }
 
             rect.render_widget(copyright, chunks_codes[2]);
+            if let Some(uri) = &app.qr_shown {
+                let (popup, width, height) = render_qr(uri, size, theme);
+                let area = popup_area(size, width, height);
+                rect.render_widget(Clear, area);
+                rect.render_widget(popup, area);
+            }
+            if let Some(passwd) = &app.passwd {
+                let area = popup_area(size, 50, 9);
+                rect.render_widget(Clear, area);
+                rect.render_widget(render_passwd(passwd, theme), area);
+            }
+            // nothing is drawn over the lock screen
+            if let Some(switcher) = app.switcher.as_ref().filter(|_| !app.locked) {
+                let area = popup_area(size, 50, switcher.profiles.len() as u16 + 7);
+                rect.render_widget(Clear, area);
+                rect.render_widget(render_switcher(switcher, theme), area);
+            }
+            if let Some(popup) = app.popup.as_ref().filter(|_| !app.locked) {
+                let (popup, height) = render_popup(popup, theme);
+                let area = popup_area(size, POPUP_WIDTH, height);
+                rect.render_widget(Clear, area);
+                rect.render_widget(popup, area);
+            }
+            if app.no_color {
+                theme::strip_colors(rect.buffer_mut(), theme);
+            }
         })?;
 
-        match rx.recv()? {
-            Event::Input(event) => match event.code {
-                KeyCode::Char('q') => {
-                    if active_menu_keys {
-                        disable_raw_mode()?;
-                        terminal.show_cursor()?;
-                        break;
-                    } else {
-                        if key_input_flag {
-                            app.key.push('q');
-                        } else {
-                            app.account.push('q');
+        // nothing changes on screen between events but the countdowns and codes, which
+        // change on the whole seconds of the clock
+        let received = runtime.block_on(next_event(
+            &mut input,
+            &mut background_rx,
+            app.until_next_second(),
+        ))?;
+        if let (Some(recorder), Event::Input(key)) = (recorder.as_mut(), &received) {
+            // anything typed as a passphrase or into the form stays out of the recording, but
+            // for the few fields that hold nothing secret
+            let passphrase = app.locked
+                || app.passwd.is_some()
+                || app
+                    .switcher
+                    .as_ref()
+                    .is_some_and(|s| s.passphrase.is_some());
+            recorder.input(
+                key,
+                passphrase || app.input_mode == InputMode::Insert && !focused_field.recordable(),
+            )?;
+        }
+        if let Event::Input(_) = received {
+            app.last_input = Instant::now();
+        }
+        match received {
+            // while locked every key goes to the passphrase, <Enter> checks it; the other
+            // vaults have locks of their own, <Tab> and <BackTab> go to them
+            Event::Input(event) if app.locked => match event.code {
+                KeyCode::Char(c) => app.unlock.passphrase.push(c),
+                KeyCode::Backspace => {
+                    app.unlock.passphrase.pop();
+                }
+                KeyCode::Esc => app.unlock.passphrase.clear(),
+                KeyCode::Enter => app.unlock(),
+                KeyCode::Tab | KeyCode::BackTab => {
+                    let right = event.code == KeyCode::Tab;
+                    let selected = app.next_vault_tab(right, code_list_state.selected());
+                    code_list_state.select(selected);
+                }
+                _ => {}
+            },
+            // a popup takes the next key, only 'y' confirms
+            Event::Input(event) if app.popup.is_some() => {
+                let popup = app.popup.take();
+                if let (Some(Popup::DeleteMarked(names)), KeyCode::Char('y')) = (&popup, event.code)
+                {
+                    app.delete_marked(names);
+                    let last = app.messages.len().saturating_sub(1);
+                    code_list_state.select(code_list_state.selected().map(|i| i.min(last)));
+                }
+                if let (Some(Popup::Delete(name)), KeyCode::Char('y')) = (popup, event.code) {
+                    // the list may have moved under the popup, the account is found again by name
+                    if let Some(i) = app.messages.iter().position(|code| code.address == name) {
+                        code_list_state.select(Some(i));
+                        let deleted = remove_code_at_index(&mut code_list_state, &mut app)
+                            .expect("can remove pet");
+                        app.recovery_cursor = None;
+                        app.save();
+                        if let Some(account) = deleted {
+                            hooks::account(hooks::Event::Delete, &account);
                         }
                     }
                 }
-                KeyCode::Char('h') => {
-                    if active_menu_keys {
-                        active_menu_item = MenuItem::Home
-                    } else {
-                        if key_input_flag {
-                            app.key.push('h');
-                        } else {
-                            app.account.push('h');
+            }
+            // the QR code stays up until <Esc>, other keys are swallowed
+            Event::Input(event) if app.qr_shown.is_some() => {
+                if event.code == KeyCode::Esc {
+                    app.qr_shown = None;
+                }
+            }
+            // the passphrase form takes every key while it is open
+            Event::Input(event) if app.passwd.is_some() => {
+                let mut passwd = app.passwd.take().expect("the form is open");
+                let mut keep_open = true;
+                match event.code {
+                    KeyCode::Esc => keep_open = false,
+                    KeyCode::Char(c) => passwd.fields[passwd.focused].push(c),
+                    KeyCode::Backspace => {
+                        passwd.fields[passwd.focused].pop();
+                    }
+                    KeyCode::Up | KeyCode::BackTab => {
+                        passwd.focused = passwd.focused.saturating_sub(1)
+                    }
+                    KeyCode::Down | KeyCode::Tab | KeyCode::Enter if passwd.focused < 2 => {
+                        passwd.focused += 1
+                    }
+                    KeyCode::Enter => keep_open = !app.change_passphrase(&mut passwd),
+                    _ => {}
+                }
+                if keep_open {
+                    app.passwd = Some(passwd);
+                }
+            }
+            // the profile switcher takes every key while it is open
+            Event::Input(event) if app.switcher.is_some() => {
+                let mut switcher = app.switcher.take().expect("the switcher is open");
+                let mut keep_open = true;
+                match (event.code, &mut switcher.passphrase) {
+                    (KeyCode::Esc, _) => keep_open = false,
+                    (KeyCode::Up, None) => switcher.selected = switcher.selected.saturating_sub(1),
+                    (KeyCode::Down, None) if switcher.selected + 1 < switcher.profiles.len() => {
+                        switcher.selected += 1
+                    }
+                    // a profile open already is only shown
+                    (KeyCode::Enter, None)
+                        if switcher
+                            .profiles
+                            .get(switcher.selected)
+                            .is_some_and(|name| app.vault_tab_of(name).is_some()) =>
+                    {
+                        let name = &switcher.profiles[switcher.selected];
+                        let tab = app.vault_tab_of(name).expect("the profile is open");
+                        let selected = app.show_vault_tab(tab, code_list_state.selected());
+                        code_list_state.select(selected);
+                        keep_open = false;
+                    }
+                    (KeyCode::Enter, None)
+                        if !switcher.profiles.is_empty()
+                            && switcher.backoff.remaining().is_zero() =>
+                    {
+                        switcher.passphrase = Some(String::new())
+                    }
+                    (KeyCode::Char(c), Some(passphrase)) => passphrase.push(c),
+                    (KeyCode::Backspace, Some(passphrase)) => {
+                        passphrase.pop();
+                    }
+                    (KeyCode::Enter, Some(passphrase)) => {
+                        let name = switcher.profiles[switcher.selected].clone();
+                        let path = profile::path(&name);
+                        match Vault::load_with_yubikey(&path, passphrase, &yubikey::respond) {
+                            Ok((mut vault, key)) => {
+                                switcher.backoff.succeed();
+                                let upgraded = store::upgrade(&path, &mut vault, &key);
+                                let store = Store::Vault { path, key };
+                                let selected = code_list_state.selected();
+                                app.open_vault_tab(vault, store, Some(name), selected);
+                                code_list_state.select(Some(0));
+                                keep_open = false;
+                                match upgraded {
+                                    Ok(Some((from, backup))) => app.flash(
+                                        trf(
+                                            "Upgraded the vault from version {}, the old file is kept as {}",
+                                            &[&from, &backup.display()],
+                                        ),
+                                        app.theme.accent,
+                                    ),
+                                    Ok(None) => {}
+                                    Err(err) => {
+                                        app.popup = Some(Popup::Error(trf(
+                                            "Cannot upgrade the vault: {}",
+                                            &[&err],
+                                        )))
+                                    }
+                                }
+                            }
+                            Err(err) => {
+                                if let cli_totp::Error::Passphrase = err {
+                                    switcher.backoff.fail();
+                                }
+                                switcher.error = Some(err.to_string());
+                                switcher.passphrase = None;
+                            }
                         }
                     }
+                    _ => {}
                 }
-                KeyCode::Char('c') => {
-                    if active_menu_keys {
-                        active_menu_item = MenuItem::Codes
-                    } else {
-                        if key_input_flag {
-                            app.key.push('c');
-                        } else {
-                            app.account.push('c');
+                if keep_open {
+                    app.switcher = Some(switcher);
+                }
+            }
+            // in insert mode keys go to the focused field of the Add form, <Esc> leaves it
+            Event::Input(event) if app.input_mode == InputMode::Insert => match event.code {
+                KeyCode::Esc => app.input_mode = InputMode::Normal,
+                KeyCode::Tab => focused_field = focused_field.next(),
+                KeyCode::BackTab => focused_field = focused_field.previous(),
+                KeyCode::Backspace => {
+                    app.field_mut(focused_field).pop();
+                }
+                // <Ctrl+E> on the secret goes to the next way of writing it
+                KeyCode::Char('e')
+                    if focused_field == InputField::Key
+                        && event.modifiers.contains(KeyModifiers::CONTROL) =>
+                {
+                    let all = SecretEncoding::ALL;
+                    let at = all.iter().position(|&e| e == app.encoding).unwrap_or(0);
+                    app.encoding = all[(at + 1) % all.len()];
+                }
+                // <Ctrl+L> on the secret switches between the RFC 6238 codes and the first versions'
+                KeyCode::Char('l')
+                    if focused_field == InputField::Key
+                        && event.modifiers.contains(KeyModifiers::CONTROL) =>
+                {
+                    app.legacy = !app.legacy;
+                }
+                KeyCode::Char(c) => app.type_char(focused_field, c),
+                // the form is filled from the QR code, to be checked before it is added
+                KeyCode::Enter if !app.qr_path.trim().is_empty() => match app.scan_qr() {
+                    Ok(()) => {
+                        focused_field = InputField::Issuer;
+                        app.flash(
+                            tr("Read the QR code, check the account and press <Enter> to add it")
+                                .to_string(),
+                            app.theme.accent,
+                        );
+                    }
+                    Err(err) => {
+                        focused_field = InputField::QrPath;
+                        app.popup = Some(Popup::Error(err));
+                    }
+                },
+                // an account is only added with a secret that can give codes
+                KeyCode::Enter
+                    if app.key.trim().is_empty() && app.editing.is_none()
+                        || !app.legacy && secret_error(&app.key, app.encoding).is_some() =>
+                {
+                    focused_field = InputField::Key;
+                }
+                KeyCode::Enter
+                    if !app.color.trim().is_empty() && parse_color(&app.color).is_none() =>
+                {
+                    focused_field = InputField::Color;
+                }
+                KeyCode::Enter => {
+                    focused_field = InputField::Issuer;
+
+                    // call construct message function
+                    let account: String = app.account.drain(..).collect();
+                    let issuer: String = app.issuer.drain(..).collect();
+                    let icon: String = app.icon.drain(..).collect();
+                    let color: String = app.color.drain(..).collect();
+                    let key: String = app.key.drain(..).collect();
+                    // kept as Base32 whatever it was typed in, it was checked above, but
+                    // for the first versions' codes that use the characters themselves
+                    let legacy = std::mem::take(&mut app.legacy);
+                    let key = match legacy {
+                        true => key.trim().to_string(),
+                        false => app.encoding.to_base32(key.trim()).unwrap_or(key),
+                    };
+                    app.encoding = SecretEncoding::default();
+                    // only digits can be typed into the field, so this fails on overflow alone
+                    let t0: u64 = app.t0.drain(..).collect::<String>().parse().unwrap_or(0);
+                    let note: String = app.note.drain(..).collect();
+                    let recovery_codes = app
+                        .recovery
+                        .drain(..)
+                        .collect::<String>()
+                        .split_whitespace()
+                        .map(|code| RecoveryCode {
+                            code: code.to_string(),
+                            used: false,
+                        })
+                        .collect();
+                    let settings = app.scanned.unwrap_or(app.defaults);
+                    let account = Account {
+                        t0,
+                        note,
+                        recovery_codes,
+                        icon: icon.trim().to_string(),
+                        color: color.trim().to_string(),
+                        legacy,
+                        ..settings.account(issuer, account, key)
+                    };
+                    match app.editing.take() {
+                        Some(name) => {
+                            app.update_account(&name, account);
+                            app.input_mode = InputMode::Normal;
+                            active_menu_item = MenuItem::Codes;
+                        }
+                        None => {
+                            app.scanned = None;
+                            app.messages.push(code_constructor(&account, app.now()));
+                            if !account.secret.is_empty() {
+                                app.vault.record(Action::Added, account.name.as_str());
+                                app.vault.accounts.push(account);
+                                app.save();
+                                hooks::account(
+                                    hooks::Event::Add,
+                                    app.vault.accounts.last().expect("just added"),
+                                );
+                            }
                         }
                     }
                 }
+                _ => {}
+            },
+            // in normal mode keys are commands, after moving them where the config says
+            Event::Input(event) => match keymap.translate(event.code) {
+                KeyCode::Char('q') => break,
+                KeyCode::Char('h') => active_menu_item = MenuItem::Home,
+                KeyCode::Char('c') => active_menu_item = MenuItem::Codes,
+                // nothing changes the accounts of a read-only vault
+                KeyCode::Char(c)
+                    if store::read_only()
+                        && (config::changes_vault(c)
+                            || config::action(c) == Some("mark")
+                                && app.recovery_cursor.is_some()) =>
+                {
+                    app.flash(tr("The vault is read-only").to_string(), app.theme.warning)
+                }
+                KeyCode::Enter | KeyCode::Char('i')
+                    if active_menu_item == MenuItem::AddCode && store::read_only() =>
+                {
+                    app.flash(tr("The vault is read-only").to_string(), app.theme.warning)
+                }
                 KeyCode::Char('a') => {
-                    if active_menu_keys {
-                        active_menu_item = MenuItem::AddCode;
-                        active_menu_keys = false;
-                    } else {
-                        if key_input_flag {
-                            app.key.push('a');
-                        } else {
-                            app.account.push('a');
-                        }
+                    // a form left half edited becomes a new account
+                    if app.editing.take().is_some() {
+                        app.clear_form();
                     }
+                    active_menu_item = MenuItem::AddCode;
+                    app.input_mode = InputMode::Insert;
+                }
+                // the form is filled in the guard, nothing happens without a selected account
+                KeyCode::Char('e')
+                    if active_menu_item == MenuItem::Codes
+                        && app.start_editing(code_list_state.selected()) =>
+                {
+                    focused_field = InputField::Issuer;
+                    active_menu_item = MenuItem::AddCode;
+                    app.input_mode = InputMode::Insert;
+                }
+                // the archive has its own cursor and keys
+                KeyCode::Up if app.view == CodesView::Archive => {
+                    app.archive_cursor = app.archive_cursor.saturating_sub(1);
+                }
+                KeyCode::Down if app.view == CodesView::Archive => {
+                    let last = app.archived().len().saturating_sub(1);
+                    app.archive_cursor = (app.archive_cursor + 1).min(last);
+                }
+                KeyCode::Char('u') if app.view == CodesView::Archive => app.restore(),
+                KeyCode::Char('d') if app.view == CodesView::Archive => app.delete_archived(),
+                // the marked accounts, when there are, instead of the selected one
+                KeyCode::Char('x') if app.view != CodesView::Archive && !app.marked.is_empty() => {
+                    app.archive_marked();
+                    let last = app.messages.len().saturating_sub(1);
+                    code_list_state.select(code_list_state.selected().map(|i| i.min(last)));
+                }
+                KeyCode::Char('d') if app.view != CodesView::Archive && !app.marked.is_empty() => {
+                    app.popup = Some(Popup::DeleteMarked(app.marked.clone()));
+                }
+                KeyCode::Esc if !app.marked.is_empty() => app.marked.clear(),
+                KeyCode::Char('x') if app.view != CodesView::Archive => {
+                    app.archive(code_list_state.selected());
+                    let last = app.messages.len().saturating_sub(1);
+                    code_list_state.select(code_list_state.selected().map(|i| i.min(last)));
+                }
+                KeyCode::Char('X') => {
+                    app.view = if app.view == CodesView::Archive {
+                        CodesView::List
+                    } else {
+                        app.archive_cursor = 0;
+                        CodesView::Archive
+                    };
                 }
                 KeyCode::Char('d') => {
-                    if active_menu_keys {
-                        remove_code_at_index(&mut code_list_state, &mut app)
-                            .expect("can remove pet");
+                    app.popup = app
+                        .selected_account(code_list_state.selected())
+                        .map(|account| Popup::Delete(account.name.clone()));
+                }
+
+                // one check at a time
+                KeyCode::Char('t') if !matches!(app.clock_check, Some(ClockCheck::Pending)) => {
+                    app.clock_check = Some(ClockCheck::Pending);
+                    let tx = background_tx.clone();
+                    runtime.spawn_blocking(move || {
+                        let check = match ntp::clock_drift(ntp::SERVER) {
+                            Ok(drift) => ClockCheck::Drift(drift),
+                            Err(err) => ClockCheck::Failed(err.to_string()),
+                        };
+                        // the UI may already be gone, nothing to do then
+                        let _ = tx.send(Event::Clock(check));
+                    });
+                }
+                KeyCode::Char(c @ ('+' | '-')) => {
+                    app.time_offset += if c == '+' { 1 } else { -1 };
+                    app.update();
+                }
+                KeyCode::Char('L') => app.lock_all(),
+                KeyCode::Char('R') => {
+                    app.sync_vault();
+                    let last = app.messages.len().saturating_sub(1);
+                    code_list_state.select(code_list_state.selected().map(|i| i.min(last)));
+                }
+                KeyCode::Char('m') => {
+                    app.mask = !app.mask;
+                    app.revealed = None;
+                }
+                KeyCode::Char('r') => {
+                    app.revealed = code_list_state
+                        .selected()
+                        .and_then(|i| app.messages.get(i))
+                        .map(|code| (code.address.clone(), Instant::now()));
+                    if let Some((name, _)) = app.revealed.clone() {
+                        app.mark_used(&name);
+                    }
+                }
+                KeyCode::Char('s') if active_menu_item == MenuItem::Codes => {
+                    app.show_qr(code_list_state.selected());
+                }
+                KeyCode::Char('o') => {
+                    app.recent_first = !app.recent_first;
+                    let selected = code_list_state.selected();
+                    code_list_state.select(app.sort_messages(selected));
+                }
+                KeyCode::Char('k') => {
+                    let selected = code_list_state
+                        .selected()
+                        .and_then(|i| app.messages.get(i))
+                        .map(|code| code.address.clone());
+                    app.secret_shown = if app.secret_shown == selected {
+                        None
                     } else {
-                        if key_input_flag {
-                            app.key.push('d');
-                        } else {
-                            app.account.push('d');
+                        if let Some(name) = &selected {
+                            app.vault.record(Action::Revealed, name.as_str());
+                            app.save();
                         }
+                        selected
+                    };
+                }
+                KeyCode::Char('b') => {
+                    let has_codes = !app
+                        .selected_recovery_codes(code_list_state.selected())
+                        .is_empty();
+                    app.recovery_cursor = match app.recovery_cursor {
+                        None if has_codes => Some(0),
+                        _ => None,
+                    };
+                }
+                KeyCode::Char(' ') if app.recovery_cursor.is_some() => {
+                    let cursor = app.recovery_cursor.unwrap_or(0);
+                    let selected = code_list_state.selected();
+                    if let Some(code) = app.selected_recovery_codes_mut(selected).get_mut(cursor) {
+                        code.used = !code.used;
                     }
+                    app.save();
                 }
-
-                // KeyCode::Char('e') => {
-                //     app.input_mode = InputMode::Editing;
-                // }
-                KeyCode::Char(c) => {
-                    active_menu_keys = false;
-                    if key_input_flag {
-                        app.key.push(c);
-                    } else {
-                        app.account.push(c);
+                // <Space> marks the selected account and goes on to the next one
+                KeyCode::Char(' ')
+                    if active_menu_item == MenuItem::Codes && app.view != CodesView::Archive =>
+                {
+                    if let Some(selected) = code_list_state.selected() {
+                        app.toggle_mark(selected);
+                        let last = app.messages.len().saturating_sub(1);
+                        code_list_state.select(Some((selected + 1).min(last)));
                     }
                 }
-                KeyCode::Esc => {
-                    active_menu_keys = true;
+                KeyCode::Char('f')
+                    if active_menu_item == MenuItem::Codes && app.view != CodesView::Archive =>
+                {
+                    let selected = code_list_state.selected();
+                    code_list_state.select(app.toggle_pin(selected));
                 }
-
-                KeyCode::Tab => {
-                    if key_input_flag {
-                        key_input_flag = false
+                // the pinned accounts are copied with their number, wherever the selection is
+                KeyCode::Char(digit @ '1'..='9')
+                    if active_menu_item == MenuItem::Codes && app.view != CodesView::Archive =>
+                {
+                    app.copy_pinned(digit as usize - '0' as usize);
+                }
+                KeyCode::Char('p') => {
+                    app.switcher = Some(Switcher {
+                        profiles: profile::list(),
+                        selected: 0,
+                        passphrase: None,
+                        error: None,
+                        backoff: backoff::Backoff::load(),
+                    });
+                }
+                KeyCode::Char(c @ ('[' | ']')) => {
+                    let selected = app.next_vault_tab(c == ']', code_list_state.selected());
+                    code_list_state.select(selected);
+                }
+                KeyCode::Char('W') => {
+                    let selected = app.close_vault_tab(code_list_state.selected());
+                    code_list_state.select(selected);
+                }
+                KeyCode::Char('P') => match &app.store {
+                    Some(Store::Vault { .. }) => app.passwd = Some(Passwd::default()),
+                    _ => app.flash(
+                        tr("Only vault files have a passphrase to change").to_string(),
+                        app.theme.warning,
+                    ),
+                },
+                KeyCode::Char('w') => {
+                    app.show_window = !app.show_window;
+                }
+                // typed once the countdown is over, pressed again it is not
+                KeyCode::Char('T') if active_menu_item == MenuItem::Codes => {
+                    if app.typing.take().is_some() {
+                        app.flash.take();
                     } else {
-                        key_input_flag = true
+                        let at = app.now() + app.autotype_delay;
+                        app.typing = code_list_state
+                            .selected()
+                            .and_then(|i| app.messages.get(i))
+                            .map(|code| (code.address.clone(), at));
+                        app.update();
                     }
                 }
-
-                KeyCode::Enter => {
-                    key_input_flag = false;
-
-                    // call construct message function
-                    let account: String = app.account.drain(..).collect();
-                    let key: String = app.key.drain(..).collect();
-                    if key.len() > 0 {
-                        app.keys.push((key.clone(), account.clone(), 0))
+                KeyCode::Char(c @ ('z' | 'v' | 'l' | 'S')) => {
+                    let view = match c {
+                        'z' => CodesView::Zoom,
+                        'v' => CodesView::Table,
+                        'S' => CodesView::Stats,
+                        _ => CodesView::Log,
+                    };
+                    app.view = if app.view == view {
+                        CodesView::List
                     } else {
-                        //
-                    }
-                    let codemsg = code_constructor(key, account);
-                    app.messages.push(codemsg.unwrap());
+                        view
+                    };
                 }
 
-                KeyCode::Backspace => {
-                    if key_input_flag {
-                        app.key.pop();
-                    } else {
-                        app.account.pop();
-                    }
+                // <Enter> on an account copies its code
+                KeyCode::Enter if active_menu_item == MenuItem::Codes => {
+                    app.copy_code(code_list_state.selected());
+                }
+                // <Enter> or 'i' on the Add form goes back to typing into the focused field
+                KeyCode::Enter | KeyCode::Char('i') if active_menu_item == MenuItem::AddCode => {
+                    app.input_mode = InputMode::Insert;
+                }
+                // <Tab>, <Shift+Tab> and the arrows move between the tabs
+                KeyCode::Tab | KeyCode::Right => active_menu_item = active_menu_item.next(),
+                KeyCode::BackTab | KeyCode::Left => active_menu_item = active_menu_item.previous(),
+
+                // while the recovery codes are shown the arrows and <Space> work on them
+                KeyCode::Down if app.recovery_cursor.is_some() => {
+                    let len = app
+                        .selected_recovery_codes(code_list_state.selected())
+                        .len();
+                    app.recovery_cursor = app.recovery_cursor.map(|c| (c + 1) % len.max(1));
+                }
+                KeyCode::Up if app.recovery_cursor.is_some() => {
+                    let len = app
+                        .selected_recovery_codes(code_list_state.selected())
+                        .len();
+                    app.recovery_cursor = app
+                        .recovery_cursor
+                        .map(|c| c.checked_sub(1).unwrap_or(len.saturating_sub(1)));
                 }
 
                 KeyCode::Down => {
-                    if active_menu_keys {
-                        if let Some(selected) = code_list_state.selected() {
-                            let number_of_codes_gens = app.messages.len();
-                            if selected >= number_of_codes_gens - 1 {
-                                code_list_state.select(Some(0));
-                            } else {
-                                code_list_state.select(Some(selected + 1));
-                            }
+                    if let Some(selected) = code_list_state.selected() {
+                        let number_of_codes_gens = app.messages.len();
+                        if selected >= number_of_codes_gens - 1 {
+                            code_list_state.select(Some(0));
+                        } else {
+                            code_list_state.select(Some(selected + 1));
                         }
                     }
                 }
                 KeyCode::Up => {
-                    if active_menu_keys {
-                        if let Some(selected) = code_list_state.selected() {
-                            let number_of_codes_gens = app.messages.len();
-                            if selected > 0 {
-                                code_list_state.select(Some(selected - 1));
-                            } else {
-                                code_list_state.select(Some(number_of_codes_gens - 1));
-                            }
+                    if let Some(selected) = code_list_state.selected() {
+                        let number_of_codes_gens = app.messages.len();
+                        if selected > 0 {
+                            code_list_state.select(Some(selected - 1));
+                        } else {
+                            code_list_state.select(Some(number_of_codes_gens - 1));
                         }
                     }
                 }
+                // a page at a time, stopping at the ends instead of wrapping around
+                KeyCode::PageDown | KeyCode::PageUp | KeyCode::Home | KeyCode::End
+                    if !app.messages.is_empty() =>
+                {
+                    let selected = code_list_state.selected().unwrap_or(0);
+                    let last = app.messages.len() - 1;
+                    code_list_state.select(Some(match event.code {
+                        KeyCode::PageDown => (selected + app.list_rows).min(last),
+                        KeyCode::PageUp => selected.saturating_sub(app.list_rows),
+                        KeyCode::Home => 0,
+                        _ => last,
+                    }));
+                }
                 _ => {}
             },
             Event::Tick => {
                 app.update();
             }
+            Event::Resize => {}
+            Event::Clock(check) => {
+                app.clock_check = Some(check);
+            }
+            Event::TimeSource(sample) => {
+                // keep using the last good offset if the source has a hiccup
+                if sample.is_ok() || !matches!(app.source_offset, Some(Ok(_))) {
+                    app.source_offset = Some(sample);
+                }
+                app.update();
+            }
+        }
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.state(session::State {
+                tab: format!("{:?}", active_menu_item),
+                menu_keys: app.input_mode == InputMode::Normal,
+                secret_field: focused_field == InputField::Key,
+                accounts: app.messages.len(),
+                selected: code_list_state.selected(),
+            })?;
         }
     }
 
+    // told about on the terminal given back
+    drop(screen);
+    if let Some(remote) = app.sync.as_ref().filter(|remote| remote.on_exit) {
+        for path in app.vault_files() {
+            if let Err(err) = remote.sync(&path, webdav::Direction::Both) {
+                eprintln!("Cannot sync {}: {}", path.display(), err);
+            }
+        }
+    }
     Ok(())
 }
 
 // Home Layout
-fn render_home<'a>() -> Paragraph<'a> {
+fn render_home<'a>(theme: Theme) -> Paragraph<'a> {
     let home = Paragraph::new(vec![
-        Spans::from(vec![Span::raw("")]),
-        Spans::from(vec![Span::styled(
-            "Time-based One-time Password (TOTP) Authenticator",
-            Style::default().fg(Color::LightGreen),
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::styled(
+            tr("Time-based One-time Password (TOTP) Authenticator"),
+            Style::default().fg(theme.accent),
         )]),
-        Spans::from(vec![Span::raw("")]),
-        Spans::from(vec![Span::raw("Press 'c' to access Codes")]),
-        Spans::from(vec![Span::raw(
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::raw(tr("Press 'c' to access Codes"))]),
+        Line::from(vec![Span::raw(tr(
+            "<Tab>, <Shift+Tab> and the arrows move between the tabs too.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
             "'a' to generate TOTP  and 'd' to delete the currently selected Code.",
-        )]),
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "'e' edits the selected account, e.g. to give it an icon or a color.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "'w' shows the previous and next codes next to the current one.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "'t' checks the system clock, the most common reason codes get rejected.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "'+' and '-' move the clock used for codes by one second if it cannot be fixed.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "'m' masks the codes and 'r' reveals the selected one for a few seconds.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "'p' opens the vault of another profile in a tab of its own.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "'[' and ']' go between the open vaults and 'W' closes the one shown.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "'P' changes the passphrase of the vault.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "'R' syncs the vault with the WebDAV server of the config.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "'L' locks every vault right away, it also happens after a few idle minutes; each opens again with its passphrase.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "<Enter> copies the code of the selected account.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "'T' types the selected code into another window after a few seconds, for fields that block pasting.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "'z' shows the selected code in big digits, for reading it from afar.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "'v' shows every code at once in a table.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "'s' shows the selected account as a QR code, to enroll it in another app.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "'o' lists the most recently used accounts first, or as they were added.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "'x' archives the selected account and 'X' shows the archive.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "<Space> marks accounts for 'd' and 'x' to act on all at once, <Esc> unmarks them.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "'f' pins the selected account to the top, 1 to 9 copy the first nine pinned anywhere.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "'l' shows the log of accounts added, deleted or revealed.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "'S' shows how often the codes of each account were copied.",
+        ))]),
+        Line::from(vec![Span::raw(tr(
+            "<PageUp>, <PageDown>, <Home> and <End> move through long lists.",
+        ))]),
     ])
     .alignment(Alignment::Center)
     .block(
         Block::default()
             .borders(Borders::ALL)
-            .style(Style::default().fg(Color::White))
-            .title("Home")
+            .style(Style::default().fg(theme.text))
+            .title(tr("Home"))
             .border_type(BorderType::Plain),
     );
     home
 }
 
-// LAYOUT FOR Codes tab
-fn render_code<'a>(code_list_state: &ListState, app: &App) -> (List<'a>, Table<'a>) {
-    // box for the accounts
-    let accounts = Block::default()
-        .borders(Borders::ALL)
-        .style(Style::default().fg(Color::White))
-        .title("TOTS")
-        .border_type(BorderType::Plain);
-    // vecs totp
-    let code_list = app.messages.clone();
-
-    //list of accounts as ListItems
-    let items: Vec<_> = code_list
+// the popup with the QR code and text of `uri`, with the width and height it needs
+// in a terminal of `size`
+fn render_qr<'a>(uri: &str, size: Rect, theme: Theme) -> (Paragraph<'a>, u16, u16) {
+    let mut lines = qr::render(uri).unwrap_or_else(|err| vec![err]);
+    let qr_width = lines
         .iter()
-        .map(|code| {
-            ListItem::new(Spans::from(vec![Span::styled(
-                code.address.clone(),
-                Style::default(),
-            )]))
-        })
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0) as u16;
+    // a QR code that does not fit cannot be scanned, the URI can still be copied by hand
+    let fits = qr_width + 2 <= size.width && lines.len() as u16 + 5 <= size.height;
+    if !fits {
+        lines = vec![tr("Make the terminal bigger to show the QR code").to_string()];
+    }
+    let width = qr_width.max(50).min(size.width.saturating_sub(2));
+    // the URI is wrapped at the popup's width
+    let uri_lines: Vec<String> = uri
+        .chars()
+        .collect::<Vec<_>>()
+        .chunks(width.max(1) as usize)
+        .map(|chunk| chunk.iter().collect())
         .collect();
-
-    //selected from list else default totp object
-    let selected_code = match code_list.get(
-        code_list_state
-            .selected()
-            .expect("there is always a selected code"),
-    ) {
-        Some(r) => r.clone(),
-        _ => Totp::new(),
-    };
-    //make a list of accounts and place it in the box
-    let list = List::new(items).block(accounts).highlight_style(
-        Style::default()
-            .bg(Color::Yellow)
-            .fg(Color::Black)
-            .add_modifier(Modifier::BOLD),
+    let mut text: Vec<Line> = lines
+        .into_iter()
+        .map(|line| Line::from(Span::raw(line)))
+        .collect();
+    text.push(Line::from(""));
+    let height = text.len() + uri_lines.len() + 2;
+    text.extend(
+        uri_lines
+            .into_iter()
+            .map(|line| Line::from(Span::styled(line, Style::default().fg(theme.info)))),
+    );
+    let popup = Paragraph::new(text).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.text))
+            .title(tr("Scan to enroll, <Esc> closes"))
+            .border_type(BorderType::Plain),
     );
+    (popup, width + 2, height as u16)
+}
 
-    let code_detail = Table::new(vec![Row::new(vec![Cell::from(Span::raw(
-        selected_code.key,
-    ))])])
-    .header(Row::new(vec![Cell::from(Span::styled(
-        "Key",
-        Style::default().add_modifier(Modifier::BOLD),
-    ))]))
-    .block(
+// the profile switcher, a list of profiles and the passphrase once one is picked
+fn render_switcher(switcher: &Switcher, theme: Theme) -> Paragraph<'_> {
+    let mut lines = vec![];
+    if switcher.profiles.is_empty() {
+        lines.push(Line::from(trf(
+            "No profiles in {}",
+            &[&profile::dir().display()],
+        )));
+        lines.push(Line::from(tr("start with --vault NAME to create one")));
+    }
+    for (i, name) in switcher.profiles.iter().enumerate() {
+        if i == switcher.selected {
+            lines.push(Line::from(Span::styled(
+                format!("> {}", name),
+                Style::default()
+                    .fg(theme.highlight)
+                    .add_modifier(Modifier::BOLD),
+            )));
+        } else {
+            lines.push(Line::from(format!("  {}", name)));
+        }
+    }
+    lines.push(Line::from(""));
+    match (&switcher.passphrase, &switcher.error) {
+        (Some(passphrase), _) => lines.push(Line::from(trf(
+            "Passphrase: {}",
+            &[&"*".repeat(passphrase.chars().count())],
+        ))),
+        (None, Some(err)) => lines.push(Line::from(Span::styled(
+            err.clone(),
+            Style::default().fg(theme.error),
+        ))),
+        (None, None) => lines.push(Line::from(tr("<Enter> opens, <Esc> cancels"))),
+    }
+    let wait = switcher.backoff.remaining();
+    if switcher.passphrase.is_none() && !wait.is_zero() {
+        lines.push(Line::from(Span::styled(
+            trf(
+                "{} failed attempts, wait {}s",
+                &[&switcher.backoff.failures(), &wait.as_secs()],
+            ),
+            Style::default().fg(theme.warning),
+        )));
+    }
+    Paragraph::new(lines).block(
         Block::default()
             .borders(Borders::ALL)
-            .style(Style::default().fg(Color::White))
-            .title("Detail")
+            .style(Style::default().fg(theme.text))
+            .title(tr("Profiles"))
+            .border_type(BorderType::Plain),
+    )
+}
+
+// the form changing the passphrase, the focused field marked
+fn render_passwd(passwd: &Passwd, theme: Theme) -> Paragraph<'_> {
+    let mut lines = vec![];
+    for (i, label) in ["Current", "New", "Repeat"].into_iter().enumerate() {
+        let text = format!(
+            "{} {:8} {}",
+            if i == passwd.focused { ">" } else { " " },
+            format!("{}:", tr(label)),
+            "*".repeat(passwd.fields[i].chars().count())
+        );
+        lines.push(Line::from(if i == passwd.focused {
+            Span::styled(text, Style::default().fg(theme.highlight))
+        } else {
+            Span::raw(text)
+        }));
+    }
+    lines.push(Line::from(""));
+    let wait = passwd.backoff.remaining();
+    lines.push(match &passwd.error {
+        Some(err) => Line::from(Span::styled(err.clone(), Style::default().fg(theme.error))),
+        None => Line::from(tr("<Enter> changes it, <Esc> cancels")),
+    });
+    if !wait.is_zero() {
+        lines.push(Line::from(Span::styled(
+            trf(
+                "{} failed attempts, wait {}s",
+                &[&passwd.backoff.failures(), &wait.as_secs()],
+            ),
+            Style::default().fg(theme.warning),
+        )));
+    }
+    Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.text))
+            .title(tr("Change passphrase"))
             .border_type(BorderType::Plain),
     )
-    .widths(&[Constraint::Min(1)]);
-    (list, code_detail)
 }
 
-fn code_constructor(key: String, account: String) -> Result<Totp, Box<dyn Error>> {
-    let totpcode = generate_code(key).unwrap();
-    let code_gen = Totp {
-        key: totpcode.to_string(),
-        address: account,
+// the box asking what `popup` asks and how many rows it takes
+fn render_popup(popup: &Popup, theme: Theme) -> (Paragraph<'static>, u16) {
+    let (title, message, hint, color) = match popup {
+        Popup::Delete(name) => (
+            tr("Delete"),
+            trf(
+                "Delete {}? Its secret is gone for good once saved.",
+                &[name],
+            ),
+            tr("'y' deletes it, any other key keeps it"),
+            theme.warning,
+        ),
+        Popup::DeleteMarked(names) => (
+            tr("Delete"),
+            trf(
+                "Delete the {} marked accounts? Their secrets are gone for good once saved.",
+                &[&names.len()],
+            ),
+            tr("'y' deletes them, any other key keeps them"),
+            theme.warning,
+        ),
+        Popup::Error(message) => (
+            tr("Error"),
+            message.clone(),
+            tr("Press any key"),
+            theme.error,
+        ),
     };
-    Ok(code_gen)
+    // inside the borders
+    let mut lines: Vec<Line> = wrap(&message, POPUP_WIDTH as usize - 2)
+        .into_iter()
+        .map(Line::from)
+        .collect();
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        hint,
+        Style::default().fg(theme.dim),
+    )));
+    let height = lines.len() as u16 + 2;
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(color))
+            .title(title)
+            .border_type(BorderType::Plain),
+    );
+    (paragraph, height)
 }
 
-fn remove_code_at_index(
-    code_list_state: &mut ListState,
-    app: &mut App,
-) -> Result<(), Box<dyn Error>> {
-    if let Some(selected) = code_list_state.selected() {
-        app.messages.remove(selected);
-        code_list_state.select(Some(if selected > 1 { selected - 1 } else { 0 }));
+// `text` in lines of at most `width` characters, broken between words; a word longer than a
+// line, like a secret, is cut
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = vec![];
+    let mut line = String::new();
+    for mut word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        while word.chars().count() > width {
+            let (at, _) = word.char_indices().nth(width).expect("the word is longer");
+            lines.push(word[..at].to_string());
+            word = &word[at..];
+        }
+        line.push_str(word);
     }
-    Ok(())
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
 }
 
-// generate TOTP code
-fn generate_code(key: String) -> Result<u64, Box<dyn std::error::Error>> {
-    let t0 = 0;
-    let tx = 30;
-    let start = SystemTime::now();
-    let time_in_seconds = start
-        .duration_since(UNIX_EPOCH)
-        .expect("Time went backwards")
-        .as_secs();
-
-    //HOTP
-    let ct = (time_in_seconds - t0) / tx;
+// a scrollbar on the right border of `area`, where the rows of the list are, once they do not
+// all fit in its `visible` rows
+fn render_scrollbar(rect: &mut Frame, area: Rect, app: &App, visible: usize) {
+    let len = app.messages.len();
+    if len <= visible || area.height == 0 {
+        return;
+    }
+    // the offsets the list can be scrolled to
+    let mut state = ScrollbarState::new(len - visible + 1)
+        .viewport_content_length(visible)
+        .position(app.list_offset);
+    let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+        .begin_symbol(None)
+        .end_symbol(None)
+        .style(Style::default().fg(app.theme.dim));
+    rect.render_stateful_widget(scrollbar, area, &mut state);
+}
 
-    let ctk = key.as_bytes();
+// a `width` x `height` area in the middle of `size`, shrunk to fit
+fn popup_area(size: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(size.width);
+    let height = height.min(size.height);
+    Rect::new(
+        size.x + (size.width - width) / 2,
+        size.y + (size.height - height) / 2,
+        width,
+        height,
+    )
+}
 
-    let keyc = hmac::Key::new(hmac::HMAC_SHA256, &ctk);
-    let s = hmac::sign(&keyc, &ct.to_be_bytes());
-    let code;
-    let mut signature = s.as_ref();
+// print the status line of the account `name` in `vault`, again every second with `follow`
+// The code of the account at `selected` in big digits with its countdown underneath, vertically
+// centered in `height` rows
+fn render_zoomed<'a>(app: &App, selected: Option<usize>, height: u16) -> Paragraph<'a> {
+    let theme = app.theme;
+    let mut lines = vec![];
+    if let Some(account) = app.selected_account(selected) {
+        let now = app.now();
+        let title = if account.issuer.is_empty() {
+            account.name.clone()
+        } else {
+            format!("{} {}", account.issuer, account.name)
+        };
+        lines.push(Line::from(vec![Span::styled(
+            title,
+            Style::default().add_modifier(Modifier::BOLD),
+        )]));
+        lines.push(Line::from(vec![Span::raw("")]));
+        let code = app.display_code(&account.name, account.code_at(now));
+        let remaining = account.totp().seconds_remaining(now);
+        let digits_style = if remaining <= EXPIRING {
+            app.code_style(account)
+        } else {
+            Style::default().fg(theme.accent)
+        };
+        for row in bigfont::render(&code) {
+            lines.push(Line::from(vec![Span::styled(row, digits_style)]));
+        }
+        lines.push(Line::from(vec![Span::raw("")]));
+        let style = if remaining <= EXPIRING {
+            Style::default().fg(theme.error)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(vec![Span::styled(
+            trf("expires in {}s", &[&remaining]),
+            style,
+        )]));
+    }
+    // the border takes two of the rows
+    let padding = (height.saturating_sub(2) as usize).saturating_sub(lines.len()) / 2;
+    let mut text = vec![Line::from(vec![Span::raw("")]); padding];
+    text.extend(lines);
+    Paragraph::new(text).alignment(Alignment::Center).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.text))
+            .title(tr("Zoom, 'z' goes back"))
+            .border_type(BorderType::Plain),
+    )
+}
+
+// The log of the vault, newest first
+fn render_log<'a>(app: &App) -> Table<'a> {
+    let theme = app.theme;
+    let rows: Vec<_> = app
+        .vault
+        .log
+        .iter()
+        .rev()
+        .map(|entry| {
+            Row::new(vec![
+                audit::format_time(entry.time),
+                entry.action.to_string(),
+                entry.subject.clone(),
+            ])
+        })
+        .collect();
+    Table::new(
+        rows,
+        [
+            Constraint::Length(21),
+            Constraint::Length(23),
+            Constraint::Min(1),
+        ],
+    )
+    .header(
+        Row::new(vec![tr("Time"), tr("Action"), tr("Account")])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.text))
+            .title(tr("Log, 'l' goes back"))
+            .border_type(BorderType::Plain),
+    )
+}
+
+// the days of copies drawn for each account in the stats
+const STATS_DAYS: u64 = 30;
+
+// bars the old consoles can draw
+const ASCII_BARS: symbols::bar::Set = symbols::bar::Set {
+    full: "#",
+    seven_eighths: "#",
+    three_quarters: "=",
+    five_eighths: "=",
+    half: "-",
+    three_eighths: "-",
+    one_quarter: "_",
+    one_eighth: "_",
+    empty: " ",
+};
+
+// How often the codes of the accounts were copied: every account together per day above, each
+// account below, the most copied first, to tell which are worth keeping at hand and which
+// could be archived
+fn render_stats(rect: &mut Frame, area: Rect, app: &App) {
+    let theme = app.theme;
+    let now = app.now();
+    let bars = if compat::on() {
+        ASCII_BARS
+    } else {
+        symbols::bar::NINE_LEVELS
+    };
+    // the bars from empty to full, a row of them draws the copies of an account
+    let levels = [
+        bars.empty,
+        bars.one_eighth,
+        bars.one_quarter,
+        bars.three_eighths,
+        bars.half,
+        bars.five_eighths,
+        bars.three_quarters,
+        bars.seven_eighths,
+        bars.full,
+    ];
+    let panes = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(7), Constraint::Min(3)].as_ref())
+        .split(area);
+
+    let days = u64::from(panes[0].width.saturating_sub(2)).min(USAGE_DAYS);
+    let mut total = vec![0; days as usize];
+    for account in app.vault.accounts.iter() {
+        for (day, copies) in account.copies_per_day(now, days).into_iter().enumerate() {
+            total[day] += copies;
+        }
+    }
+    let chart = Sparkline::default()
+        .data(&total)
+        .bar_set(bars)
+        .style(Style::default().fg(theme.accent))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(theme.text))
+                .title(trf("Codes copied per day, last {} days", &[&days]))
+                .border_type(BorderType::Plain),
+        );
+    rect.render_widget(chart, panes[0]);
+
+    let mut accounts: Vec<&Account> = app
+        .vault
+        .accounts
+        .iter()
+        .filter(|account| !account.archived)
+        .collect();
+    accounts.sort_by_key(|account| std::cmp::Reverse(account.copies));
+    let rows: Vec<_> = accounts
+        .into_iter()
+        .map(|account| {
+            let per_day = account.copies_per_day(now, STATS_DAYS);
+            let max = per_day.iter().copied().max().unwrap_or(0).max(1);
+            let trend: String = per_day
+                .iter()
+                // any copy shows, however few next to the busiest day
+                .map(|&copies| levels[((copies * 8).div_ceil(max)) as usize])
+                .collect();
+            let last_copied = match account.last_used {
+                Some(time) => audit::format_time(time),
+                None => tr("never").to_string(),
+            };
+            let name = if account.issuer.is_empty() {
+                account.name.clone()
+            } else {
+                format!("{} {}", account.issuer, account.name)
+            };
+            let style = if account.copies == 0 {
+                Style::default().fg(theme.dim)
+            } else {
+                Style::default()
+            };
+            Row::new(vec![
+                name,
+                account.copies.to_string(),
+                per_day.iter().sum::<u64>().to_string(),
+                last_copied,
+                trend,
+            ])
+            .style(style)
+        })
+        .collect();
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Min(10),
+            Constraint::Length(7),
+            Constraint::Length(8),
+            Constraint::Length(21),
+            Constraint::Length(STATS_DAYS as u16),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            tr("Account"),
+            tr("Copies"),
+            tr("30 days"),
+            tr("Last copied"),
+            tr("Per day"),
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.text))
+            .title(tr("Stats, 'S' goes back"))
+            .border_type(BorderType::Plain),
+    );
+    rect.render_widget(table, panes[1]);
+}
+
+// The archived accounts, the one under the cursor highlighted by the list state
+fn render_archive<'a>(app: &App) -> List<'a> {
+    let theme = app.theme;
+    let items: Vec<_> = app
+        .archived()
+        .into_iter()
+        .map(|i| {
+            let account = &app.vault.accounts[i];
+            ListItem::new(if account.issuer.is_empty() {
+                account.name.clone()
+            } else {
+                format!("{} {}", account.issuer, account.name)
+            })
+        })
+        .collect();
+    let title = if items.is_empty() {
+        tr("Archive is empty, 'x' archives the selected account, 'X' goes back")
+    } else {
+        tr("Archive, 'u' restores, 'd' deletes for good, 'X' goes back")
+    };
+    List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(theme.text))
+                .title(title)
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(theme.highlight)
+                .fg(theme.on_highlight)
+                .add_modifier(Modifier::BOLD),
+        )
+}
+
+// Every account in view with its live code and the seconds it has left, the selected one
+// highlighted; `visible_rows` rows from the list offset on are built
+fn render_all_codes<'a>(app: &App, selected: Option<usize>, visible_rows: usize) -> Table<'a> {
+    let theme = app.theme;
+    let now = app.now();
+    let rows: Vec<_> = app
+        .messages
+        .iter()
+        .enumerate()
+        .skip(app.list_offset)
+        .take(visible_rows)
+        .filter_map(|(i, code)| Some((i, app.account_of(code)?)))
+        .map(|(i, account)| {
+            let remaining = account.totp().seconds_remaining(now);
+            let expires = Span::styled(
+                format!("{}s", remaining),
+                if remaining <= EXPIRING {
+                    Style::default().fg(theme.error)
+                } else {
+                    Style::default()
+                },
+            );
+            let code = Span::styled(
+                app.display_code(&account.name, account.code_at(now)),
+                app.code_style(account),
+            );
+            let row = Row::new(vec![
+                Cell::from(
+                    app.mark_prefix(&account.name)
+                        + &app.pin_prefix(&account.name)
+                        + &icon_prefix(account.icon())
+                        + &account.issuer,
+                ),
+                Cell::from(account.name.clone()),
+                Cell::from(code),
+                Cell::from(expires),
+            ]);
+            if Some(i) == selected {
+                row.style(Style::default().bg(theme.highlight).fg(theme.on_highlight))
+            } else {
+                row.style(account_style(Some(account)))
+            }
+        })
+        .collect();
+    let mut title = tr("All codes, 'v' goes back").to_string();
+    if !app.marked.is_empty() {
+        title += &format!(" {}", trf("{} marked", &[&app.marked.len()]));
+    }
+    if let Some(selected) = selected {
+        if app.messages.len() > visible_rows {
+            title += &format!(" {}/{}", selected + 1, app.messages.len());
+        }
+    }
+    Table::new(
+        rows,
+        [
+            Constraint::Percentage(30),
+            Constraint::Percentage(40),
+            Constraint::Length(11),
+            Constraint::Length(8),
+        ],
+    )
+    .header(
+        Row::new(vec![tr("Issuer"), tr("Account"), tr("Code"), tr("Expires")])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.text))
+            .title(title)
+            .border_type(BorderType::Plain),
+    )
+}
+
+// Layout shown in place of every tab while the app is locked
+fn render_locked(unlock: &Unlock, needs_passphrase: bool, theme: Theme) -> Paragraph<'_> {
+    let mut lines = vec![
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::styled(
+            tr("Locked, the codes are hidden"),
+            Style::default().fg(theme.accent),
+        )]),
+        Line::from(vec![Span::raw("")]),
+    ];
+    if needs_passphrase {
+        lines.push(Line::from(tr(
+            "Type the passphrase of the vault and press <Enter> to show the codes again",
+        )));
+        lines.push(Line::from(format!(
+            "{} {}",
+            tr("Passphrase:"),
+            "*".repeat(unlock.passphrase.chars().count())
+        )));
+    } else {
+        lines.push(Line::from(tr("Press <Enter> to show the codes again")));
+    }
+    if let Some(err) = &unlock.error {
+        lines.push(Line::from(Span::styled(
+            err.clone(),
+            Style::default().fg(theme.error),
+        )));
+    }
+    let wait = unlock.backoff.remaining();
+    if !wait.is_zero() {
+        lines.push(Line::from(Span::styled(
+            trf(
+                "{} failed attempts, wait {}s",
+                &[&unlock.backoff.failures(), &wait.as_secs()],
+            ),
+            Style::default().fg(theme.warning),
+        )));
+    }
+    Paragraph::new(lines).alignment(Alignment::Center).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.text))
+            .title(tr("Locked"))
+            .border_type(BorderType::Plain),
+    )
+}
+
+// first row to show so that `selected` stays inside a window of `visible` rows over `len` rows
+fn scroll_offset(offset: usize, selected: Option<usize>, visible: usize, len: usize) -> usize {
+    let offset = offset.min(len.saturating_sub(visible));
+    match selected {
+        Some(selected) if selected < offset => selected,
+        Some(selected) if selected >= offset + visible => selected + 1 - visible.max(1),
+        _ => offset,
+    }
+}
+
+// Where the list, the details and the timer of the Codes tab go in `area`: side by side when it is
+// wide enough, stacked when it is narrow, without the details when it is tiny
+fn codes_layout(area: Rect) -> (Rect, Option<Rect>, Rect) {
+    if area.width >= NARROW {
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [
+                    Constraint::Percentage(20),
+                    Constraint::Percentage(40),
+                    Constraint::Percentage(40),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+        let bar = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(4)
+            .constraints([Constraint::Percentage(10)].as_ref())
+            .split(panes[2]);
+        return (panes[0], Some(panes[1]), bar[0]);
+    }
+    if area.height >= TINY {
+        let panes = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(area.height / 3),
+                    Constraint::Min(5),
+                    Constraint::Length(3),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+        return (panes[0], Some(panes[1]), panes[2]);
+    }
+    let panes = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(3)].as_ref())
+        .split(area);
+    (panes[0], None, panes[1])
+}
+
+// LAYOUT FOR Codes tab, the codes go in the list when there is no room for the details
+fn render_code<'a>(
+    code_list_state: &ListState,
+    app: &App,
+    visible_rows: usize,
+    detail_width: Option<u16>,
+) -> (List<'a>, Table<'a>) {
+    let theme = app.theme;
+    let codes_in_list = detail_width.is_none();
+    // the values get what the borders and the names leave of the pane
+    let value_width = detail_width.map_or(usize::MAX, |width| width.saturating_sub(13) as usize);
+    // box for the accounts
+    let accounts = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(theme.text))
+        .title({
+            let mut title = match &app.profile {
+                Some(name) => format!("{} ({})", tr("TOTS"), name),
+                None => tr("TOTS").to_string(),
+            };
+            if app.recent_first {
+                title += &format!(" {}", tr("recent first"));
+            }
+            if store::read_only() {
+                title += &format!(" {}", tr("read-only"));
+            }
+            if !app.marked.is_empty() {
+                title += &format!(" {}", trf("{} marked", &[&app.marked.len()]));
+            }
+            // where the selection is once the list does not fit
+            if let Some(selected) = code_list_state.selected() {
+                if app.messages.len() > visible_rows {
+                    title += &format!(" {}/{}", selected + 1, app.messages.len());
+                }
+            }
+            title
+        })
+        .border_type(BorderType::Plain);
+    // vecs totp
+    let code_list = &app.messages;
+
+    //list of the accounts in view as ListItems, the rest is never built
+    let items: Vec<_> = code_list
+        .iter()
+        .skip(app.list_offset)
+        .take(visible_rows)
+        .map(|code| {
+            let account = app.account_of(code);
+            let icon = account.map_or("", |account| account.icon());
+            let mut spans = vec![
+                Span::styled(
+                    app.mark_prefix(&code.address),
+                    Style::default()
+                        .fg(theme.accent)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    app.pin_prefix(&code.address),
+                    Style::default().fg(theme.accent),
+                ),
+                Span::raw(icon_prefix(icon)),
+                Span::raw(code.address.clone()),
+            ];
+            if codes_in_list {
+                spans.push(Span::raw("  "));
+                spans.push(Span::styled(
+                    app.display_code(&code.address, code.code.clone()),
+                    account.map_or(Style::default(), |account| app.code_style(account)),
+                ));
+            }
+            ListItem::new(Line::from(spans)).style(account_style(account))
+        })
+        .collect();
+
+    //selected from list else default totp object
+    let selected_code = match code_list.get(
+        code_list_state
+            .selected()
+            .expect("there is always a selected code"),
+    ) {
+        Some(r) => r.clone(),
+        _ => Totp::new(),
+    };
+    //make a list of accounts and place it in the box
+    let list = List::new(items).block(accounts).highlight_style(
+        Style::default()
+            .bg(theme.highlight)
+            .fg(theme.on_highlight)
+            .add_modifier(Modifier::BOLD),
+    );
+
+    let label = Style::default().add_modifier(Modifier::BOLD);
+    let mut rows = vec![];
+    if let Some(account) = app.account_of(&selected_code) {
+        // long values, like notes, go on as many lines as they need
+        let detail = |name: &'static str, value: String| {
+            let lines: Vec<Line> = wrap(&value, value_width)
+                .into_iter()
+                .map(Line::from)
+                .collect();
+            let height = lines.len() as u16;
+            Row::new(vec![
+                Cell::from(Span::styled(tr(name), label)),
+                Cell::from(Text::from(lines)),
+            ])
+            .height(height)
+        };
+        rows.push(detail("Issuer", account.issuer.clone()));
+        rows.push(detail("Account", account.name.clone()));
+        if !account.site.is_empty() {
+            rows.push(detail("Site", account.site.clone()));
+        }
+        if account.legacy {
+            rows.push(detail("Algorithm", tr("legacy").to_string()));
+        } else {
+            rows.push(detail("Algorithm", account.algorithm.name().to_string()));
+        }
+        rows.push(detail("Digits", account.digits.to_string()));
+        rows.push(detail("Period", format!("{}s", account.period)));
+        if account.t0 != 0 {
+            rows.push(detail("T0", account.t0.to_string()));
+        }
+        if let Some(used) = account.last_used {
+            rows.push(detail("Used", ago(app.now().saturating_sub(used))));
+        }
+
+        // codes of the neighbouring time steps, for when the clocks disagree by a few seconds
+        let dim = Style::default().fg(theme.dim);
+        let neighbour = |name: &'static str, offset: i64| {
+            let totp = account.totp();
+            let code = totp.format(totp.generate_for_step(app.now(), offset));
+            Row::new(vec![
+                Cell::from(Span::styled(tr(name), dim)),
+                Cell::from(Span::styled(app.display_code(&account.name, code), dim)),
+            ])
+        };
+        if app.show_window {
+            rows.push(neighbour("Previous", -1));
+        }
+
+        // seconds until the code rotates, red once it is too late to bother typing it
+        let remaining = account.totp().seconds_remaining(app.now());
+        let expires_style = if remaining <= EXPIRING {
+            Style::default().fg(theme.error)
+        } else {
+            Style::default()
+        };
+        rows.push(Row::new(vec![
+            Cell::from(Span::styled(tr("Code"), label)),
+            Cell::from(Line::from(vec![
+                Span::styled(
+                    app.display_code(&account.name, selected_code.code.clone()),
+                    app.code_style(account),
+                ),
+                Span::raw("  "),
+                Span::styled(trf("expires in {}s", &[&remaining]), expires_style),
+            ])),
+        ]));
+
+        if app.show_window {
+            rows.push(neighbour("Next", 1));
+        }
+
+        // the code of the secret before the account was enrolled again, while it is kept
+        if let (Some(totp), Some(previous)) = (account.previous_totp(app.now()), &account.previous)
+        {
+            let code = totp.format(totp.generate_at(app.now()));
+            rows.push(Row::new(vec![
+                Cell::from(Span::styled(tr("Old code"), dim)),
+                Cell::from(Span::styled(
+                    trf(
+                        "{}  until {}",
+                        &[
+                            &app.display_code(&account.name, code),
+                            &audit::format_time(previous.until),
+                        ],
+                    ),
+                    dim,
+                )),
+            ]));
+        }
+
+        if !account.note.is_empty() {
+            rows.push(detail("Note", account.note.clone()));
+        }
+        if !account.recovery_codes.is_empty() {
+            let unused = account.recovery_codes.iter().filter(|c| !c.used).count();
+            rows.push(detail(
+                "Recovery",
+                trf(
+                    "{} of {} unused, press 'b' to show",
+                    &[&unused, &account.recovery_codes.len()],
+                ),
+            ));
+        }
+
+        // the secret stays hidden unless asked for, and only for the selected account
+        let secret = if app.secret_shown.as_deref() == Some(account.name.as_str()) {
+            account.secret.clone()
+        } else {
+            tr("hidden, press 'k' to show").to_string()
+        };
+        rows.push(detail("Secret", secret));
+    }
+
+    // the recovery codes take the place of the details while they are shown, the details
+    // are headed by the account in its color
+    let mut title = match app.account_of(&selected_code) {
+        Some(account) if !account.color.is_empty() => Line::from(Span::styled(
+            icon_prefix(account.icon()) + &account.name,
+            account_style(Some(account)).add_modifier(Modifier::BOLD),
+        )),
+        _ => Line::from(tr("Detail")),
+    };
+    if let (Some(cursor), Some(account)) = (app.recovery_cursor, app.account_of(&selected_code)) {
+        title = Line::from(tr("Recovery codes, <Space> marks used"));
+        rows = account
+            .recovery_codes
+            .iter()
+            .enumerate()
+            .map(|(i, code)| {
+                let mut style = if code.used {
+                    Style::default()
+                        .fg(theme.dim)
+                        .add_modifier(Modifier::CROSSED_OUT)
+                } else {
+                    Style::default()
+                };
+                if i == cursor {
+                    style = style.bg(theme.highlight).fg(theme.on_highlight);
+                }
+                Row::new(vec![
+                    Cell::from(if code.used { "[x]" } else { "[ ]" }),
+                    Cell::from(code.code.clone()),
+                ])
+                .style(style)
+            })
+            .collect();
+    }
+
+    let code_detail = Table::new(rows, [Constraint::Length(10), Constraint::Min(1)]).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.text))
+            .title(title)
+            .border_type(BorderType::Plain),
+    );
+    (list, code_detail)
+}
+
+// the style of an account's row, in its own color if it has one
+fn account_style(account: Option<&Account>) -> Style {
+    match account.and_then(|account| parse_color(&account.color)) {
+        Some(color) => Style::default().fg(compat::color(color)),
+        None => Style::default(),
+    }
+}
 
-    if signature.len() < 32 {
-        return generate_code(key);
+// `icon` and a space before the name of an account, nothing for accounts without one or
+// in compatibility mode, where emoji break the layout
+fn icon_prefix(icon: &str) -> String {
+    if icon.is_empty() || compat::on() {
+        String::new()
     } else {
-        code = signature
-            .read_u64::<BigEndian>()
-            .with_context(|| format!("could not parse integer"))?
-            % (10_u64.pow(6));
+        format!("{} ", icon)
     }
+}
 
-    Ok(code)
+fn code_constructor(account: &Account, time: u64) -> Totp {
+    Totp {
+        code: account.code_at(time),
+        address: account.name.clone(),
+        step: account.step_at(time),
+    }
 }
 
+// remove the selected account, returning it
+fn remove_code_at_index(
+    code_list_state: &mut ListState,
+    app: &mut App,
+) -> Result<Option<Account>, Box<dyn Error>> {
+    let mut deleted = None;
+    if let Some(selected) = code_list_state.selected() {
+        let code = app.messages.remove(selected);
+        if let Some(i) = app
+            .vault
+            .accounts
+            .iter()
+            .position(|a| a.name == code.address)
+        {
+            deleted = Some(app.vault.accounts.remove(i));
+            app.vault.record(Action::Deleted, code.address);
+        }
+        code_list_state.select(Some(selected.saturating_sub(1)));
+    }
+    Ok(deleted)
+}
+
+// how long `seconds` were, roughly, as in "used 2h ago"
+fn ago(seconds: u64) -> String {
+    match seconds {
+        0..=59 => tr("just now").to_string(),
+        60..=3599 => trf("{} min ago", &[&(seconds / 60)]),
+        3600..=86_399 => trf("{}h ago", &[&(seconds / 3600)]),
+        _ => trf("{} days ago", &[&(seconds / 86_400)]),
+    }
+}
+
+// `code` split in two halves, "123 456" or "1234 5678", to read and type it more easily;
+// the code copied stays in one piece
+fn group_digits(code: &str) -> String {
+    if code.len() < 6 || !code.is_ascii() {
+        return code.to_string();
+    }
+    let (first, second) = code.split_at(code.len() / 2);
+    format!("{} {}", first, second)
+}
+
+// what is wrong with `secret` as typed into the Add form in `encoding`, None if it is
+// empty or usable
+fn secret_error(secret: &str, encoding: SecretEncoding) -> Option<String> {
+    let secret = secret.trim();
+    let wrong = match encoding {
+        SecretEncoding::Base32 => secret
+            .chars()
+            .find(|c| !matches!(c.to_ascii_uppercase(), 'A'..='Z' | '2'..='7' | ' ' | '-' | '=')),
+        SecretEncoding::Hex => secret
+            .chars()
+            .find(|c| !c.is_ascii_hexdigit() && !c.is_whitespace()),
+        SecretEncoding::Raw => None,
+    };
+    if let Some(c) = wrong {
+        return Some(match encoding {
+            SecretEncoding::Hex => trf("'{}' is not hex, only 0-9 and a-f", &[&c]),
+            _ => trf("'{}' is not Base32, only A-Z and 2-7", &[&c]),
+        });
+    }
+    let Some(bytes) = encoding.decode(secret) else {
+        return Some(tr("hex needs two digits for every byte").to_string());
+    };
+    if !secret.is_empty() && bytes.len() < MIN_SECRET_BYTES {
+        return Some(match encoding {
+            SecretEncoding::Base32 => trf(
+                "too short, secrets have at least {} characters",
+                &[&(MIN_SECRET_BYTES * 8 / 5)],
+            ),
+            _ => trf(
+                "too short, secrets have at least {} bytes",
+                &[&MIN_SECRET_BYTES],
+            ),
+        });
+    }
+    None
+}
+
+// the shortest secret services hand out, 80 bits
+const MIN_SECRET_BYTES: usize = 10;
+
+// how long a message flashed in the footer stays
+const FLASH_FOR: Duration = Duration::from_secs(3);
+
+/// Seconds to go to another window before the code is typed into it, unless the config
+/// says otherwise
+const AUTOTYPE_DELAY: u64 = 3;
+
+// what is shown instead of a code in privacy mode
+const MASK: &str = "••••••";
+
+// seconds left at which a code is shown as about to rotate, too late to bother typing it
+const EXPIRING: u64 = 5;
+
+// terminal widths below which the Codes panes are stacked, heights below which the margin
+// goes and below which the Codes tab drops the details
+const NARROW: u16 = 100;
+const SHORT: u16 = 24;
+const TINY: u16 = 16;
+
+// columns of the confirmation and error popups
+const POPUP_WIDTH: u16 = 50;
+
+// how often the external time source is read
+const TIME_SOURCE_INTERVAL: Duration = Duration::from_secs(60);
+
+// seconds since the unix epoch according to the system clock, with sub-second precision
+fn unix_time_f64() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs_f64()
+}
+
+/// The current code of an account, as listed in the Codes tab
 #[derive(Clone)]
 struct Totp {
-    key: String,
+    code: String,
     address: String,
+    /// The time step `code` belongs to
+    step: u64,
 }
 impl Totp {
     fn new() -> Totp {
         Totp {
-            key: String::new(),
+            code: String::new(),
             address: String::new(),
+            step: 0,
         }
     }
 }
 impl PartialEq for Totp {
     fn eq(&self, other: &Self) -> bool {
-        self.key == other.key
+        self.code == other.code
     }
 }
 
 enum Event<I> {
     Input(I),
     Tick,
+    /// The terminal changed size
+    Resize,
+    Clock(ClockCheck),
+    /// Seconds the external time source is ahead of the system clock
+    TimeSource(Result<f64, String>),
+}
+
+// the next thing the UI reacts to: a key or a resize of the terminal, what a background
+// task sends, or a tick once `timeout` is over
+async fn next_event(
+    input: &mut EventStream,
+    background: &mut mpsc::UnboundedReceiver<Event<KeyEvent>>,
+    timeout: Duration,
+) -> Result<Event<KeyEvent>, Box<dyn Error>> {
+    let tick = tokio::time::sleep(timeout);
+    tokio::pin!(tick);
+    loop {
+        tokio::select! {
+            event = input.next() => match event {
+                // Windows reports releasing keys too
+                Some(Ok(CEvent::Key(key))) if key.kind == KeyEventKind::Press => {
+                    return Ok(Event::Input(key))
+                }
+                // redraw for the new size right away rather than on the next tick
+                Some(Ok(CEvent::Resize(..))) => return Ok(Event::Resize),
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Err(err.into()),
+                None => return Err("the terminal closed".into()),
+            },
+            // the sender is kept by the UI, there is always one
+            Some(event) = background.recv() => return Ok(event),
+            _ = &mut tick => return Ok(Event::Tick),
+        }
+    }
+}
+
+/// Outcome of comparing the system clock with an NTP server
+enum ClockCheck {
+    Pending,
+    /// Seconds the system clock is ahead (positive) or behind (negative)
+    Drift(f64),
+    Failed(String),
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 enum MenuItem {
     Home,
     Codes,
     AddCode,
 }
 
+impl MenuItem {
+    /// The tab to the right, wrapping around
+    fn next(self) -> MenuItem {
+        match self {
+            MenuItem::Home => MenuItem::Codes,
+            MenuItem::Codes => MenuItem::AddCode,
+            MenuItem::AddCode => MenuItem::Home,
+        }
+    }
+
+    /// The tab to the left, wrapping around
+    fn previous(self) -> MenuItem {
+        match self {
+            MenuItem::Home => MenuItem::AddCode,
+            MenuItem::Codes => MenuItem::Home,
+            MenuItem::AddCode => MenuItem::Codes,
+        }
+    }
+}
+
 impl From<MenuItem> for usize {
     fn from(input: MenuItem) -> usize {
         match input {
@@ -533,43 +2585,1149 @@ impl From<MenuItem> for usize {
     }
 }
 
+/// The ways the Codes tab can show the accounts
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum CodesView {
+    /// The list of accounts next to the details of the selected one
+    List,
+    /// The selected code in big digits
+    Zoom,
+    /// Every account with its code in one table
+    Table,
+    /// The archived accounts, to restore or delete them
+    Archive,
+    /// The log of sensitive actions, newest first
+    Log,
+    /// How often the codes of each account were copied
+    Stats,
+}
+
+/// The fields of the Add form, in <Tab> order
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum InputField {
+    Issuer,
+    Icon,
+    Color,
+    Account,
+    Key,
+    T0,
+    Note,
+    Recovery,
+    QrPath,
+}
+
+impl InputField {
+    // whether what is typed into the field may go into a session recording: those known to
+    // hold nothing secret, the others and any field added later are redacted
+    fn recordable(self) -> bool {
+        matches!(
+            self,
+            InputField::Issuer | InputField::Icon | InputField::Color | InputField::T0
+        )
+    }
+
+    fn next(self) -> InputField {
+        match self {
+            InputField::Issuer => InputField::Icon,
+            InputField::Icon => InputField::Color,
+            InputField::Color => InputField::Account,
+            InputField::Account => InputField::Key,
+            InputField::Key => InputField::T0,
+            InputField::T0 => InputField::Note,
+            InputField::Note => InputField::Recovery,
+            InputField::Recovery => InputField::QrPath,
+            InputField::QrPath => InputField::Issuer,
+        }
+    }
+
+    fn previous(self) -> InputField {
+        match self {
+            InputField::Issuer => InputField::QrPath,
+            InputField::Icon => InputField::Issuer,
+            InputField::Color => InputField::Icon,
+            InputField::Account => InputField::Color,
+            InputField::Key => InputField::Account,
+            InputField::T0 => InputField::Key,
+            InputField::Note => InputField::T0,
+            InputField::Recovery => InputField::Note,
+            InputField::QrPath => InputField::Recovery,
+        }
+    }
+}
+
+/// A vault open in another tab than the one shown
+struct OpenVault {
+    vault: Vault,
+    store: Option<Store>,
+    profile: Option<String>,
+    locked: bool,
+    /// The account selected in it, selected again once it is shown
+    selected: Option<usize>,
+}
+
+/// Picks a profile and takes the passphrase of its vault
+struct Switcher {
+    profiles: Vec<String>,
+    selected: usize,
+    /// Passphrase typed so far, Some once a profile has been picked
+    passphrase: Option<String>,
+    /// Why the last attempt to open a vault failed
+    error: Option<String>,
+    /// Wrong passphrases so far, <Enter> waits until the next attempt may be made
+    backoff: backoff::Backoff,
+}
+
+/// The passphrase typed on the lock screen
+#[derive(Default)]
+struct Unlock {
+    passphrase: String,
+    /// Why the last attempt failed
+    error: Option<String>,
+    /// Wrong passphrases so far
+    backoff: backoff::Backoff,
+}
+
+/// The form changing the passphrase of the vault
+#[derive(Default)]
+struct Passwd {
+    /// The current passphrase, the new one and the new one again
+    fields: [String; 3],
+    /// Index of the field typed into
+    focused: usize,
+    /// Why the last attempt failed
+    error: Option<String>,
+    /// Wrong current passphrases so far
+    backoff: backoff::Backoff,
+}
+
+/// A box in the middle of the screen that takes the next key
+enum Popup {
+    /// Whether to delete the account of this name, 'y' does
+    Delete(String),
+    /// Whether to delete the marked accounts of these names, 'y' does
+    DeleteMarked(Vec<String>),
+    /// What went wrong, any key dismisses it
+    Error(String),
+}
+
+/// Whether keys are commands or text for the Add form
+#[derive(Copy, Clone, PartialEq, Debug)]
 enum InputMode {
+    /// Keys are commands, e.g. 'c' shows the codes
     Normal,
-    Editing,
+    /// Keys are typed into the focused field of the Add form
+    Insert,
 }
 
 /// App holds the state of the application
 struct App {
     /// Current value of the input box
+    issuer: String,
+    icon: String,
+    color: String,
     account: String,
     key: String,
+    t0: String,
+    note: String,
+    recovery: String,
+    /// Image of an enrollment QR code to fill the form from
+    qr_path: String,
+    /// Settings of the account read from a QR code, those the form has no fields for
+    scanned: Option<config::Defaults>,
+    /// How the secret in the form is written
+    encoding: SecretEncoding,
+    /// Whether the account in the form makes the codes of the first versions
+    legacy: bool,
     /// Current input mode
     input_mode: InputMode,
     /// History of recorded messages
     messages: Vec<Totp>,
-    progress: f64,
-    keys: Vec<(String, String, u64)>,
+    /// Every account with a secret, the codes in `messages` are generated from these
+    vault: Vault,
+    /// Where the vault is saved after every change, None keeps it in memory
+    store: Option<Store>,
+    /// Profile the vault belongs to, None for a vault given as a file or the password store
+    profile: Option<String>,
+    /// The vaults open in the other tabs, in the order of the tabs without the one shown
+    other_vaults: Vec<OpenVault>,
+    /// Where the tab of the vault shown is among the others
+    vault_tab: usize,
+    /// The profile switcher, while it is open
+    switcher: Option<Switcher>,
+    /// The form changing the passphrase, while it is open
+    passwd: Option<Passwd>,
+    /// The confirmation or error shown over everything, while it is open
+    popup: Option<Popup>,
+    /// Why the vault could not be saved the last time
+    save_error: Option<String>,
+    /// The vault file and its directory, those of them others can read
+    exposed: Option<String>,
+    /// Show the previous and next codes in the detail pane
+    show_window: bool,
+    /// How the Codes tab shows the accounts
+    view: CodesView,
+    /// Result of the last clock check against NTP
+    clock_check: Option<ClockCheck>,
+    /// Seconds added to the system clock before computing codes
+    time_offset: i64,
+    /// Idle time after which the app locks itself, None never locks
+    lock_after: Option<Duration>,
+    /// When the last key was pressed
+    last_input: Instant,
+    /// Codes and accounts are hidden until the user comes back
+    locked: bool,
+    /// What was typed on the lock screen
+    unlock: Unlock,
+    /// Name of the external time source, None uses the system clock
+    time_source: Option<&'static str>,
+    /// Last reading of the external time source, relative to the system clock
+    source_offset: Option<Result<f64, String>>,
+    /// Index of the first account shown in the Codes list
+    list_offset: usize,
+    /// How many accounts the Codes list showed last time, what PageUp/PageDown move by
+    list_rows: usize,
+    /// Privacy mode, codes are masked until revealed
+    mask: bool,
+    /// Account revealed in privacy mode and since when
+    revealed: Option<(String, Instant)>,
+    /// How long a revealed code stays visible, None until something else is revealed
+    reveal_for: Option<Duration>,
+    /// Account whose secret is shown in the detail pane
+    secret_shown: Option<String>,
+    /// otpauth:// URI shown as a QR code in a popup
+    qr_shown: Option<String>,
+    /// Cursor in the selected account's recovery codes, Some while they are shown
+    recovery_cursor: Option<usize>,
+    /// Cursor in the archived accounts
+    archive_cursor: usize,
+    /// Names of the accounts marked with <Space>, which 'd' and 'x' act on all at once
+    marked: Vec<String>,
+    /// Sort the Codes list by when the accounts were last used instead of as in the vault
+    recent_first: bool,
+    /// Where copied codes go
+    clipboard: clipboard::Clipboard,
+    /// The last code copied and when, until it is taken off the clipboard
+    copied: Option<(String, Instant)>,
+    /// How long a copied code stays on the clipboard, None leaves it there
+    clear_clipboard_after: Option<Duration>,
+    /// Seconds before a copied code expires to show a desktop notification, None shows none
+    notify_before_expiry: Option<u64>,
+    /// The account whose code was copied and when that code expires, until it is told about
+    expiry_notice: Option<(String, u64)>,
+    /// How codes are typed into the focused window
+    autotype: autotype::Backend,
+    /// Seconds between asking for a code to be typed and typing it
+    autotype_delay: u64,
+    /// The account whose code is typed and at what time, while counting down to it
+    typing: Option<(String, u64)>,
+    /// Message flashed in the footer, its color and since when
+    flash: Option<(String, Color, Instant)>,
+    /// The colors of the UI
+    theme: Theme,
+    /// Draw with attributes instead of colors
+    no_color: bool,
+    /// Name of the account the Add form is editing, None while it adds one
+    editing: Option<String>,
+    /// Settings of the accounts added on the Add tab
+    defaults: config::Defaults,
+    /// Seconds the old secret of an account given a new one in the form keeps giving codes
+    keep_previous_secret: Option<u64>,
+    /// WebDAV folder 'R' syncs the vault file with
+    sync: Option<webdav::Remote>,
 }
 
 impl App {
+    /// Current time in seconds since the unix epoch, taken from the external time
+    /// source when there is one and corrected by the configured offset
+    fn clock(&self) -> f64 {
+        let source_offset = match self.source_offset {
+            Some(Ok(offset)) => offset,
+            _ => 0.0,
+        };
+        unix_time_f64() + source_offset + self.time_offset as f64
+    }
+
+    /// How long until the clock reaches its next whole second, when countdowns and
+    /// codes change
+    fn until_next_second(&self) -> Duration {
+        Duration::from_secs_f64(1.0 - self.clock().rem_euclid(1.0))
+    }
+
+    /// Current time in whole seconds, as used for the TOTP counter
+    fn now(&self) -> u64 {
+        self.clock() as u64
+    }
+
+    /// Hide the codes until the passphrase of the vault is typed again
+    fn lock(&mut self) {
+        self.locked = true;
+        self.unlock = Unlock {
+            backoff: backoff::Backoff::load(),
+            ..Unlock::default()
+        };
+        // the secret is in the QR code
+        self.qr_shown = None;
+        self.passwd = None;
+        self.switcher = None;
+        self.popup = None;
+        self.typing = None;
+    }
+
+    /// Lock every open vault, each unlocked by its own passphrase, and take a code copied
+    /// from them off the clipboard
+    fn lock_all(&mut self) {
+        self.lock();
+        for open in self.other_vaults.iter_mut() {
+            open.locked = true;
+        }
+        if let Some((code, _)) = self.copied.take() {
+            self.clipboard.clear_if(&code);
+        }
+    }
+
+    /// Show the codes again if the passphrase typed on the lock screen is the one of the
+    /// vault shown, not that of its decoy; a vault kept in memory or in the password store
+    /// has none to ask for
+    fn unlock(&mut self) {
+        let typed = std::mem::take(&mut self.unlock.passphrase);
+        let Some(Store::Vault { key, .. }) = &self.store else {
+            self.locked = false;
+            return;
+        };
+        if !self.unlock.backoff.remaining().is_zero() {
+            self.unlock.error = Some(tr("Wait before trying again").to_string());
+            return;
+        }
+        match key.matches(&typed, &yubikey::respond) {
+            Ok(true) => {
+                self.unlock.backoff.succeed();
+                self.unlock = Unlock::default();
+                self.locked = false;
+            }
+            Ok(false) => {
+                self.unlock.backoff.fail();
+                self.unlock.error = Some(cli_totp::Error::Passphrase.to_string());
+            }
+            Err(err) => self.unlock.error = Some(err.to_string()),
+        }
+    }
+
     fn update(&mut self) {
-        for (k, a, _) in self.keys.iter() {
-            let codemsg = code_constructor(k.to_string(), a.to_string()).unwrap();
-            if !self.messages.contains(&(codemsg)) {
-                match self.messages.iter_mut().find(|x| x.address == *a) {
-                    Some(r) => {
-                        r.key = codemsg.key;
-                        self.progress = 0.0;
-                    }
-                    _ => (),
+        if let Some(lock_after) = self.lock_after {
+            if self.last_input.elapsed() >= lock_after {
+                self.lock_all();
+            }
+        }
+        if let (Some((code, at)), Some(after)) = (&self.copied, self.clear_clipboard_after) {
+            if at.elapsed() >= after {
+                self.clipboard.clear_if(code);
+                self.copied = None;
+            }
+        }
+        if let (Some((name, expires)), Some(before)) =
+            (&self.expiry_notice, self.notify_before_expiry)
+        {
+            let now = self.now();
+            // nothing to tell once the code is off the clipboard
+            if self.copied.is_none() {
+                self.expiry_notice = None;
+            } else if now + before >= *expires {
+                // sent in the background
+                let _ = notify::expiring(name, expires.saturating_sub(now));
+                self.expiry_notice = None;
+            }
+        }
+
+        if let Some((name, at)) = self.typing.clone() {
+            let now = self.now();
+            if now >= at {
+                self.typing = None;
+                self.type_code(&name);
+            } else {
+                self.flash(
+                    trf(
+                        "Typing the code of {} in {}s, go to the window to type it into",
+                        &[&name, &(at - now)],
+                    ),
+                    self.theme.warning,
+                );
+            }
+        }
+
+        // a code is only generated again once its account is in another time step
+        let now = self.now();
+        // looked up by name once per tick rather than once per code; the first of accounts
+        // named alike wins, as it does everywhere else
+        let mut accounts = HashMap::with_capacity(self.vault.accounts.len());
+        for account in self.vault.accounts.iter() {
+            accounts.entry(account.name.as_str()).or_insert(account);
+        }
+        for message in self.messages.iter_mut() {
+            let account = accounts.get(message.address.as_str());
+            if let Some(account) = account.filter(|a| a.step_at(now) != message.step) {
+                *message = code_constructor(account, now);
+            }
+        }
+    }
+
+    /// Where we are inside the current time step of `account`, taken from the same
+    /// clock as the codes
+    fn progress(&self, account: &Account) -> f64 {
+        let period = account.period.max(1) as f64;
+        (self.clock() - account.t0 as f64).rem_euclid(period) / period
+    }
+
+    /// Write the vault back to its file, if it has one
+    fn save(&mut self) {
+        let now = self.now();
+        for account in self.vault.accounts.iter_mut() {
+            account.forget_previous(now);
+        }
+        if let Some(store) = &mut self.store {
+            self.save_error = store.save(&self.vault).err().map(|err| err.to_string());
+            if let Some(err) = &self.save_error {
+                warn!(error = %err, "cannot save");
+                self.popup = Some(Popup::Error(trf("Cannot save the vault: {}", &[err])));
+            }
+        }
+    }
+
+    /// Copy the current code of the account at `selected` to the clipboard and remember it was used
+    fn copy_code(&mut self, selected: Option<usize>) {
+        if let Some(account) = self.selected_account(selected) {
+            let name = account.name.clone();
+            self.copy_code_of(&name);
+        }
+    }
+
+    /// Copy the current code of the account `name` to the clipboard and remember it was used
+    fn copy_code_of(&mut self, name: &str) {
+        let now = self.now();
+        let Some(account) = self.vault.accounts.iter().find(|a| a.name == name) else {
+            return;
+        };
+        let (name, code) = (account.name.clone(), account.code_at(now));
+        let expires = now + account.totp().seconds_remaining(now);
+        match self.clipboard.copy(&code) {
+            Ok(()) => {
+                if let Some(account) = self.vault.accounts.iter().find(|a| a.name == name) {
+                    hooks::account(hooks::Event::Copy, account);
+                }
+                self.copied = Some((code, Instant::now()));
+                if self.notify_before_expiry.is_some() {
+                    self.expiry_notice = Some((name.clone(), expires));
                 }
+                self.flash(trf("Copied the code of {}", &[&name]), self.theme.accent);
             }
+            Err(err) => self.popup = Some(Popup::Error(trf("Cannot copy the code: {}", &[&err]))),
         }
+        self.mark_used(&name);
+    }
+
+    /// Names of the pinned accounts in the list, in its order; the first nine are
+    /// copied with the keys 1 to 9
+    fn pinned(&self) -> Vec<&str> {
+        self.messages
+            .iter()
+            .filter(|code| self.account_of(code).is_some_and(|a| a.pinned))
+            .map(|code| code.address.as_str())
+            .collect()
+    }
+
+    /// Copy the code of the `number`th pinned account, counting from 1
+    fn copy_pinned(&mut self, number: usize) {
+        match self.pinned().get(number - 1) {
+            Some(&name) => {
+                let name = name.to_string();
+                self.copy_code_of(&name);
+            }
+            None => self.flash(
+                trf(
+                    "No account is pinned at {}, 'f' pins the selected one",
+                    &[&number],
+                ),
+                self.theme.error,
+            ),
+        }
+    }
+
+    /// Pin the account at `selected` in the list, or unpin it, and list it again
+    fn toggle_pin(&mut self, selected: Option<usize>) -> Option<usize> {
+        let name = self.selected_account(selected)?.name.clone();
+        let account = self.vault.accounts.iter_mut().find(|a| a.name == name)?;
+        account.pinned = !account.pinned;
+        let message = if account.pinned {
+            trf("Pinned {}, copied with its number", &[&name])
+        } else {
+            trf("Unpinned {}", &[&name])
+        };
+        self.flash(message, self.theme.accent);
+        self.save();
+        self.sort_messages(selected)
+    }
+
+    /// The number of the account `name` if it is one of the first nine pinned, for the lists
+    fn pin_prefix(&self, name: &str) -> String {
+        let pinned = self.pinned();
+        match pinned.iter().position(|&p| p == name) {
+            _ if pinned.is_empty() => String::new(),
+            Some(i) if i < 9 => format!("{} ", i + 1),
+            _ => "  ".to_string(),
+        }
+    }
 
-        self.progress += 0.0065;
+    /// Type the current code of the account `name` into the focused window and remember
+    /// it was used
+    fn type_code(&mut self, name: &str) {
+        let now = self.now();
+        let Some(account) = self.vault.accounts.iter().find(|a| a.name == name) else {
+            return;
+        };
+        match autotype::type_text(self.autotype, &account.code_at(now)) {
+            Ok(()) => self.flash(trf("Typed the code of {}", &[&name]), self.theme.accent),
+            Err(err) => self.popup = Some(Popup::Error(trf("Cannot type the code: {}", &[&err]))),
+        }
+        self.mark_used(name);
+    }
+
+    /// Show `message` in the footer for a few seconds
+    fn flash(&mut self, message: String, color: Color) {
+        self.flash = Some((message, color, Instant::now()));
+    }
+
+    /// Show the QR code of the account at `selected`, noting in the log that its secret was shown
+    fn show_qr(&mut self, selected: Option<usize>) {
+        let Some(account) = self.selected_account(selected) else {
+            return;
+        };
+        let (name, uri) = (account.name.clone(), otpauth::to_uri(account));
+        self.qr_shown = Some(uri);
+        self.vault.record(Action::Revealed, name);
+        self.save();
+    }
+
+    /// Remember the account `name` was used just now
+    fn mark_used(&mut self, name: &str) {
+        let now = self.now();
+        if let Some(account) = self.vault.accounts.iter_mut().find(|a| a.name == name) {
+            account.record_use(now);
+            self.save();
+        }
+    }
+
+    /// Put the Codes list in the order asked for, the most recently used first or as in
+    /// the vault, returning where the account at `selected` went
+    fn sort_messages(&mut self, selected: Option<usize>) -> Option<usize> {
+        let address = selected
+            .and_then(|i| self.messages.get(i))
+            .map(|code| code.address.clone());
+        let position = |name: &str| self.vault.accounts.iter().position(|a| a.name == name);
+        let mut order: Vec<(Option<usize>, Totp)> = self
+            .messages
+            .drain(..)
+            .map(|code| (position(&code.address), code))
+            .collect();
+        // pinned accounts go first, accounts never used last, in the order of the vault
+        // like everything else
+        order.sort_by_key(|(index, _)| {
+            let pinned = index.is_some_and(|i| self.vault.accounts[i].pinned);
+            let last_used = index
+                .filter(|_| self.recent_first)
+                .and_then(|i| self.vault.accounts[i].last_used);
+            (!pinned, std::cmp::Reverse(last_used), *index)
+        });
+        self.messages = order.into_iter().map(|(_, code)| code).collect();
+        match address {
+            Some(address) => self.messages.iter().position(|c| c.address == address),
+            None => selected,
+        }
+    }
+
+    /// Encrypt the vault with the new passphrase of `passwd` once its current one checks
+    /// out, true if it did; otherwise `passwd` says why not
+    fn change_passphrase(&mut self, passwd: &mut Passwd) -> bool {
+        let [current, new, repeat] = &passwd.fields;
+        let Some(Store::Vault { key, path }) = &self.store else {
+            return true;
+        };
+        let problem = if !passwd.backoff.remaining().is_zero() {
+            Some(tr("Wait before trying again").to_string())
+        } else if new.is_empty() {
+            Some(tr("The new passphrase is empty").to_string())
+        } else if new != repeat {
+            Some(tr("The new passphrases differ").to_string())
+        } else {
+            match key.matches(current, &yubikey::respond) {
+                Ok(true) => match key.opens_other(path, new, &yubikey::respond) {
+                    Ok(false) => None,
+                    Ok(true) => Some(cli_totp::Error::SamePassphrase.to_string()),
+                    Err(err) => Some(err.to_string()),
+                },
+                Ok(false) => {
+                    passwd.backoff.fail();
+                    passwd.fields[0].clear();
+                    passwd.focused = 0;
+                    Some(cli_totp::Error::Passphrase.to_string())
+                }
+                Err(err) => Some(err.to_string()),
+            }
+        };
+        if let Some(problem) = problem {
+            passwd.error = Some(problem);
+            return false;
+        }
+        passwd.backoff.succeed();
+        let new = passwd.fields[1].clone();
+        let kdf = store::kdf();
+        let store = self.store.as_mut().expect("the vault has a store");
+        let (message, color) =
+            match store.set_passphrase(&mut self.vault, &new, kdf, &yubikey::respond) {
+                Ok(()) => (tr("Changed the passphrase").to_string(), self.theme.accent),
+                Err(err) => (
+                    trf("Cannot change the passphrase: {}", &[&err]),
+                    self.theme.error,
+                ),
+            };
+        self.flash(message, color);
+        true
+    }
+
+    /// Show the accounts of `vault` instead of the current ones, saving to `store` from now on
+    fn open_vault(&mut self, vault: Vault, store: Option<Store>, profile: Option<String>) {
+        let now = self.now();
+        self.messages = vault
+            .accounts
+            .iter()
+            .filter(|account| !account.archived)
+            .map(|account| code_constructor(account, now))
+            .collect();
+        self.vault = vault;
+        self.store = store;
+        self.profile = profile;
+        self.save_error = None;
+        self.exposed = match &self.store {
+            Some(Store::Vault { path, .. }) => Some(permissions::exposed(path))
+                .filter(|exposed| !exposed.is_empty())
+                .map(|exposed| permissions::describe(&exposed)),
+            _ => None,
+        };
+        self.list_offset = 0;
+        self.revealed = None;
+        self.secret_shown = None;
+        self.recovery_cursor = None;
+        self.archive_cursor = 0;
+        self.marked.clear();
+        self.sort_messages(None);
+    }
+
+    /// Open `vault` in a tab of its own, or in the one shown if it has no vault yet
+    fn open_vault_tab(
+        &mut self,
+        vault: Vault,
+        store: Store,
+        profile: Option<String>,
+        selected: Option<usize>,
+    ) {
+        if self.store.is_some() {
+            let shown = self.park(selected);
+            self.other_vaults.insert(self.vault_tab, shown);
+            self.vault_tab = self.other_vaults.len();
+        }
+        self.locked = false;
+        self.open_vault(vault, Some(store), profile);
+    }
+
+    /// Show the vault of the tab `tab`, returning the account to select in it
+    fn show_vault_tab(&mut self, tab: usize, selected: Option<usize>) -> Option<usize> {
+        if tab == self.vault_tab || tab > self.other_vaults.len() {
+            return selected;
+        }
+        let shown = self.park(selected);
+        self.other_vaults.insert(self.vault_tab, shown);
+        let open = self.other_vaults.remove(tab);
+        self.vault_tab = tab;
+        self.unpark(open)
+    }
+
+    /// Show the vault of the tab to the right, or to the left, wrapping around
+    fn next_vault_tab(&mut self, right: bool, selected: Option<usize>) -> Option<usize> {
+        let tabs = self.other_vaults.len() + 1;
+        let tab = if right {
+            (self.vault_tab + 1) % tabs
+        } else {
+            (self.vault_tab + tabs - 1) % tabs
+        };
+        self.show_vault_tab(tab, selected)
+    }
+
+    /// Close the tab of the vault shown and show the next one, unless it is the only one
+    fn close_vault_tab(&mut self, selected: Option<usize>) -> Option<usize> {
+        if self.other_vaults.is_empty() {
+            self.flash(
+                tr("This is the only vault open").to_string(),
+                self.theme.warning,
+            );
+            return selected;
+        }
+        // everything was saved as it was changed
+        drop(self.park(None));
+        let tab = self.vault_tab.min(self.other_vaults.len() - 1);
+        let open = self.other_vaults.remove(tab);
+        self.vault_tab = tab;
+        self.unpark(open)
+    }
+
+    /// The names of the tabs of the open vaults, their profile or else their file
+    fn vault_tabs(&self) -> Vec<String> {
+        let name = |profile: &Option<String>, store: &Option<Store>| match (profile, store) {
+            (Some(profile), _) => profile.clone(),
+            (None, Some(store)) => store.label(),
+            (None, None) => "-".to_string(),
+        };
+        let mut names: Vec<String> = self
+            .other_vaults
+            .iter()
+            .map(|open| name(&open.profile, &open.store))
+            .collect();
+        names.insert(self.vault_tab, name(&self.profile, &self.store));
+        names
+    }
+
+    /// The tab the vault of the profile `name` is open in
+    fn vault_tab_of(&self, name: &str) -> Option<usize> {
+        let mut profiles: Vec<Option<&str>> = self
+            .other_vaults
+            .iter()
+            .map(|open| open.profile.as_deref())
+            .collect();
+        profiles.insert(self.vault_tab, self.profile.as_deref());
+        profiles.iter().position(|&profile| profile == Some(name))
+    }
+
+    /// Sync the vault shown with its copy on the WebDAV server, showing the accounts again
+    /// if the copy was newer
+    fn sync_vault(&mut self) {
+        let Some(remote) = &self.sync else {
+            self.flash(
+                tr("No WebDAV server to sync with, set [sync] in the config").to_string(),
+                self.theme.warning,
+            );
+            return;
+        };
+        let Some(Store::Vault { path, .. }) = &self.store else {
+            self.flash(
+                tr("Only vault files are synced").to_string(),
+                self.theme.warning,
+            );
+            return;
+        };
+        match remote.sync(path, webdav::Direction::Both) {
+            Ok(webdav::Outcome::UpToDate) => self.flash(
+                tr("The vault is the same as on the server").to_string(),
+                self.theme.accent,
+            ),
+            Ok(webdav::Outcome::Pushed) => self.flash(
+                tr("Pushed the vault to the server").to_string(),
+                self.theme.accent,
+            ),
+            Ok(webdav::Outcome::Pulled) => match self.store.as_mut().map(Store::load) {
+                Some(Ok(vault)) => {
+                    let (store, profile) = (self.store.take(), self.profile.take());
+                    self.open_vault(vault, store, profile);
+                    self.flash(
+                        tr("Pulled the vault from the server").to_string(),
+                        self.theme.accent,
+                    );
+                }
+                // the accounts shown are no longer those of the file, saving them would
+                // overwrite what was pulled
+                Some(Err(err)) => {
+                    self.store = None;
+                    self.popup = Some(Popup::Error(trf(
+                        "Cannot open the vault pulled from the server, the one before is in the backups; changes are no longer saved: {}",
+                        &[&err],
+                    )))
+                }
+                None => {}
+            },
+            Err(err) => self.popup = Some(Popup::Error(trf("Cannot sync: {}", &[&err]))),
+        }
+    }
+
+    /// The vault files open in any tab
+    fn vault_files(&self) -> Vec<PathBuf> {
+        std::iter::once(&self.store)
+            .chain(self.other_vaults.iter().map(|open| &open.store))
+            .filter_map(|store| match store {
+                Some(Store::Vault { path, .. }) => Some(path.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // the vault shown, taken out to be kept in a tab
+    fn park(&mut self, selected: Option<usize>) -> OpenVault {
+        // about an account of this vault
+        self.typing = None;
+        self.editing = None;
+        OpenVault {
+            vault: std::mem::take(&mut self.vault),
+            store: self.store.take(),
+            profile: self.profile.take(),
+            locked: self.locked,
+            selected,
+        }
+    }
+
+    // show the vault kept in `open`, returning the account to select in it
+    fn unpark(&mut self, open: OpenVault) -> Option<usize> {
+        self.locked = open.locked;
+        // typed for the vault shown before
+        self.unlock.passphrase.clear();
+        self.unlock.error = None;
+        self.open_vault(open.vault, open.store, open.profile);
+        match open.selected {
+            Some(i) if i < self.messages.len() => Some(i),
+            _ => Some(0),
+        }
+    }
+
+    /// Indices in the vault of the archived accounts, in the order the archive shows them
+    fn archived(&self) -> Vec<usize> {
+        (0..self.vault.accounts.len())
+            .filter(|&i| self.vault.accounts[i].archived)
+            .collect()
+    }
+
+    /// Move the account at `selected` in the list to the archive
+    fn archive(&mut self, selected: Option<usize>) {
+        let Some(code) = selected.and_then(|i| self.messages.get(i)).cloned() else {
+            return;
+        };
+        if let Some(account) = self
+            .vault
+            .accounts
+            .iter_mut()
+            .find(|a| a.name == code.address)
+        {
+            account.archived = true;
+            self.vault.record(Action::Archived, code.address.as_str());
+            self.messages.retain(|c| c.address != code.address);
+            self.recovery_cursor = None;
+            self.flash = Some((
+                trf("Archived {}, 'X' shows the archive", &[&code.address]),
+                self.theme.accent,
+                Instant::now(),
+            ));
+            self.save();
+        }
+    }
+
+    /// What goes before the name of the account `name` in the lists: a star if it is
+    /// marked, room for one if others are
+    fn mark_prefix(&self, name: &str) -> String {
+        match (
+            self.marked.is_empty(),
+            self.marked.iter().any(|m| m == name),
+        ) {
+            (true, _) => String::new(),
+            (false, true) => "* ".to_string(),
+            (false, false) => "  ".to_string(),
+        }
+    }
+
+    /// Mark the account at `selected` in the list, or take its mark away
+    fn toggle_mark(&mut self, selected: usize) {
+        let Some(code) = self.messages.get(selected) else {
+            return;
+        };
+        match self.marked.iter().position(|name| *name == code.address) {
+            Some(i) => {
+                self.marked.remove(i);
+            }
+            None => self.marked.push(code.address.clone()),
+        }
+    }
+
+    /// Archive every marked account
+    fn archive_marked(&mut self) {
+        let marked = std::mem::take(&mut self.marked);
+        let mut archived = vec![];
+        for account in self.vault.accounts.iter_mut() {
+            if marked.contains(&account.name) && !account.archived {
+                account.archived = true;
+                archived.push(account.name.clone());
+            }
+        }
+        for name in archived {
+            self.vault.record(Action::Archived, name);
+        }
+        self.messages.retain(|code| !marked.contains(&code.address));
+        self.recovery_cursor = None;
+        self.flash(
+            trf(
+                "Archived {} accounts, 'X' shows the archive",
+                &[&marked.len()],
+            ),
+            self.theme.accent,
+        );
+        self.save();
+    }
+
+    /// Delete the accounts called `names`, those marked when the deletion was asked for
+    fn delete_marked(&mut self, names: &[String]) {
+        let (deleted, kept): (Vec<Account>, Vec<Account>) =
+            std::mem::take(&mut self.vault.accounts)
+                .into_iter()
+                .partition(|account| names.contains(&account.name));
+        self.vault.accounts = kept;
+        for account in deleted.iter() {
+            self.vault.record(Action::Deleted, account.name.as_str());
+        }
+        self.messages.retain(|code| !names.contains(&code.address));
+        self.marked.retain(|name| !names.contains(name));
+        self.recovery_cursor = None;
+        self.save();
+        for account in deleted.iter() {
+            hooks::account(hooks::Event::Delete, account);
+        }
+    }
+
+    /// Put the account under the archive cursor back into the list
+    fn restore(&mut self) {
+        let Some(&i) = self.archived().get(self.archive_cursor) else {
+            return;
+        };
+        let now = self.now();
+        let account = &mut self.vault.accounts[i];
+        account.archived = false;
+        self.messages.push(code_constructor(account, now));
+        let name = account.name.clone();
+        self.vault.record(Action::Restored, name);
+        self.archive_cursor = self.archive_cursor.saturating_sub(1);
+        self.save();
+    }
+
+    /// Delete the account under the archive cursor for good
+    fn delete_archived(&mut self) {
+        let Some(&i) = self.archived().get(self.archive_cursor) else {
+            return;
+        };
+        let account = self.vault.accounts.remove(i);
+        self.vault.record(Action::Deleted, account.name.as_str());
+        self.archive_cursor = self.archive_cursor.saturating_sub(1);
+        self.save();
+        hooks::account(hooks::Event::Delete, &account);
+    }
+
+    /// The account a listed code belongs to
+    fn account_of(&self, code: &Totp) -> Option<&Account> {
+        self.vault.accounts.iter().find(|a| a.name == code.address)
+    }
+
+    /// Recovery codes of the account selected in the list
+    fn selected_recovery_codes(&self, selected: Option<usize>) -> &[RecoveryCode] {
+        selected
+            .and_then(|i| self.messages.get(i))
+            .and_then(|code| self.account_of(code))
+            .map_or(&[], |account| &account.recovery_codes)
+    }
+
+    fn selected_recovery_codes_mut(&mut self, selected: Option<usize>) -> &mut [RecoveryCode] {
+        let name = match selected.and_then(|i| self.messages.get(i)) {
+            Some(code) => code.address.clone(),
+            None => return &mut [],
+        };
+        match self.vault.accounts.iter_mut().find(|a| a.name == name) {
+            Some(account) => &mut account.recovery_codes,
+            None => &mut [],
+        }
+    }
+
+    /// The account selected in the list
+    fn selected_account(&self, selected: Option<usize>) -> Option<&Account> {
+        selected
+            .and_then(|i| self.messages.get(i))
+            .and_then(|code| self.account_of(code))
+    }
+
+    /// `code` as it may be shown for the account `address`, grouped, or masked in privacy mode
+    /// unless the account has been revealed
+    fn display_code(&self, address: &str, code: String) -> String {
+        let revealed = self.revealed.as_ref().is_some_and(|(a, at)| {
+            a == address && self.reveal_for.is_none_or(|d| at.elapsed() < d)
+        });
+        if self.mask && !revealed && !code.is_empty() {
+            MASK.to_string()
+        } else {
+            group_digits(&code)
+        }
+    }
+
+    /// How the code of `account` is drawn, dimmed in red once it is about to rotate
+    fn code_style(&self, account: &Account) -> Style {
+        if account.totp().seconds_remaining(self.now()) <= EXPIRING {
+            Style::default()
+                .fg(self.theme.error)
+                .add_modifier(Modifier::DIM)
+        } else {
+            Style::default()
+        }
+    }
+
+    /// The Add form field behind `field`
+    fn field_mut(&mut self, field: InputField) -> &mut String {
+        match field {
+            InputField::Issuer => &mut self.issuer,
+            InputField::Icon => &mut self.icon,
+            InputField::Color => &mut self.color,
+            InputField::Account => &mut self.account,
+            InputField::Key => &mut self.key,
+            InputField::T0 => &mut self.t0,
+            InputField::Note => &mut self.note,
+            InputField::Recovery => &mut self.recovery,
+            InputField::QrPath => &mut self.qr_path,
+        }
+    }
+
+    /// Fill the Add form with the account at `selected` to edit it, all but its secret;
+    /// false if nothing is selected
+    fn start_editing(&mut self, selected: Option<usize>) -> bool {
+        let Some(account) = self.selected_account(selected) else {
+            return false;
+        };
+        let recovery: Vec<&str> = account
+            .recovery_codes
+            .iter()
+            .map(|code| code.code.as_str())
+            .collect();
+        let t0 = match account.t0 {
+            0 => String::new(),
+            t0 => t0.to_string(),
+        };
+        let (issuer, icon, color, name, note, recovery) = (
+            account.issuer.clone(),
+            account.icon.clone(),
+            account.color.clone(),
+            account.name.clone(),
+            account.note.clone(),
+            recovery.join(" "),
+        );
+        let legacy = account.legacy;
+        (self.issuer, self.icon, self.color, self.note) = (issuer, icon, color, note);
+        (self.t0, self.recovery) = (t0, recovery);
+        self.account = name.clone();
+        self.key.clear();
+        self.legacy = legacy;
+        self.editing = Some(name);
+        true
+    }
+
+    /// Fill the Add form from the otpauth:// URI of the QR code in the image at the path
+    /// typed, keeping what was typed into the fields the URI does not have
+    fn scan_qr(&mut self) -> Result<(), String> {
+        let typed = self.qr_path.trim();
+        let path = match typed.strip_prefix("~/") {
+            Some(rest) => directories::BaseDirs::new()
+                .map(|dirs| dirs.home_dir().join(rest))
+                .unwrap_or_else(|| PathBuf::from(typed)),
+            None => PathBuf::from(typed),
+        };
+        let uri = qr::decode(&path)?;
+        let scanned = otpauth::parse(&uri)
+            .map_err(|err| trf("The QR code is not of an account: {}", &[&err]))?;
+        if !scanned.issuer.is_empty() {
+            self.issuer = scanned.issuer;
+        }
+        if !scanned.name.is_empty() {
+            self.account = scanned.name;
+        }
+        self.key = scanned.secret;
+        self.encoding = SecretEncoding::Base32;
+        self.legacy = false;
+        self.scanned = Some(config::Defaults {
+            algorithm: scanned.algorithm,
+            digits: scanned.digits,
+            period: scanned.period,
+        });
+        self.qr_path.clear();
+        Ok(())
+    }
+
+    /// Empty every field of the Add form
+    fn clear_form(&mut self) {
+        for field in [
+            &mut self.issuer,
+            &mut self.icon,
+            &mut self.color,
+            &mut self.account,
+            &mut self.key,
+            &mut self.t0,
+            &mut self.note,
+            &mut self.recovery,
+            &mut self.qr_path,
+        ] {
+            field.clear();
+        }
+        self.encoding = SecretEncoding::default();
+        self.legacy = false;
+        self.scanned = None;
+    }
+
+    /// Replace what the form can change of the account `name` with `edited`, keeping
+    /// its secret when none was typed and which recovery codes were used; a new secret is
+    /// that of a re-enrollment, the old one is kept for as long as the config says
+    fn update_account(&mut self, name: &str, edited: Account) {
+        let now = self.now();
+        let Some(account) = self.vault.accounts.iter_mut().find(|a| a.name == name) else {
+            return;
+        };
+        let used: Vec<String> = account
+            .recovery_codes
+            .drain(..)
+            .filter(|code| code.used)
+            .map(|code| code.code)
+            .collect();
+        account.issuer = edited.issuer;
+        account.name = edited.name;
+        let reenrolled = !edited.secret.trim().is_empty();
+        if reenrolled {
+            let keep_until = self.keep_previous_secret.map(|seconds| now + seconds);
+            account.reenroll(edited.secret, keep_until);
+        }
+        // the codes of the secret kept or the new one, as chosen in the form
+        account.legacy = edited.legacy;
+        // the new secret came with its own settings
+        if self.scanned.take().is_some() {
+            (account.algorithm, account.digits) = (edited.algorithm, edited.digits);
+            account.period = edited.period;
+        }
+        account.t0 = edited.t0;
+        account.note = edited.note;
+        account.icon = edited.icon;
+        account.color = edited.color;
+        account.recovery_codes = edited.recovery_codes;
+        for code in account.recovery_codes.iter_mut() {
+            code.used = used.contains(&code.code);
+        }
+        let code = code_constructor(account, now);
+        if let Some(message) = self.messages.iter_mut().find(|m| m.address == name) {
+            *message = code;
+        }
+        let new_name = account.name.clone();
+        if reenrolled {
+            self.vault.record(Action::Reenrolled, new_name.as_str());
+        }
+        self.vault.record(Action::Edited, new_name);
+        self.revealed = None;
+        self.secret_shown = None;
+        self.save();
+    }
 
-        if self.progress > 1.0 {
-            self.progress = 0.0;
+    /// Type a character into an Add form field, T0 only takes digits
+    fn type_char(&mut self, field: InputField, c: char) {
+        if field != InputField::T0 || c.is_ascii_digit() {
+            self.field_mut(field).push(c);
         }
     }
 }
@@ -577,12 +3735,66 @@ impl App {
 impl Default for App {
     fn default() -> App {
         App {
+            issuer: String::new(),
+            icon: String::new(),
+            color: String::new(),
             account: String::new(),
             key: String::new(),
+            t0: String::new(),
+            note: String::new(),
+            recovery: String::new(),
+            qr_path: String::new(),
+            scanned: None,
+            encoding: SecretEncoding::default(),
+            legacy: false,
             input_mode: InputMode::Normal,
             messages: Vec::new(),
-            progress: 0.0,
-            keys: vec![],
+            vault: Vault::default(),
+            store: None,
+            profile: None,
+            other_vaults: vec![],
+            vault_tab: 0,
+            switcher: None,
+            popup: None,
+            passwd: None,
+            save_error: None,
+            exposed: None,
+            show_window: false,
+            view: CodesView::List,
+            clock_check: None,
+            time_offset: 0,
+            lock_after: None,
+            last_input: Instant::now(),
+            locked: false,
+            unlock: Unlock::default(),
+            time_source: None,
+            source_offset: None,
+            list_offset: 0,
+            list_rows: 1,
+            mask: false,
+            revealed: None,
+            reveal_for: None,
+            secret_shown: None,
+            qr_shown: None,
+            recovery_cursor: None,
+            archive_cursor: 0,
+            marked: vec![],
+            recent_first: false,
+            clipboard: clipboard::Clipboard::default(),
+            copied: None,
+            clear_clipboard_after: None,
+            notify_before_expiry: None,
+            expiry_notice: None,
+            autotype: autotype::Backend::default(),
+            autotype_delay: AUTOTYPE_DELAY,
+            typing: None,
+            flash: None,
+            theme: Theme::default(),
+            no_color: false,
+            editing: None,
+            defaults: config::Defaults::default(),
+            keep_previous_secret: None,
+            sync: None,
         }
     }
 }
@@ -0,0 +1,147 @@
+//! Adding imported accounts to a vault that may have some of them already. An
+//! account is the same as one in the vault if it has the same name; if it also
+//! gives the same codes it is skipped, otherwise what to keep is asked for.
+
+use cli_totp::{otp, Account, Vault};
+use std::fmt;
+use std::io::{self, BufRead, IsTerminal, Write};
+use tracing::debug;
+
+/// What to keep when an imported account has the name of one in the vault
+/// but gives other codes
+#[derive(Clone, Copy, PartialEq, Debug, clap::ValueEnum)]
+pub enum Resolution {
+    /// Ask for each of them
+    Ask,
+    /// Keep the account in the vault, drop the imported one
+    Local,
+    /// Replace the account in the vault with the imported one
+    Imported,
+    /// Keep both, the imported one under a new name
+    Both,
+}
+
+/// What merging did with the imported accounts
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub added: usize,
+    /// Already in the vault, giving the same codes
+    pub duplicates: usize,
+    pub kept_local: usize,
+    pub replaced: usize,
+    /// Added under a new name next to the account of the same name
+    pub renamed: usize,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "added {}, already there {}, kept the vault's {}, replaced {}, added under a new name {}",
+            self.added, self.duplicates, self.kept_local, self.replaced, self.renamed
+        )
+    }
+}
+
+/// Add `imported` to `vault`, settling conflicts with the same name as `resolution` says;
+/// Ask asks on stderr and reads the answers from stdin
+pub fn merge(
+    vault: &mut Vault,
+    imported: Vec<Account>,
+    resolution: Resolution,
+) -> io::Result<Summary> {
+    let mut summary = Summary::default();
+    for account in imported {
+        let Some(index) = vault.accounts.iter().position(|a| a.name == account.name) else {
+            debug!(account = %account.name, "added");
+            vault.accounts.push(account);
+            summary.added += 1;
+            continue;
+        };
+        if same_codes(&vault.accounts[index], &account) {
+            debug!(account = %account.name, "already in the vault");
+            summary.duplicates += 1;
+            continue;
+        }
+        let resolution = match resolution {
+            Resolution::Ask => ask(&vault.accounts[index], &account)?,
+            resolution => resolution,
+        };
+        debug!(account = %account.name, ?resolution, "name taken, other codes");
+        match resolution {
+            Resolution::Local | Resolution::Ask => summary.kept_local += 1,
+            Resolution::Imported => {
+                vault.accounts[index] = account;
+                summary.replaced += 1;
+            }
+            Resolution::Both => {
+                let name = free_name(vault, &account.name);
+                vault.accounts.push(Account { name, ..account });
+                summary.renamed += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Whether `a` and `b` give the same codes, whatever else differs
+pub fn same_codes(a: &Account, b: &Account) -> bool {
+    // legacy accounts use their secret as it is written, even when it reads as Base32
+    let secret = |account: &Account| {
+        otp::decode_base32(&account.secret)
+            .filter(|_| !account.legacy)
+            .unwrap_or_else(|| account.secret.as_bytes().to_vec())
+    };
+    a.legacy == b.legacy
+        && secret(a) == secret(b)
+        && a.algorithm == b.algorithm
+        && a.digits == b.digits
+        && a.period == b.period
+        && a.t0 == b.t0
+}
+
+// ask which of `local` and `imported` to keep, until the answer is one of the choices
+fn ask(local: &Account, imported: &Account) -> io::Result<Resolution> {
+    if !io::stdin().is_terminal() {
+        return Err(io::Error::other(format!(
+            "{} is in the vault already, tell what to keep with --on-conflict",
+            local.name
+        )));
+    }
+    let describe = |account: &Account| {
+        format!(
+            "{} {}, {} {} digits every {}s",
+            account.issuer,
+            account.name,
+            format!("{:?}", account.algorithm).to_uppercase(),
+            account.digits,
+            account.period
+        )
+    };
+    eprintln!("{} is in the vault already with other codes:", local.name);
+    eprintln!("  vault:    {}", describe(local));
+    eprintln!("  imported: {}", describe(imported));
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        eprint!("Keep [l]ocal, [i]mported or [b]oth? ");
+        io::stderr().flush()?;
+        let answer = match lines.next() {
+            Some(line) => line?,
+            None => return Ok(Resolution::Local),
+        };
+        match answer.trim() {
+            "l" | "local" => return Ok(Resolution::Local),
+            "i" | "imported" => return Ok(Resolution::Imported),
+            "b" | "both" => return Ok(Resolution::Both),
+            _ => {}
+        }
+    }
+}
+
+// `name` with the lowest number that makes it unique in `vault`, e.g. "alice (2)"
+fn free_name(vault: &Vault, name: &str) -> String {
+    (2..)
+        .map(|n| format!("{} ({})", name, n))
+        .find(|candidate| vault.accounts.iter().all(|a| &a.name != candidate))
+        .expect("some number is free")
+}
@@ -0,0 +1,95 @@
+//! Moving the accounts from one place they can be kept to another, the vault
+//! file or the password store. What was written is read back and must give the
+//! same codes as the accounts it came from before the old place is emptied.
+//!
+//! The plaintext vault files of the first versions, from before vaults were
+//! encrypted, are moved into the vault the same way and then erased.
+
+use cli_totp::{Account, Vault};
+use serde::Deserialize;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Where accounts can be kept
+#[derive(Clone, Copy, PartialEq, Debug, clap::ValueEnum)]
+pub enum Backend {
+    /// The encrypted vault file of --vault or the config
+    File,
+    /// Entries of the password store, managed by `pass`
+    Pass,
+}
+
+impl Backend {
+    /// What the backend is called in messages
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::File => "the vault file",
+            Backend::Pass => "the password store folder",
+        }
+    }
+}
+
+// seconds from now the codes are compared at, the steps around now and some far away
+const OFFSETS: &[i64] = &[-30, 0, 30, 86_400, 365 * 86_400];
+
+/// Check that `written` has the accounts of `vault`, each giving the same codes around
+/// `now`; what is wrong with it otherwise
+pub fn verify(vault: &Vault, written: &Vault, now: u64) -> Result<(), String> {
+    if written.accounts.len() != vault.accounts.len() {
+        return Err(format!(
+            "{} accounts were written but {} read back",
+            vault.accounts.len(),
+            written.accounts.len()
+        ));
+    }
+    for account in vault.accounts.iter() {
+        let Some(copy) = written.accounts.iter().find(|a| a.name == account.name) else {
+            return Err(format!("{} was not read back", account.name));
+        };
+        for &offset in OFFSETS {
+            let time = now.saturating_add_signed(offset);
+            if copy.code_at(time) != account.code_at(time) {
+                return Err(format!("{} gives other codes once moved", account.name));
+            }
+        }
+    }
+    Ok(())
+}
+
+// a vault file as the first versions wrote it, before vaults were encrypted
+#[derive(Deserialize)]
+struct Plaintext {
+    accounts: Vec<Account>,
+}
+
+/// The accounts of the plaintext vault file at `path`; their codes were the app's own
+/// then, so they are marked legacy to keep giving the same ones
+pub fn read_plaintext(path: &Path) -> Result<Vec<Account>, Box<dyn Error>> {
+    let data = fs::read(path)?;
+    if Vault::is_vault(&data) {
+        return Err(format!("{} is encrypted already", path.display()).into());
+    }
+    let plaintext: Plaintext = serde_json::from_slice(&data)
+        .map_err(|err| format!("{} is not a plaintext vault: {}", path.display(), err))?;
+    Ok(plaintext
+        .accounts
+        .into_iter()
+        .map(|account| Account {
+            legacy: true,
+            ..account
+        })
+        .collect())
+}
+
+/// Overwrite the file at `path` with zeros, then delete it. Journaling and copy-on-write
+/// filesystems, and SSDs, may keep the old content elsewhere all the same
+pub fn erase(path: &Path) -> io::Result<()> {
+    let len = fs::metadata(path)?.len();
+    let mut file = fs::OpenOptions::new().write(true).open(path)?;
+    io::copy(&mut io::repeat(0).take(len), &mut file)?;
+    file.sync_all()?;
+    drop(file);
+    fs::remove_file(path)
+}
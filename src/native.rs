@@ -0,0 +1,171 @@
+//! The native messaging host of the browser extension. The browser starts it and
+//! talks to it over stdin and stdout as Chrome and Firefox do, each message JSON
+//! preceded by its length as a 32-bit integer in native byte order.
+//!
+//! The extension sends the address of the page it is on, `{"url": "https://github.com/login"}`,
+//! and gets back `{"code": "123456"}` or `{"error": "..."}`. The host asks the agent
+//! for the code of the site, which the agent only gives once it is approved on its
+//! terminal or in a notification, so the host never opens the vault itself.
+
+use crate::agent;
+use crate::paths;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::env;
+use std::fs::{self, Permissions};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+/// The name the host is registered under, what the extension connects to
+pub const NAME: &str = "cli_totp";
+
+// nothing the extension sends comes near this, the browsers allow up to 4 GiB
+const MAX_MESSAGE: usize = 64 * 1024;
+
+/// The browsers the host can be registered with
+#[derive(Clone, Copy, PartialEq, Debug, clap::ValueEnum)]
+pub enum Browser {
+    Chrome,
+    Chromium,
+    Firefox,
+}
+
+#[derive(Deserialize)]
+struct Request {
+    url: String,
+}
+
+/// Answer the messages of the browser with the agent on `socket` until the browser
+/// closes stdin
+pub fn run(socket: &Path) -> io::Result<()> {
+    let (mut input, mut output) = (io::stdin().lock(), io::stdout().lock());
+    while let Some(message) = read_message(&mut input)? {
+        let answer = answer(socket, &message);
+        debug!(%answer, "answered the browser");
+        write_message(&mut output, &answer)?;
+    }
+    Ok(())
+}
+
+/// Register the host with `browser` for the extension with the ID `extension`, to ask
+/// the agent on `socket` if given, returning where the manifest was written
+pub fn install(browser: Browser, extension: &str, socket: Option<&Path>) -> io::Result<PathBuf> {
+    // the browser starts the host without arguments of ours, a script gives them
+    let script = paths::data_dir().join("native-host");
+    let mut command = format!(
+        "#!/bin/sh\nexec {} native-host",
+        quote(&env::current_exe()?.to_string_lossy())
+    );
+    if let Some(socket) = socket {
+        command += &format!(" --socket {}", quote(&socket.to_string_lossy()));
+    }
+    command += " \"$@\"\n";
+    fs::create_dir_all(paths::data_dir())?;
+    fs::write(&script, command)?;
+    fs::set_permissions(&script, Permissions::from_mode(0o755))?;
+
+    let mut manifest = json!({
+        "name": NAME,
+        "description": "cli-TOTP codes for the site of the page",
+        "path": script,
+        "type": "stdio",
+    });
+    let (key, allowed) = match browser {
+        Browser::Firefox => ("allowed_extensions", json!([extension])),
+        _ => (
+            "allowed_origins",
+            json!([format!("chrome-extension://{}/", extension)]),
+        ),
+    };
+    manifest[key] = allowed;
+    let path = manifest_dir(browser)?.join(format!("{}.json", NAME));
+    fs::create_dir_all(path.parent().expect("the manifest is in a directory"))?;
+    fs::write(&path, serde_json::to_string_pretty(&manifest)? + "\n")?;
+    info!(?browser, manifest = %path.display(), "native messaging host registered");
+    Ok(path)
+}
+
+/// The site of `url` as the agent matches it against the issuers, its host name without
+/// a leading www., e.g. github.com for https://www.github.com/login
+fn site(url: &str) -> Option<String> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = host.split(':').next()?.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    (!host.is_empty()).then(|| host.to_string())
+}
+
+// what to send back for `message`
+fn answer(socket: &Path, message: &[u8]) -> Value {
+    let request: Request = match serde_json::from_slice(message) {
+        Ok(request) => request,
+        Err(err) => return json!({ "error": format!("bad request: {}", err) }),
+    };
+    let Some(site) = site(&request.url) else {
+        return json!({ "error": format!("no site in {:?}", request.url) });
+    };
+    match agent::request(socket, &format!("site-code {}", site)) {
+        Ok(answer) => match answer.trim().strip_prefix("error: ") {
+            Some(err) => json!({ "error": err }),
+            None => json!({ "code": answer.trim() }),
+        },
+        Err(err) => json!({ "error": err.to_string() }),
+    }
+}
+
+// the next message, None once the browser is gone
+fn read_message(input: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut length = [0; 4];
+    match input.read_exact(&mut length) {
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        read => read?,
+    }
+    let length = u32::from_ne_bytes(length) as usize;
+    if length > MAX_MESSAGE {
+        return Err(io::Error::other(format!(
+            "a message of {} bytes is too long",
+            length
+        )));
+    }
+    let mut message = vec![0; length];
+    input.read_exact(&mut message)?;
+    Ok(Some(message))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let message = serde_json::to_vec(message)?;
+    output.write_all(&(message.len() as u32).to_ne_bytes())?;
+    output.write_all(&message)?;
+    output.flush()
+}
+
+// where `browser` looks for the manifests of native messaging hosts
+fn manifest_dir(browser: Browser) -> io::Result<PathBuf> {
+    let home = directories::BaseDirs::new()
+        .map(|dirs| dirs.home_dir().to_path_buf())
+        .ok_or_else(|| io::Error::other("no home directory to register the host in"))?;
+    let dir = if cfg!(target_os = "macos") {
+        match browser {
+            Browser::Chrome => "Library/Application Support/Google/Chrome/NativeMessagingHosts",
+            Browser::Chromium => "Library/Application Support/Chromium/NativeMessagingHosts",
+            Browser::Firefox => "Library/Application Support/Mozilla/NativeMessagingHosts",
+        }
+    } else {
+        match browser {
+            Browser::Chrome => ".config/google-chrome/NativeMessagingHosts",
+            Browser::Chromium => ".config/chromium/NativeMessagingHosts",
+            Browser::Firefox => ".mozilla/native-messaging-hosts",
+        }
+    };
+    Ok(home.join(dir))
+}
+
+/// `text` quoted for sh
+pub fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
@@ -0,0 +1,101 @@
+//! Desktop notifications, shown when a copied code is about to expire so it is
+//! not pasted only to be rejected: better to wait for the next one. Off unless
+//! `notify_before_expiry` in the config says how many seconds before.
+//!
+//! The agent also asks through them which code the browser gets, when it has no
+//! terminal to ask on.
+
+#[cfg(unix)]
+use crate::i18n::tr;
+use crate::i18n::trf;
+use notify_rust::Notification;
+#[cfg(unix)]
+use std::io;
+use std::thread::{self, JoinHandle};
+#[cfg(unix)]
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Tell that the code copied of the account `label` expires in `seconds`; the
+/// notification is sent from the thread returned, as the notification daemon can be slow
+pub fn expiring(label: &str, seconds: u64) -> JoinHandle<()> {
+    let body = trf(
+        "The code of {} expires in {}s, wait for the next one",
+        &[&label, &seconds],
+    );
+    thread::spawn(move || {
+        match Notification::new()
+            .summary("cli-TOTP")
+            .body(&body)
+            .timeout(5000)
+            .show()
+        {
+            Ok(_) => debug!("expiry notification shown"),
+            // the terminal belongs to the UI, there is nowhere else to tell
+            Err(err) => warn!(%err, "cannot show the expiry notification"),
+        }
+    })
+}
+
+/// Ask with `question` which of `choices` to go with, None if refused or not answered
+/// within `timeout`; a notification with a button for each, or a dialog on macOS
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn choose(question: &str, choices: &[String], timeout: Duration) -> io::Result<Option<usize>> {
+    use std::sync::mpsc;
+
+    let mut notification = Notification::new();
+    notification
+        .summary("cli-TOTP")
+        .body(question)
+        .timeout(i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX));
+    for (i, choice) in choices.iter().enumerate() {
+        notification.action(&i.to_string(), choice);
+    }
+    notification.action("refuse", tr("Refuse"));
+    let handle = notification.show().map_err(io::Error::other)?;
+    // some notification daemons never close one with buttons, so it is not waited on
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        handle.wait_for_action(|action| {
+            let _ = sender.send(action.to_string());
+        })
+    });
+    Ok(receiver
+        .recv_timeout(timeout)
+        .ok()
+        .and_then(|action| action.parse::<usize>().ok())
+        .filter(|&i| i < choices.len()))
+}
+
+/// Ask with `question` which of `choices` to go with, None if refused or not answered
+/// within `timeout`; a dialog with the choices numbered, the number typed into it
+#[cfg(target_os = "macos")]
+pub fn choose(question: &str, choices: &[String], timeout: Duration) -> io::Result<Option<usize>> {
+    use std::process::Command;
+
+    let mut text = question.to_string();
+    for (i, choice) in choices.iter().enumerate() {
+        text.push_str(&format!("\n{}. {}", i + 1, choice));
+    }
+    // the text is handed as an argument, never written into the script
+    let script = format!(
+        "on run argv\n\
+         display dialog (item 1 of argv) default answer \"1\" \
+         buttons {{\"{}\", \"{}\"}} default button 2 giving up after {}\n\
+         if button returned of result is not (item 2 of argv) then return \"\"\n\
+         return text returned of result\n\
+         end run",
+        tr("Refuse"),
+        tr("Give"),
+        timeout.as_secs()
+    );
+    let output = Command::new("osascript")
+        .args(["-e", &script, &text, tr("Give")])
+        .output()?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_sub(1))
+        .filter(|&i| i < choices.len()))
+}
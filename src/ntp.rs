@@ -0,0 +1,219 @@
+//! The SNTP client checking the system clock, codes are only accepted while the
+//! clocks of the machine and of the service agree.
+
+use byteorder::{BigEndian, ByteOrder};
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// NTP server used to check the system clock
+pub const SERVER: &str = "pool.ntp.org:123";
+/// Drift in seconds above which the clock check warns
+pub const DRIFT_THRESHOLD: f64 = 5.0;
+// seconds between the NTP era (1900) and the unix epoch
+const NTP_UNIX_OFFSET: f64 = 2_208_988_800.0;
+// length of an SNTP packet without extensions
+const PACKET_LEN: usize = 48;
+
+/// Ask the SNTP server `server` for the time and return how far the system clock is
+/// ahead (+) or behind (-), in seconds; each address it resolves to is tried in turn
+pub fn clock_drift(server: &str) -> io::Result<f64> {
+    let mut last_error = None;
+    for addr in server.to_socket_addrs()? {
+        match drift_from(addr) {
+            Ok(drift) => return Ok(drift),
+            Err(err) => last_error = Some(err),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| invalid(format!("{} has no address", server))))
+}
+
+// the drift according to the server at `addr`
+fn drift_from(addr: SocketAddr) -> io::Result<f64> {
+    let local: SocketAddr = match addr {
+        SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+        SocketAddr::V6(_) => ([0_u16; 8], 0).into(),
+    };
+    let socket = UdpSocket::bind(local)?;
+    // replies from anyone else are dropped
+    socket.connect(addr)?;
+    socket.set_read_timeout(Some(Duration::from_secs(3)))?;
+
+    let sent = unix_time();
+    let request = request(sent);
+    socket.send(&request)?;
+    let mut reply = [0u8; PACKET_LEN];
+    let len = socket.recv(&mut reply)?;
+    let received = unix_time();
+    check_reply(&reply[..len], &request)?;
+
+    // server receive and transmit timestamps, see RFC 4330 section 5
+    let server_received = timestamp(&reply[32..40]);
+    let server_sent = timestamp(&reply[40..48]);
+    Ok(((sent - server_received) + (received - server_sent)) / 2.0)
+}
+
+// a client request sent at `time`, the server copies its transmit timestamp into the
+// originate timestamp of the reply
+fn request(time: f64) -> [u8; PACKET_LEN] {
+    let mut request = [0u8; PACKET_LEN];
+    // leap indicator 0, version 4, mode 3 (client)
+    request[0] = 0x23;
+    let ntp_time = time + NTP_UNIX_OFFSET;
+    BigEndian::write_u32(&mut request[40..44], ntp_time as u32);
+    BigEndian::write_u32(
+        &mut request[44..48],
+        (ntp_time.fract() * 4_294_967_296.0) as u32,
+    );
+    request
+}
+
+// an error unless `reply` is the answer of a synchronized server to `request`,
+// RFC 4330 section 5
+fn check_reply(reply: &[u8], request: &[u8; PACKET_LEN]) -> io::Result<()> {
+    if reply.len() < PACKET_LEN {
+        return Err(invalid("short reply from NTP server".to_string()));
+    }
+    let (leap, mode, stratum) = (reply[0] >> 6, reply[0] & 0x07, reply[1]);
+    if mode != 4 {
+        return Err(invalid(format!(
+            "reply from NTP server is of mode {}, not a server's",
+            mode
+        )));
+    }
+    if reply[24..32] != request[40..48] {
+        return Err(invalid(
+            "reply from NTP server is not to the request sent".to_string(),
+        ));
+    }
+    // Kiss-o'-Death, the reference id tells why, e.g. RATE to send less often
+    if stratum == 0 {
+        let code = String::from_utf8_lossy(&reply[12..16]);
+        return Err(invalid(format!(
+            "NTP server refused to answer: {}",
+            code.trim_end_matches('\0')
+        )));
+    }
+    if leap == 3 || stratum > 15 {
+        return Err(invalid("NTP server is not synchronized".to_string()));
+    }
+    if reply[40..48] == [0; 8] {
+        return Err(invalid("reply from NTP server has no time".to_string()));
+    }
+    Ok(())
+}
+
+// convert a 64 bit NTP timestamp to unix seconds
+fn timestamp(bytes: &[u8]) -> f64 {
+    let seconds = BigEndian::read_u32(&bytes[..4]) as f64;
+    let fraction = BigEndian::read_u32(&bytes[4..]) as f64 / 4_294_967_296.0;
+    seconds + fraction - NTP_UNIX_OFFSET
+}
+
+// seconds since the unix epoch according to the system clock, with sub-second precision
+fn unix_time() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs_f64()
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // what a synchronized stratum 2 server answers to `request`
+    fn reply(request: &[u8; PACKET_LEN]) -> [u8; PACKET_LEN] {
+        let mut reply = [0u8; PACKET_LEN];
+        // leap indicator 0, version 4, mode 4 (server)
+        reply[0] = 0x24;
+        reply[1] = 2;
+        reply[24..32].copy_from_slice(&request[40..48]);
+        reply[32..40].copy_from_slice(&request[40..48]);
+        reply[40..48].copy_from_slice(&request[40..48]);
+        reply
+    }
+
+    #[test]
+    fn writes_the_time_sent_in_the_request() {
+        let request = request(1_700_000_000.5);
+        assert_eq!(request[0], 0x23);
+        assert_eq!(timestamp(&request[40..48]), 1_700_000_000.5);
+    }
+
+    #[test]
+    fn accepts_the_reply_to_the_request() {
+        let request = request(1_700_000_000.25);
+        assert!(check_reply(&reply(&request), &request).is_ok());
+    }
+
+    #[test]
+    fn rejects_other_replies() {
+        let request = request(1_700_000_000.25);
+        let short = reply(&request);
+        assert!(check_reply(&short[..47], &request).is_err());
+
+        let mut client = reply(&request);
+        client[0] = 0x23;
+        let mut broadcast = reply(&request);
+        broadcast[0] = 0x25;
+        let mut other = reply(&request);
+        other[31] ^= 1;
+        let mut unsynchronized = reply(&request);
+        unsynchronized[0] |= 0xc0;
+        let mut stratum_16 = reply(&request);
+        stratum_16[1] = 16;
+        let mut no_time = reply(&request);
+        no_time[40..48].fill(0);
+        for reply in [
+            client,
+            broadcast,
+            other,
+            unsynchronized,
+            stratum_16,
+            no_time,
+        ] {
+            assert!(check_reply(&reply, &request).is_err());
+        }
+    }
+
+    // a server on `local` answering one request with the time of this machine
+    fn serve_once(local: &str) -> Option<SocketAddr> {
+        let socket = UdpSocket::bind(local).ok()?;
+        let addr = socket.local_addr().ok()?;
+        std::thread::spawn(move || {
+            let mut request = [0u8; PACKET_LEN];
+            let (_, client) = socket.recv_from(&mut request).unwrap();
+            let mut answer = reply(&request);
+            let now = super::request(unix_time());
+            answer[32..40].copy_from_slice(&now[40..48]);
+            answer[40..48].copy_from_slice(&now[40..48]);
+            socket.send_to(&answer, client).unwrap();
+        });
+        Some(addr)
+    }
+
+    #[test]
+    fn asks_servers_of_either_address_family() {
+        let v4 = serve_once("127.0.0.1:0").unwrap();
+        assert!(clock_drift(&v4.to_string()).unwrap().abs() < 1.0);
+        // not every machine has IPv6
+        if let Some(v6) = serve_once("[::1]:0") {
+            assert!(clock_drift(&v6.to_string()).unwrap().abs() < 1.0);
+        }
+    }
+
+    #[test]
+    fn tells_the_kiss_code() {
+        let request = request(1_700_000_000.25);
+        let mut kiss = reply(&request);
+        kiss[1] = 0;
+        kiss[12..16].copy_from_slice(b"RATE");
+        let err = check_reply(&kiss, &request).unwrap_err();
+        assert!(err.to_string().contains("RATE"), "{}", err);
+    }
+}
@@ -0,0 +1,403 @@
+//! The one-time password generator, RFC 4226 HOTP and RFC 6238 TOTP.
+
+use byteorder::{BigEndian, ByteOrder};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+
+/// Length of a time step in seconds, unless the account says otherwise
+pub const PERIOD: u64 = 30;
+/// Number of digits in a code, unless the account says otherwise
+pub const DIGITS: u32 = 6;
+/// The most digits a code can have, the HOTP value it is taken from has 31 bits
+pub const MAX_DIGITS: u32 = 9;
+
+/// Hash function behind the HMAC of the RFC 4226 and RFC 6238 codes
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Algorithm {
+    #[default]
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl Algorithm {
+    /// The name used in otpauth URIs and the RFCs
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Sha1 => "SHA1",
+            Algorithm::Sha256 => "SHA256",
+            Algorithm::Sha512 => "SHA512",
+        }
+    }
+
+    /// The algorithm called `name`, in any case
+    pub fn from_name(name: &str) -> Option<Algorithm> {
+        match name.to_ascii_uppercase().as_str() {
+            "SHA1" => Some(Algorithm::Sha1),
+            "SHA256" => Some(Algorithm::Sha256),
+            "SHA512" => Some(Algorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    fn hmac(self) -> hmac::Algorithm {
+        match self {
+            Algorithm::Sha1 => hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY,
+            Algorithm::Sha256 => hmac::HMAC_SHA256,
+            Algorithm::Sha512 => hmac::HMAC_SHA512,
+        }
+    }
+}
+
+/// Generates the codes of one account
+pub struct Totp {
+    secret: Vec<u8>,
+    t0: u64,
+    period: u64,
+    algorithm: Algorithm,
+    digits: u32,
+    // the codes of the first versions of this app, see `legacy_code`
+    legacy: bool,
+}
+
+impl Totp {
+    /// A generator for the raw `secret` with time steps of `period` seconds
+    /// counted from `t0` (unix time)
+    pub fn new(secret: Vec<u8>, t0: u64, period: u64, algorithm: Algorithm, digits: u32) -> Totp {
+        Totp {
+            secret,
+            t0,
+            period: period.max(1),
+            algorithm,
+            digits,
+            legacy: false,
+        }
+    }
+
+    /// A generator giving the codes of the first versions of this app for `secret`,
+    /// which are not the RFC 6238 ones, see `legacy_code`
+    pub fn legacy(secret: Vec<u8>, t0: u64, period: u64, digits: u32) -> Totp {
+        Totp {
+            legacy: true,
+            ..Totp::new(secret, t0, period, Algorithm::Sha256, digits)
+        }
+    }
+
+    /// The time step `time` (seconds since the unix epoch) falls in, there are
+    /// no time steps before T0 so the counter stays at 0 until then
+    pub fn counter(&self, time: u64) -> u64 {
+        time.saturating_sub(self.t0) / self.period
+    }
+
+    /// The code for `time` (seconds since the unix epoch)
+    pub fn generate_at(&self, time: u64) -> u64 {
+        self.generate_for_step(time, 0)
+    }
+
+    /// The code `offset` time steps before (negative) or after the one containing `time`
+    pub fn generate_for_step(&self, time: u64, offset: i64) -> u64 {
+        let counter = self.counter(time).saturating_add_signed(offset);
+        if self.legacy {
+            legacy_code(&self.secret, counter, self.digits)
+        } else {
+            hotp(&self.secret, counter, self.algorithm, self.digits)
+        }
+    }
+
+    /// Seconds left at `time` before the current time step ends and the code rotates
+    pub fn seconds_remaining(&self, time: u64) -> u64 {
+        self.period - time.saturating_sub(self.t0) % self.period
+    }
+
+    /// A code of this generator as text, keeping its leading zeros
+    pub fn format(&self, code: u64) -> String {
+        format_code(code, self.digits)
+    }
+}
+
+/// The RFC 4226 HOTP value of `counter`, `digits` long
+pub fn hotp(key: &[u8], counter: u64, algorithm: Algorithm, digits: u32) -> u64 {
+    let key = hmac::Key::new(algorithm.hmac(), key);
+    let signature = hmac::sign(&key, &counter.to_be_bytes());
+    let signature = signature.as_ref();
+    // dynamic truncation, RFC 4226 section 5.3
+    let offset = (signature[signature.len() - 1] & 0x0f) as usize;
+    let binary = BigEndian::read_u32(&signature[offset..offset + 4]) & 0x7fff_ffff;
+    binary as u64 % modulus(digits)
+}
+
+/// The code the first versions of this app gave for `counter`: HMAC-SHA256 like
+/// HOTP, but the first 8 bytes of the signature instead of the dynamic truncation
+pub fn legacy_code(key: &[u8], counter: u64, digits: u32) -> u64 {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+    let signature = hmac::sign(&key, &counter.to_be_bytes());
+    BigEndian::read_u64(&signature.as_ref()[..8]) % modulus(digits)
+}
+
+/// Whether codes of `digits` digits can be given, 1 to [`MAX_DIGITS`]
+pub fn valid_digits(digits: u32) -> bool {
+    (1..=MAX_DIGITS).contains(&digits)
+}
+
+// what a value is reduced modulo to be `digits` long, all of it when that does not fit
+fn modulus(digits: u32) -> u64 {
+    10_u64.checked_pow(digits).unwrap_or(u64::MAX)
+}
+
+/// The RFC 6238 TOTP value at `time` (seconds since the unix epoch), with time
+/// steps of `period` seconds counted from the epoch
+pub fn totp(key: &[u8], time: u64, period: u64, algorithm: Algorithm, digits: u32) -> u64 {
+    hotp(key, time / period, algorithm, digits)
+}
+
+/// A code as text, keeping its leading zeros
+pub fn format_code(code: u64, digits: u32) -> String {
+    format!("{:0width$}", code, width = digits as usize)
+}
+
+/// Decode a Base32 (RFC 4648) secret as services hand them out, in any case and
+/// with any spaces, dashes and padding left in; None if it is not Base32
+pub fn decode_base32(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = vec![];
+    let (mut buffer, mut bits) = (0_u32, 0);
+    for c in text.chars().filter(|c| !matches!(c, ' ' | '-' | '=')) {
+        let value = match c.to_ascii_uppercase() {
+            c @ 'A'..='Z' => c as u32 - 'A' as u32,
+            c @ '2'..='7' => c as u32 - '2' as u32 + 26,
+            _ => return None,
+        };
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+/// `bytes` as Base32 (RFC 4648) without padding, how secrets are kept
+pub fn encode_base32(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut text = String::new();
+    let (mut buffer, mut bits) = (0_u32, 0);
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            text.push(ALPHABET[(buffer >> bits) as usize & 31] as char);
+        }
+        buffer &= (1 << bits) - 1;
+    }
+    if bits > 0 {
+        text.push(ALPHABET[(buffer << (5 - bits)) as usize & 31] as char);
+    }
+    text
+}
+
+/// How a secret is written down when it is handed out
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum SecretEncoding {
+    /// Base32, what otpauth URIs and most services use
+    #[default]
+    Base32,
+    /// Two hex digits a byte, as in the RFC test vectors
+    Hex,
+    /// The characters themselves are the key
+    Raw,
+}
+
+impl SecretEncoding {
+    /// Every encoding, in the order the Add form cycles through them
+    pub const ALL: [SecretEncoding; 3] = [
+        SecretEncoding::Base32,
+        SecretEncoding::Hex,
+        SecretEncoding::Raw,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SecretEncoding::Base32 => "base32",
+            SecretEncoding::Hex => "hex",
+            SecretEncoding::Raw => "raw",
+        }
+    }
+
+    /// The key `text` in this encoding stands for, None if it is not in this encoding
+    pub fn decode(self, text: &str) -> Option<Vec<u8>> {
+        match self {
+            SecretEncoding::Base32 => decode_base32(text),
+            SecretEncoding::Hex => {
+                let digits: Vec<u8> = text
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .map(|c| c.to_digit(16).map(|d| d as u8))
+                    .collect::<Option<_>>()?;
+                if !digits.len().is_multiple_of(2) {
+                    return None;
+                }
+                Some(
+                    digits
+                        .chunks(2)
+                        .map(|pair| pair[0] << 4 | pair[1])
+                        .collect(),
+                )
+            }
+            SecretEncoding::Raw => Some(text.as_bytes().to_vec()),
+        }
+    }
+
+    /// `text` in this encoding turned into the Base32 secrets are kept as
+    pub fn to_base32(self, text: &str) -> Option<String> {
+        match self {
+            // kept as typed, spaces and all, decoding skips them
+            SecretEncoding::Base32 => decode_base32(text).map(|_| text.to_string()),
+            encoding => encoding.decode(text).map(|key| encode_base32(&key)),
+        }
+    }
+}
+
+impl std::str::FromStr for SecretEncoding {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<SecretEncoding, String> {
+        SecretEncoding::ALL
+            .into_iter()
+            .find(|encoding| encoding.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("unknown secret encoding {}, base32, hex or raw", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the keys of the test vectors, RFC 6238 appendix B
+    const SHA1_KEY: &[u8] = b"12345678901234567890";
+    const SHA256_KEY: &[u8] = b"12345678901234567890123456789012";
+    const SHA512_KEY: &[u8] = b"1234567890123456789012345678901234567890123456789012345678901234";
+
+    #[test]
+    fn gives_the_rfc_4226_codes() {
+        // appendix D
+        let codes = [
+            755224, 287082, 359152, 969429, 338314, 254676, 287922, 162583, 399871, 520489,
+        ];
+        for (counter, code) in codes.into_iter().enumerate() {
+            assert_eq!(hotp(SHA1_KEY, counter as u64, Algorithm::Sha1, 6), code);
+        }
+    }
+
+    #[test]
+    fn gives_the_rfc_6238_codes() {
+        let vectors = [
+            (59, "94287082", "46119246", "90693936"),
+            (1_111_111_109, "07081804", "68084774", "25091201"),
+            (1_111_111_111, "14050471", "67062674", "99943326"),
+            (1_234_567_890, "89005924", "91819424", "93441116"),
+            (2_000_000_000, "69279037", "90698825", "38618901"),
+            (20_000_000_000, "65353130", "77737706", "47863826"),
+        ];
+        for (time, sha1, sha256, sha512) in vectors {
+            for (key, algorithm, code) in [
+                (SHA1_KEY, Algorithm::Sha1, sha1),
+                (SHA256_KEY, Algorithm::Sha256, sha256),
+                (SHA512_KEY, Algorithm::Sha512, sha512),
+            ] {
+                let generator = Totp::new(key.to_vec(), 0, 30, algorithm, 8);
+                assert_eq!(
+                    generator.format(generator.generate_at(time)),
+                    code,
+                    "{}",
+                    time
+                );
+                assert_eq!(
+                    totp(key, time, 30, algorithm, 8),
+                    code.parse::<u64>().unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn counts_time_steps_from_t0() {
+        let totp = Totp::new(SHA1_KEY.to_vec(), 1_000, 30, Algorithm::Sha1, 6);
+        assert_eq!(totp.counter(0), 0);
+        assert_eq!(totp.counter(1_059), 1);
+        assert_eq!(totp.generate_at(1_059), 287082);
+        assert_eq!(totp.generate_for_step(1_059, -1), 755224);
+        assert_eq!(totp.generate_for_step(1_059, 1), 359152);
+        assert_eq!(totp.seconds_remaining(1_059), 1);
+        assert_eq!(totp.seconds_remaining(1_060), 30);
+    }
+
+    #[test]
+    fn gives_the_codes_of_the_first_versions_in_legacy_mode() {
+        // HMAC-SHA256 of the time step, its first 8 bytes modulo a million
+        assert_eq!(legacy_code(SHA1_KEY, 0, 6), 952123);
+        assert_eq!(legacy_code(SHA1_KEY, 1, 6), 533482);
+        assert_eq!(legacy_code(SHA1_KEY, 1, 8), 58533482);
+        let totp = Totp::legacy(SHA1_KEY.to_vec(), 0, 30, 6);
+        assert_eq!(totp.generate_at(1_700_000_000), 316733);
+        assert_ne!(
+            totp.generate_at(59),
+            hotp(SHA1_KEY, 1, Algorithm::Sha256, 6)
+        );
+    }
+
+    #[test]
+    fn keeps_leading_zeros() {
+        assert_eq!(format_code(7_081_804, 8), "07081804");
+        assert_eq!(format_code(42, 6), "000042");
+    }
+
+    #[test]
+    fn decodes_base32_as_services_write_it() {
+        // RFC 4648 section 10
+        let vectors: [(&str, &[u8]); 7] = [
+            ("", b""),
+            ("MY======", b"f"),
+            ("MZXQ====", b"fo"),
+            ("MZXW6===", b"foo"),
+            ("MZXW6YQ=", b"foob"),
+            ("MZXW6YTB", b"fooba"),
+            ("MZXW6YTBOI======", b"foobar"),
+        ];
+        for (text, bytes) in vectors {
+            assert_eq!(decode_base32(text).as_deref(), Some(bytes), "{}", text);
+            assert_eq!(encode_base32(bytes), text.trim_end_matches('='));
+        }
+        assert_eq!(
+            decode_base32("jbsw y3dp-ehpk 3pxp"),
+            decode_base32("JBSWY3DPEHPK3PXP")
+        );
+        for text in ["MZXW1", "MZXW0", "MZXW8", "MZ.XW"] {
+            assert_eq!(decode_base32(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn decodes_hex_and_raw_secrets() {
+        let hex = SecretEncoding::Hex;
+        assert_eq!(hex.decode("3132 33aF").unwrap(), b"123\xaf");
+        assert_eq!(hex.decode("313"), None);
+        assert_eq!(hex.decode("31g2"), None);
+        assert_eq!(hex.to_base32("666f6f").as_deref(), Some("MZXW6"));
+        assert_eq!(SecretEncoding::Raw.decode("foo").unwrap(), b"foo");
+        assert_eq!(
+            SecretEncoding::Raw.to_base32("foo").as_deref(),
+            Some("MZXW6")
+        );
+        // Base32 is kept as typed
+        assert_eq!(
+            SecretEncoding::Base32.to_base32("mzxw 6").as_deref(),
+            Some("mzxw 6")
+        );
+        assert_eq!(SecretEncoding::Base32.to_base32("mzxw1"), None);
+        assert_eq!("HEX".parse(), Ok(SecretEncoding::Hex));
+        assert!("base64".parse::<SecretEncoding>().is_err());
+    }
+}
@@ -0,0 +1,139 @@
+//! `otpauth://` URIs, the format of enrollment QR codes and most exports.
+//!
+//! ```text
+//! otpauth://totp/Issuer:name?secret=BASE32&issuer=Issuer&algorithm=SHA1&digits=6&period=30
+//! ```
+
+use crate::account::Account;
+use crate::otp::{self, Algorithm};
+
+/// Why a URI could not be turned into an account
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct ParseError(String);
+
+/// The account an `otpauth://totp/` URI describes
+pub fn parse(uri: &str) -> Result<Account, ParseError> {
+    let rest = uri
+        .trim()
+        .strip_prefix("otpauth://")
+        .ok_or_else(|| ParseError("not an otpauth:// URI".to_string()))?;
+    let (kind, rest) = rest.split_once('/').unwrap_or((rest, ""));
+    if !kind.eq_ignore_ascii_case("totp") {
+        return Err(ParseError(format!("{} codes are not supported", kind)));
+    }
+    let (label, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let label = percent_decode(label);
+    let (label_issuer, name) = match label.split_once(':') {
+        Some((issuer, name)) => (issuer.trim().to_string(), name.trim().to_string()),
+        None => (String::new(), label.trim().to_string()),
+    };
+
+    let mut account = Account::new(label_issuer, name, String::new());
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(value);
+        match key.to_ascii_lowercase().as_str() {
+            "secret" => account.secret = value,
+            // the parameter wins over the label, it is what the spec recommends
+            "issuer" => account.issuer = value,
+            "algorithm" => {
+                account.algorithm = Algorithm::from_name(&value)
+                    .ok_or_else(|| ParseError(format!("unknown algorithm {}", value)))?
+            }
+            "digits" => {
+                account.digits = Some(number(&value, "digits")?)
+                    .filter(|&digits| otp::valid_digits(digits))
+                    .ok_or_else(|| ParseError(format!("invalid digits {}", value)))?
+            }
+            "period" => account.period = number(&value, "period")?,
+            _ => {}
+        }
+    }
+    if account.secret.is_empty() {
+        return Err(ParseError("the URI has no secret".to_string()));
+    }
+    if otp::decode_base32(&account.secret).is_none() {
+        return Err(ParseError("the secret is not Base32".to_string()));
+    }
+    Ok(account)
+}
+
+/// The `otpauth://totp/` URI of `account`, what enrollment QR codes hold
+pub fn to_uri(account: &Account) -> String {
+    let label = if account.issuer.is_empty() {
+        percent_encode(&account.name)
+    } else {
+        format!(
+            "{}:{}",
+            percent_encode(&account.issuer),
+            percent_encode(&account.name)
+        )
+    };
+    let mut uri = format!(
+        "otpauth://totp/{}?secret={}",
+        label,
+        account.secret.replace([' ', '-', '='], "")
+    );
+    if !account.issuer.is_empty() {
+        uri += &format!("&issuer={}", percent_encode(&account.issuer));
+    }
+    uri += &format!(
+        "&algorithm={}&digits={}&period={}",
+        account.algorithm.name(),
+        account.digits,
+        account.period
+    );
+    uri
+}
+
+// a positive number parameter
+fn number<T: std::str::FromStr + PartialOrd + Default>(
+    value: &str,
+    name: &str,
+) -> Result<T, ParseError> {
+    match value.parse() {
+        Ok(n) if n > T::default() => Ok(n),
+        _ => Err(ParseError(format!("invalid {} {}", name, value))),
+    }
+}
+
+/// Escape everything but the unreserved characters of RFC 3986
+pub fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Undo %XX escapes and + for spaces, leaving malformed escapes as they are
+pub fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                match text
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,234 @@
+//! Accounts kept in the password store, managed by `pass`.
+//!
+//! Every account is an entry under a folder of the store. The entry holds the
+//! account's otpauth:// URI on its first line, as the pass-otp extension
+//! expects, followed by `key: value` lines for what the URI cannot carry:
+//!
+//! ```text
+//! otpauth://totp/GitHub:me?secret=...&issuer=GitHub&algorithm=SHA1&digits=6&period=30
+//! t0: 1700000000
+//! note: recovery email is the work one
+//! recovery: 1234-5678
+//! recovery used: 8765-4321
+//! last used: 1700000123
+//! copies: 42
+//! usage: 19700:3 19701:1
+//! archived: yes
+//! icon: 🐙
+//! color: red
+//! legacy secret: my old key
+//! ```
+
+use cli_totp::{otp, otpauth, Account, RecoveryCode, Vault};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tracing::{debug, info};
+
+/// The accounts under one folder of the password store
+pub struct Folder {
+    folder: String,
+    /// What each entry held when last read or written, so saving only touches
+    /// the entries that changed
+    written: HashMap<String, String>,
+}
+
+impl Folder {
+    pub fn new(folder: &str) -> Folder {
+        Folder {
+            folder: folder.trim_matches('/').to_string(),
+            written: HashMap::new(),
+        }
+    }
+
+    /// The folder of the password store the entries are in
+    pub fn name(&self) -> &str {
+        &self.folder
+    }
+
+    /// Read every entry of the folder
+    pub fn load(&mut self) -> Result<Vault, Box<dyn Error>> {
+        let mut entries = vec![];
+        find_entries(&store_dir().join(&self.folder), Path::new(""), &mut entries);
+        entries.sort();
+
+        let mut vault = Vault::default();
+        for entry in entries {
+            let content = pass(&["show", &self.entry_path(&entry)], None)?;
+            let mut lines = content.lines();
+            let mut account = otpauth::parse(lines.next().unwrap_or_default())
+                .map_err(|err| format!("{}/{}: {}", self.folder, entry, err))?;
+            if account.name.is_empty() {
+                account.name = entry.clone();
+            }
+            for line in lines {
+                match line.split_once(": ") {
+                    Some(("t0", t0)) => account.t0 = t0.parse().unwrap_or(0),
+                    Some(("note", note)) => account.note = note.to_string(),
+                    Some(("recovery", code)) => account.recovery_codes.push(RecoveryCode {
+                        code: code.to_string(),
+                        used: false,
+                    }),
+                    Some(("recovery used", code)) => account.recovery_codes.push(RecoveryCode {
+                        code: code.to_string(),
+                        used: true,
+                    }),
+                    Some(("last used", time)) => account.last_used = time.parse().ok(),
+                    Some(("copies", copies)) => account.copies = copies.parse().unwrap_or(0),
+                    Some(("usage", usage)) => {
+                        account.usage = usage
+                            .split_whitespace()
+                            .filter_map(|day| day.split_once(':'))
+                            .filter_map(|(day, copies)| {
+                                Some((day.parse().ok()?, copies.parse().ok()?))
+                            })
+                            .collect()
+                    }
+                    Some(("archived", archived)) => account.archived = archived == "yes",
+                    Some(("icon", icon)) => account.icon = icon.to_string(),
+                    Some(("color", color)) => account.color = color.to_string(),
+                    Some(("legacy secret", secret)) => {
+                        account.secret = secret.to_string();
+                        account.legacy = true;
+                    }
+                    _ => {}
+                }
+            }
+            self.written.insert(entry, content);
+            vault.accounts.push(account);
+        }
+        info!(folder = %self.folder, accounts = vault.accounts.len(), "password store read");
+        Ok(vault)
+    }
+
+    /// Write the accounts that changed and remove the entries of the accounts that are gone
+    pub fn save(&mut self, vault: &Vault) -> Result<(), Box<dyn Error>> {
+        let mut current = HashMap::new();
+        for account in vault.accounts.iter() {
+            let mut content = if account.legacy {
+                // a legacy secret need not be Base32, the URI gets its bytes and a line below it
+                otpauth::to_uri(&Account {
+                    secret: otp::encode_base32(account.secret.as_bytes()),
+                    ..account.clone()
+                })
+            } else {
+                otpauth::to_uri(account)
+            } + "\n";
+            if account.t0 != 0 {
+                content += &format!("t0: {}\n", account.t0);
+            }
+            if !account.note.is_empty() {
+                content += &format!("note: {}\n", account.note.replace('\n', " "));
+            }
+            for code in account.recovery_codes.iter() {
+                let key = if code.used {
+                    "recovery used"
+                } else {
+                    "recovery"
+                };
+                content += &format!("{}: {}\n", key, code.code);
+            }
+            if let Some(time) = account.last_used {
+                content += &format!("last used: {}\n", time);
+            }
+            if account.copies != 0 {
+                content += &format!("copies: {}\n", account.copies);
+            }
+            if !account.usage.is_empty() {
+                let days: Vec<_> = account
+                    .usage
+                    .iter()
+                    .map(|(day, copies)| format!("{}:{}", day, copies))
+                    .collect();
+                content += &format!("usage: {}\n", days.join(" "));
+            }
+            if account.archived {
+                content += "archived: yes\n";
+            }
+            if !account.icon.is_empty() {
+                content += &format!("icon: {}\n", account.icon);
+            }
+            if !account.color.is_empty() {
+                content += &format!("color: {}\n", account.color);
+            }
+            if account.legacy {
+                content += &format!("legacy secret: {}\n", account.secret);
+            }
+            current.insert(account.name.replace('/', "_"), content);
+        }
+
+        for (entry, content) in current.iter() {
+            if self.written.get(entry) != Some(content) {
+                let path = self.entry_path(entry);
+                debug!(entry = %path, "writing pass entry");
+                pass(&["insert", "--multiline", "--force", &path], Some(content))?;
+            }
+        }
+        for entry in self.written.keys() {
+            if !current.contains_key(entry) {
+                debug!(entry = %self.entry_path(entry), "removing pass entry");
+                pass(&["rm", "--force", &self.entry_path(entry)], None)?;
+            }
+        }
+        self.written = current;
+        Ok(())
+    }
+
+    fn entry_path(&self, entry: &str) -> String {
+        format!("{}/{}", self.folder, entry)
+    }
+}
+
+// root of the password store, where pass keeps it too
+fn store_dir() -> PathBuf {
+    std::env::var_os("PASSWORD_STORE_DIR")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".password-store"))
+        })
+        .unwrap_or_default()
+}
+
+// names of the entries below `dir`, relative to the folder, collected into `entries`
+fn find_entries(dir: &Path, prefix: &Path, entries: &mut Vec<String>) {
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let name = prefix.join(entry.file_name());
+        if path.is_dir() {
+            find_entries(&path, &name, entries);
+        } else if path.extension().is_some_and(|ext| ext == "gpg") {
+            entries.push(name.with_extension("").to_string_lossy().into_owned());
+        }
+    }
+}
+
+// run pass with `args`, feeding it `input`, and return what it prints
+fn pass(args: &[&str], input: Option<&str>) -> Result<String, Box<dyn Error>> {
+    let mut child = Command::new("pass")
+        .args(args)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("cannot run pass: {}", err))?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "pass {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
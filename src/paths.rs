@@ -0,0 +1,48 @@
+//! Where files go unless told otherwise, by the conventions of the platform:
+//! the XDG directories on Linux, ~/Library on macOS and %APPDATA% on Windows.
+
+use directories::ProjectDirs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+// set by --data-dir, before anything asks for the data directory
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+fn project() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", "cli-totp")
+}
+
+/// Keep the data in `dir` instead of the platform's data directory
+pub fn set_data_dir(dir: PathBuf) {
+    let _ = DATA_DIR.set(dir);
+}
+
+/// Directory the vaults of the profiles are kept in, e.g. ~/.local/share/cli-totp
+pub fn data_dir() -> PathBuf {
+    match DATA_DIR.get() {
+        Some(dir) => dir.clone(),
+        None => project()
+            .map(|dirs| dirs.data_dir().to_path_buf())
+            .unwrap_or_default(),
+    }
+}
+
+/// Directory config.toml is read from, e.g. ~/.config/cli-totp
+pub fn config_dir() -> PathBuf {
+    project()
+        .map(|dirs| dirs.config_dir().to_path_buf())
+        .unwrap_or_default()
+}
+
+/// Directory for files that only live as long as the session, like sockets, a
+/// directory of the user's own in the temporary one where the platform has none
+#[cfg(unix)]
+pub fn runtime_dir() -> PathBuf {
+    project()
+        .and_then(|dirs| dirs.runtime_dir().map(|dir| dir.to_path_buf()))
+        .unwrap_or_else(|| {
+            // SAFETY: getuid cannot fail and touches no memory
+            let uid = unsafe { libc::getuid() };
+            std::env::temp_dir().join(format!("cli-totp-{}", uid))
+        })
+}
@@ -0,0 +1,90 @@
+//! Keeping the vault, its backups and their directories to their owner on Unix:
+//! they are made readable by the owner only, and a vault others can read is
+//! refused by the commands, unless --allow-exposed is given, and warned about by
+//! the UI until `doctor --fix` takes that away. Elsewhere the directories
+//! the platform puts them in are private already, and none of this does anything.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// what the vault and backup files, and the directories they are in, are made
+#[cfg(unix)]
+const FILE_MODE: u32 = 0o600;
+#[cfg(unix)]
+const DIR_MODE: u32 = 0o700;
+
+/// A file or directory others than its owner can read
+pub struct Exposed {
+    pub path: PathBuf,
+    pub mode: u32,
+    /// What it should be instead
+    pub wanted: u32,
+}
+
+/// Make the directory `dir` and those it is in, those made readable by the owner only
+pub fn create_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, DIR_MODE);
+    builder.create(dir)
+}
+
+/// Make a new file at `path`, readable by its owner only from the start, failing if there
+/// is one already
+pub fn create_new(path: &Path) -> io::Result<fs::File> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, FILE_MODE);
+    options.open(path)
+}
+
+/// The vault file at `path` and its directory, those of them others can read
+pub fn exposed(path: &Path) -> Vec<Exposed> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+        [(Some(path), FILE_MODE), (dir, DIR_MODE)]
+            .into_iter()
+            .filter_map(|(path, wanted)| {
+                let path = path?;
+                let mode = fs::metadata(path).ok()?.permissions().mode() & 0o777;
+                (mode & 0o077 != 0).then(|| Exposed {
+                    path: path.to_path_buf(),
+                    mode,
+                    wanted,
+                })
+            })
+            .collect()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        vec![]
+    }
+}
+
+/// Take away what others can do with the vault file at `path` and its directory, and
+/// tell which were changed
+pub fn fix(path: &Path) -> io::Result<Vec<Exposed>> {
+    let exposed = exposed(path);
+    #[cfg(unix)]
+    for exposed in &exposed {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&exposed.path, fs::Permissions::from_mode(exposed.wanted))?;
+    }
+    Ok(exposed)
+}
+
+/// The paths of `exposed` joined for a warning
+pub fn describe(exposed: &[Exposed]) -> String {
+    let paths: Vec<String> = exposed
+        .iter()
+        .map(|exposed| exposed.path.display().to_string())
+        .collect();
+    paths.join(" and ")
+}
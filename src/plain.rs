@@ -0,0 +1,164 @@
+//! A line-based interface for screen readers and bare terminals: a prompt
+//! takes one command per line and every answer is a plain line of text, no
+//! raw mode, alternate screen or redrawing.
+//!
+//! Commands: `list`, `show N`, `add`, `delete N`, `help` and `quit`.
+
+use crate::config::Defaults;
+use crate::store::{self, Store};
+use cli_totp::audit::Action;
+use cli_totp::{Account, Vault};
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HELP: &str = "Commands:
+  list       number, name and current code of every account
+  show N     code of account N and the seconds it stays valid
+  add        add an account, asking for its details one by one
+  delete N   delete account N, asking for confirmation
+  help       this list
+  quit       leave";
+
+/// Answer commands read from stdin until `quit` or the end of input, saving
+/// changes to `store` if there is one. Added accounts get the settings of `defaults`
+pub fn run(
+    mut vault: Vault,
+    mut store: Option<Store>,
+    time_offset: i64,
+    defaults: Defaults,
+) -> Result<(), Box<dyn Error>> {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    println!(
+        "{} accounts. Type help for the commands.",
+        vault.accounts.len()
+    );
+    loop {
+        let line = match prompt(&mut lines, "> ")? {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+        let (command, argument) = line.split_once(' ').unwrap_or((&line, ""));
+        let now = now(time_offset);
+        match command {
+            "" => {}
+            "list" | "l" => {
+                if vault.accounts.is_empty() {
+                    println!("No accounts.");
+                }
+                for (index, account) in vault.accounts.iter().enumerate() {
+                    println!(
+                        "{}. {}: {}",
+                        index + 1,
+                        label(account),
+                        account.code_at(now)
+                    );
+                }
+            }
+            "show" | "s" => match find(&vault, argument) {
+                Ok(index) => {
+                    let account = &vault.accounts[index];
+                    println!(
+                        "{}: {}, valid for {} more seconds",
+                        label(account),
+                        account.code_at(now),
+                        account.totp().seconds_remaining(now)
+                    );
+                }
+                Err(message) => println!("{}", message),
+            },
+            "add" | "a" | "delete" | "d" if store::read_only() => {
+                println!("The vault is read-only, accounts cannot be added or deleted.")
+            }
+            "add" | "a" => {
+                let issuer = match prompt(&mut lines, "Issuer: ")? {
+                    Some(issuer) => issuer,
+                    None => return Ok(()),
+                };
+                let name = match prompt(&mut lines, "Name: ")? {
+                    Some(name) => name,
+                    None => return Ok(()),
+                };
+                let secret = rpassword::prompt_password("Secret (not shown): ")?;
+                let secret = secret.trim();
+                if name.is_empty() || secret.is_empty() {
+                    println!("Not added, the name and the secret are needed.");
+                    continue;
+                }
+                vault.record(Action::Added, name.as_str());
+                vault
+                    .accounts
+                    .push(defaults.account(issuer, name, secret.to_string()));
+                save(&vault, &mut store);
+                println!("Added account {}.", vault.accounts.len());
+            }
+            "delete" | "d" => match find(&vault, argument) {
+                Ok(index) => {
+                    let question = format!("Delete {}? (y/n) ", label(&vault.accounts[index]));
+                    if prompt(&mut lines, &question)?.as_deref() == Some("y") {
+                        let account = vault.accounts.remove(index);
+                        vault.record(Action::Deleted, account.name.as_str());
+                        save(&vault, &mut store);
+                        println!("Deleted {}.", label(&account));
+                    } else {
+                        println!("Kept it.");
+                    }
+                }
+                Err(message) => println!("{}", message),
+            },
+            "help" | "h" | "?" => println!("{}", HELP),
+            "quit" | "q" | "exit" => return Ok(()),
+            _ => println!("Unknown command {}. Type help for the commands.", command),
+        }
+    }
+}
+
+// print `text` without a newline and read the answer, None at the end of input
+fn prompt(
+    lines: &mut impl Iterator<Item = io::Result<String>>,
+    text: &str,
+) -> io::Result<Option<String>> {
+    print!("{}", text);
+    io::stdout().flush()?;
+    lines
+        .next()
+        .transpose()
+        .map(|line| line.map(|line| line.trim().to_string()))
+}
+
+// the index of the account numbered `argument` in the list
+fn find(vault: &Vault, argument: &str) -> Result<usize, String> {
+    match argument.trim().parse::<usize>() {
+        Ok(number) if number >= 1 && number <= vault.accounts.len() => Ok(number - 1),
+        Ok(number) => Err(format!("There is no account {}.", number)),
+        Err(_) => Err("Give the account's number from list.".to_string()),
+    }
+}
+
+fn label(account: &Account) -> String {
+    if account.issuer.is_empty() {
+        account.name.clone()
+    } else {
+        format!("{} {}", account.issuer, account.name)
+    }
+}
+
+fn save(vault: &Vault, store: &mut Option<Store>) {
+    match store {
+        Some(store) => {
+            if let Err(err) = store.save(vault) {
+                println!("Cannot save the vault: {}", err);
+            }
+        }
+        None => println!("Not saved, start with --vault or --pass to keep accounts."),
+    }
+}
+
+fn now(time_offset: i64) -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+        .saturating_add_signed(time_offset)
+}
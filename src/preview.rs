@@ -0,0 +1,94 @@
+//! The list of accounts an import is about to add, shown before anything is
+//! written so that entries can be left out. Each entry says whether it looks
+//! right; those that do not start out unchecked.
+
+use crate::merge;
+use crate::secret_error;
+use cli_totp::otp::SecretEncoding;
+use cli_totp::{Account, Vault};
+use std::io::{self, BufRead, Write};
+
+/// Print `accounts` with their status, and `skipped`, the entries that could not be read,
+/// then let them be checked and unchecked by number until <Enter>. The checked accounts,
+/// None if the import was cancelled
+pub fn choose(
+    vault: &Vault,
+    accounts: Vec<Account>,
+    skipped: &[String],
+) -> io::Result<Option<Vec<Account>>> {
+    let statuses: Vec<(String, bool)> = accounts.iter().map(|a| status(vault, a)).collect();
+    let mut checked: Vec<bool> = statuses.iter().map(|&(_, ok)| ok).collect();
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        for (i, account) in accounts.iter().enumerate() {
+            eprintln!(
+                "[{}] {:>3}. {} {}: {}",
+                if checked[i] { "x" } else { " " },
+                i + 1,
+                account.issuer,
+                account.name,
+                statuses[i].0
+            );
+        }
+        for reason in skipped {
+            eprintln!("   cannot import {}", reason);
+        }
+        eprint!(
+            "{} of {} checked. Numbers toggle (e.g. 1 3-5), <Enter> imports, q cancels: ",
+            checked.iter().filter(|&&c| c).count(),
+            accounts.len()
+        );
+        io::stderr().flush()?;
+        let answer = match lines.next() {
+            Some(line) => line?,
+            None => return Ok(None),
+        };
+        match answer.trim() {
+            "" => break,
+            "q" => return Ok(None),
+            answer => {
+                for number in numbers(answer, accounts.len()) {
+                    checked[number - 1] = !checked[number - 1];
+                }
+            }
+        }
+    }
+    Ok(Some(
+        accounts
+            .into_iter()
+            .zip(checked)
+            .filter_map(|(account, checked)| checked.then_some(account))
+            .collect(),
+    ))
+}
+
+// what to know about importing `account` into `vault`, and whether it should be
+fn status(vault: &Vault, account: &Account) -> (String, bool) {
+    if account.secret.trim().is_empty() {
+        return ("no secret".to_string(), false);
+    }
+    if let Some(err) =
+        secret_error(&account.secret, SecretEncoding::Base32).filter(|_| !account.legacy)
+    {
+        return (format!("secret {}", err), false);
+    }
+    match vault.accounts.iter().find(|a| a.name == account.name) {
+        Some(local) if merge::same_codes(local, account) => {
+            ("in the vault already".to_string(), false)
+        }
+        Some(_) => ("name taken, gives other codes".to_string(), true),
+        None => ("ok".to_string(), true),
+    }
+}
+
+// the numbers from 1 to `len` in `text`, single ones and ranges like 3-5; others are ignored
+fn numbers(text: &str, len: usize) -> Vec<usize> {
+    let mut numbers = vec![];
+    for word in text.split(|c: char| c == ',' || c.is_whitespace()) {
+        let (first, last) = word.split_once('-').unwrap_or((word, word));
+        if let (Ok(first), Ok(last)) = (first.parse::<usize>(), last.parse::<usize>()) {
+            numbers.extend((first.max(1)..=last.min(len)).filter(|n| (1..=len).contains(n)));
+        }
+    }
+    numbers
+}
@@ -0,0 +1,43 @@
+//! Named vaults kept side by side, so `--vault work` and `--vault personal` are
+//! separate files with their own passphrases.
+
+use crate::paths;
+use std::fs;
+use std::path::{PathBuf, MAIN_SEPARATOR};
+
+// extension of the profiles' vault files
+const EXTENSION: &str = "vault";
+
+/// Directory the profiles are kept in, the data directory of the platform or --data-dir
+pub fn dir() -> PathBuf {
+    paths::data_dir()
+}
+
+/// The vault file of the profile `name`
+pub fn path(name: &str) -> PathBuf {
+    dir().join(name).with_extension(EXTENSION)
+}
+
+/// The profile and vault file `--vault ARG` refers to, a bare name without dots or
+/// path separators is a profile, anything else a file
+pub fn resolve(arg: &str) -> (Option<String>, PathBuf) {
+    if arg.is_empty() || arg.contains(['/', '.', MAIN_SEPARATOR]) {
+        (None, PathBuf::from(arg))
+    } else {
+        (Some(arg.to_string()), path(arg))
+    }
+}
+
+/// Names of the existing profiles, sorted
+pub fn list() -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+    names.sort();
+    names
+}
@@ -0,0 +1,49 @@
+//! QR codes drawn with half blocks, two rows of modules to a line of text, for
+//! showing an account to the camera of another authenticator, and read back from
+//! pictures of them with zbarimg, e.g. a screenshot of an enrollment page.
+
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
+use std::path::Path;
+use std::process::Command;
+
+/// The QR code of `text` with its quiet zone, as lines; the light modules are the
+/// drawn ones, so it reads right on a dark terminal like `qrencode -t UTF8`
+pub fn render(text: &str) -> Result<Vec<String>, String> {
+    let code = QrCode::new(text.as_bytes()).map_err(|err| err.to_string())?;
+    let image = code
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .build();
+    Ok(image.lines().map(str::to_string).collect())
+}
+
+/// The text of the QR code in the image at `path`, the first one if there are several
+pub fn decode(path: &Path) -> Result<String, String> {
+    if !path.is_file() {
+        return Err(format!("there is no image at {}", path.display()));
+    }
+    let output = Command::new("zbarimg")
+        .args(["--quiet", "--raw", "-Sdisable", "-Sqrcode.enable"])
+        .arg(path)
+        .output()
+        .map_err(|err| format!("cannot run zbarimg, is it installed? {}", err))?;
+    // it fails with 4 when it finds no code in the image
+    if output.status.code() == Some(4) {
+        return Err(format!("there is no QR code in {}", path.display()));
+    }
+    if !output.status.success() {
+        return Err(format!(
+            "zbarimg cannot read {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| format!("there is no QR code in {}", path.display()))
+}
@@ -0,0 +1,60 @@
+//! Taking over the terminal for the full screen UI and giving it back. The
+//! terminal is given back however the UI ends: quitting, an error returned
+//! from the loop, or a panic, which would otherwise leave it in raw mode with
+//! the cursor hidden. In compatibility mode the UI stays on the normal screen,
+//! which is cleared when it is given back.
+
+use crate::compat;
+use crossterm::cursor::{MoveTo, Show};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use std::io;
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// whether the UI has the terminal, so it is given back once
+static TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// The terminal in raw mode on the alternate screen, or the normal one in compatibility
+/// mode, until dropped
+pub struct Screen(());
+
+impl Screen {
+    /// Switch to raw mode and the alternate screen, unless in compatibility mode, and have panics give the
+    /// terminal back before their message is printed
+    pub fn take() -> io::Result<Screen> {
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore();
+            hook(info);
+        }));
+        enable_raw_mode()?;
+        TAKEN.store(true, Ordering::SeqCst);
+        if !compat::on() {
+            execute!(io::stdout(), EnterAlternateScreen)?;
+        }
+        Ok(Screen(()))
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+// leave raw mode and the alternate screen, or clear the screen in compatibility mode, and show
+// the cursor, if the UI has the terminal;
+// errors are ignored, there is nothing left to report them on
+fn restore() {
+    if TAKEN.swap(false, Ordering::SeqCst) {
+        let _ = disable_raw_mode();
+        if compat::on() {
+            let _ = execute!(io::stdout(), Clear(ClearType::All), MoveTo(0, 0), Show);
+        } else {
+            let _ = execute!(io::stdout(), LeaveAlternateScreen, Show);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Checks the generator against the test vectors published with the RFCs.
+
+use cli_totp::otp::{self, Algorithm};
+
+// the HOTP secret of RFC 4226 appendix D, also the SHA1 seed of RFC 6238
+const SEED_SHA1: &[u8] = b"12345678901234567890";
+// the SHA256 and SHA512 seeds of RFC 6238 appendix B
+const SEED_SHA256: &[u8] = b"12345678901234567890123456789012";
+const SEED_SHA512: &[u8] = b"1234567890123456789012345678901234567890123456789012345678901234";
+
+// RFC 4226 appendix D, 6 digit codes for the counters 0 to 9
+const HOTP: [u64; 10] = [
+    755224, 287082, 359152, 969429, 338314, 254676, 287922, 162583, 399871, 520489,
+];
+
+// RFC 6238 appendix B, 8 digit codes with 30 second steps
+const TOTP: [(u64, Algorithm, u64); 18] = [
+    (59, Algorithm::Sha1, 94287082),
+    (59, Algorithm::Sha256, 46119246),
+    (59, Algorithm::Sha512, 90693936),
+    (1111111109, Algorithm::Sha1, 7081804),
+    (1111111109, Algorithm::Sha256, 68084774),
+    (1111111109, Algorithm::Sha512, 25091201),
+    (1111111111, Algorithm::Sha1, 14050471),
+    (1111111111, Algorithm::Sha256, 67062674),
+    (1111111111, Algorithm::Sha512, 99943326),
+    (1234567890, Algorithm::Sha1, 89005924),
+    (1234567890, Algorithm::Sha256, 91819424),
+    (1234567890, Algorithm::Sha512, 93441116),
+    (2000000000, Algorithm::Sha1, 69279037),
+    (2000000000, Algorithm::Sha256, 90698825),
+    (2000000000, Algorithm::Sha512, 38618901),
+    (20000000000, Algorithm::Sha1, 65353130),
+    (20000000000, Algorithm::Sha256, 77737706),
+    (20000000000, Algorithm::Sha512, 47863826),
+];
+
+/// Run every vector, printing a line per vector, and tell whether they all passed
+pub fn run() -> bool {
+    let mut failed = 0;
+    let mut report = |name: String, expected: u64, got: u64, digits: usize| {
+        let verdict = if got == expected { "ok" } else { "FAILED" };
+        if got != expected {
+            failed += 1;
+        }
+        println!(
+            "{:<40} expected {:0digits$}  got {:0digits$}  {}",
+            name,
+            expected,
+            got,
+            verdict,
+            digits = digits
+        );
+    };
+
+    for (counter, &expected) in HOTP.iter().enumerate() {
+        let got = otp::hotp(SEED_SHA1, counter as u64, Algorithm::Sha1, 6);
+        report(
+            format!("RFC 4226 HOTP SHA1 counter {}", counter),
+            expected,
+            got,
+            6,
+        );
+    }
+    for &(time, algorithm, expected) in TOTP.iter() {
+        let seed = match algorithm {
+            Algorithm::Sha1 => SEED_SHA1,
+            Algorithm::Sha256 => SEED_SHA256,
+            Algorithm::Sha512 => SEED_SHA512,
+        };
+        let got = otp::totp(seed, time, 30, algorithm, 8);
+        report(
+            format!("RFC 6238 TOTP {} time {}", algorithm.name(), time),
+            expected,
+            got,
+            8,
+        );
+    }
+
+    let total = HOTP.len() + TOTP.len();
+    println!("{} of {} vectors passed", total - failed, total);
+    failed == 0
+}
@@ -0,0 +1,106 @@
+//! Recording and replaying of TUI sessions, used to reproduce UI bugs.
+//!
+//! A recording is a JSON-lines file: one line per input event and one per
+//! state transition, each stamped with the milliseconds since the session
+//! started. Characters typed as a passphrase or into the Add form are redacted
+//! before they are written, but for the fields that hold nothing secret such as
+//! the issuer, so a recording can be attached to an issue.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Character that replaces everything typed into a redacted field
+const REDACTED: char = '*';
+
+/// Snapshot of the UI state that is logged whenever it changes
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct State {
+    pub tab: String,
+    pub menu_keys: bool,
+    pub secret_field: bool,
+    pub accounts: usize,
+    pub selected: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Entry {
+    Input { at_ms: u64, key: KeyEvent },
+    State { at_ms: u64, state: State },
+}
+
+/// Writes the events of the running session to a file
+pub struct Recorder {
+    out: BufWriter<File>,
+    started: Instant,
+    last_state: Option<State>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> io::Result<Recorder> {
+        Ok(Recorder {
+            out: BufWriter::new(File::create(path)?),
+            started: Instant::now(),
+            last_state: None,
+        })
+    }
+
+    /// Log an input event, `redact` is set when the key is typed into a field that may
+    /// hold a secret; the keys held with <Ctrl>, like <Ctrl+E>, are commands and kept
+    pub fn input(&mut self, key: &KeyEvent, redact: bool) -> io::Result<()> {
+        let mut key = *key;
+        if redact && !key.modifiers.contains(KeyModifiers::CONTROL) {
+            if let KeyCode::Char(_) = key.code {
+                key.code = KeyCode::Char(REDACTED);
+            }
+        }
+        let entry = Entry::Input {
+            at_ms: self.elapsed_ms(),
+            key,
+        };
+        self.write(&entry)
+    }
+
+    /// Log the state after handling an event, unless it did not change
+    pub fn state(&mut self, state: State) -> io::Result<()> {
+        if self.last_state.as_ref() == Some(&state) {
+            return Ok(());
+        }
+        self.last_state = Some(state.clone());
+        let entry = Entry::State {
+            at_ms: self.elapsed_ms(),
+            state,
+        };
+        self.write(&entry)
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    // one line per entry, flushed right away so a crash still leaves a usable log
+    fn write(&mut self, entry: &Entry) -> io::Result<()> {
+        serde_json::to_writer(&mut self.out, entry)?;
+        self.out.write_all(b"\n")?;
+        self.out.flush()
+    }
+}
+
+/// Read the input events of a recording, with the offset at which each one happened
+pub fn load_replay(path: &Path) -> io::Result<Vec<(Duration, KeyEvent)>> {
+    let mut events = vec![];
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Entry::Input { at_ms, key } = serde_json::from_str(&line)? {
+            events.push((Duration::from_millis(at_ms), key));
+        }
+    }
+    Ok(events)
+}
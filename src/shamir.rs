@@ -0,0 +1,286 @@
+//! Splitting the key of a vault into shares with Shamir's secret sharing, any
+//! `threshold` of which give the key back while fewer tell nothing about it, so
+//! it can be kept in several places without any one of them opening the vault.
+//!
+//! Each byte of the key is the constant term of its own random polynomial of
+//! degree `threshold - 1` over GF(256), and share `x` holds their values at `x`.
+//! A share is written as one line:
+//!
+//! ```text
+//! cli-totp-share:<x>:<threshold>:<key id>:<values in hex>
+//! ```
+//!
+//! where the key id, 4 random bytes drawn for each split, tells shares of
+//! different splits apart. It says nothing about the key, so holding a share does
+//! not help to check guesses of it; the vault opening with the key put back
+//! together is what checks it.
+
+use ring::rand::{SecureRandom, SystemRandom};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+const PREFIX: &str = "cli-totp-share";
+
+/// One of the shares of a key
+#[derive(Clone, PartialEq, Debug)]
+pub struct Share {
+    /// Where the polynomials were evaluated, from 1
+    pub x: u8,
+    /// How many shares give the key back
+    pub threshold: u8,
+    id: [u8; 4],
+    values: Vec<u8>,
+}
+
+/// Split `key` into `shares` shares, any `threshold` of which give it back
+pub fn split(key: &[u8], threshold: u8, shares: u8) -> Result<Vec<Share>, Box<dyn Error>> {
+    if threshold < 2 {
+        return Err("the threshold must be at least 2, a single share would be the key".into());
+    }
+    if shares < threshold {
+        return Err("there must be at least as many shares as the threshold".into());
+    }
+    let mut id = [0; 4];
+    let mut coefficients = vec![0; key.len() * (threshold as usize - 1)];
+    let random = SystemRandom::new();
+    random
+        .fill(&mut id)
+        .and_then(|()| random.fill(&mut coefficients))
+        .map_err(|_| "no random numbers available")?;
+    Ok((1..=shares)
+        .map(|x| {
+            let values = key
+                .iter()
+                .zip(coefficients.chunks(threshold as usize - 1))
+                .map(|(&secret, coefficients)| {
+                    // Horner's rule, highest degree first
+                    let value = coefficients
+                        .iter()
+                        .rev()
+                        .fold(0, |value, &coefficient| mul(value, x) ^ coefficient);
+                    mul(value, x) ^ secret
+                })
+                .collect();
+            Share {
+                x,
+                threshold,
+                id,
+                values,
+            }
+        })
+        .collect())
+}
+
+/// Put the key back together from `shares`, which must be at least the threshold of
+/// the same split, each once
+pub fn combine(shares: &[Share]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let Some(first) = shares.first() else {
+        return Err("no shares given".into());
+    };
+    if shares
+        .iter()
+        .any(|share| share.id != first.id || share.values.len() != first.values.len())
+    {
+        return Err("the shares are of different splits".into());
+    }
+    let mut xs: Vec<u8> = shares.iter().map(|share| share.x).collect();
+    xs.sort_unstable();
+    xs.dedup();
+    if xs.len() != shares.len() {
+        return Err("a share was given twice".into());
+    }
+    if shares.len() < first.threshold as usize {
+        return Err(format!(
+            "{} shares were given, {} are needed",
+            shares.len(),
+            first.threshold
+        )
+        .into());
+    }
+    // Lagrange interpolation at 0, where the key is
+    let shares = &shares[..first.threshold as usize];
+    Ok((0..first.values.len())
+        .map(|i| {
+            shares.iter().fold(0, |key, share| {
+                let basis = shares
+                    .iter()
+                    .filter(|other| other.x != share.x)
+                    .fold(1, |basis, other| {
+                        mul(basis, div(other.x, other.x ^ share.x))
+                    });
+                key ^ mul(share.values[i], basis)
+            })
+        })
+        .collect())
+}
+
+impl fmt::Display for Share {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}:{}:{}",
+            PREFIX,
+            self.x,
+            self.threshold,
+            hex(&self.id),
+            hex(&self.values)
+        )
+    }
+}
+
+impl FromStr for Share {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Share, String> {
+        let bad = || format!("not a share: {}", text.trim());
+        let fields: Vec<&str> = text.trim().split(':').collect();
+        let [PREFIX, x, threshold, id, values] = fields[..] else {
+            return Err(bad());
+        };
+        let id = unhex(id).ok_or_else(bad)?;
+        let share = Share {
+            x: x.parse().map_err(|_| bad())?,
+            threshold: threshold.parse().map_err(|_| bad())?,
+            id: id.try_into().map_err(|_| bad())?,
+            values: unhex(values).ok_or_else(bad)?,
+        };
+        if share.x == 0 || share.threshold < 2 || share.values.is_empty() {
+            return Err(bad());
+        }
+        Ok(share)
+    }
+}
+
+// multiplication in GF(256) with the AES polynomial x^8 + x^4 + x^3 + x + 1
+fn mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        a = (a << 1) ^ if a & 0x80 != 0 { 0x1b } else { 0 };
+        b >>= 1;
+    }
+    product
+}
+
+// division in GF(256), `b` is never 0 as the x of the shares differ
+fn div(a: u8, b: u8) -> u8 {
+    // b^254 is the inverse of b
+    let mut inverse = 1;
+    for _ in 0..254 {
+        inverse = mul(inverse, b);
+    }
+    mul(a, inverse)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|at| u8::from_str_radix(text.get(at..at + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [
+        0x00, 0x01, 0x02, 0x7f, 0x80, 0xfe, 0xff, 0x42, 0x13, 0x37, 0xde, 0xad, 0xbe, 0xef, 0xca,
+        0xfe, 0xba, 0xbe, 0x10, 0x20, 0x30, 0x40, 0x50, 0x60, 0x70, 0x80, 0x90, 0xa0, 0xb0, 0xc0,
+        0xd0, 0xe0,
+    ];
+
+    // the shares at the bits set in `mask`, in reverse order to show it does not matter
+    fn subset(shares: &[Share], mask: u32) -> Vec<Share> {
+        shares
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| mask & 1 << i != 0)
+            .map(|(_, share)| share.clone())
+            .rev()
+            .collect()
+    }
+
+    #[test]
+    fn any_threshold_of_the_shares_gives_the_key_back() {
+        for (threshold, count) in [(2, 2), (2, 3), (3, 5), (4, 6), (5, 5)] {
+            let shares = split(&KEY, threshold, count).unwrap();
+            assert_eq!(shares.len(), count as usize);
+            for mask in 1..1_u32 << count {
+                let given = subset(&shares, mask);
+                match combine(&given) {
+                    Ok(key) => {
+                        assert!(given.len() >= threshold as usize);
+                        assert_eq!(key, KEY, "{} of {}, {:b}", threshold, count, mask);
+                    }
+                    Err(_) => assert!(given.len() < threshold as usize),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn writes_shares_as_lines_and_reads_them_back() {
+        let shares = split(&KEY, 2, 3).unwrap();
+        for share in &shares {
+            let line = share.to_string();
+            assert!(line.starts_with("cli-totp-share:"));
+            assert_eq!(line.parse::<Share>().unwrap(), *share);
+        }
+        let read: Vec<Share> = shares[1..]
+            .iter()
+            .map(|share| share.to_string().parse().unwrap())
+            .collect();
+        assert_eq!(combine(&read).unwrap(), KEY);
+        for line in [
+            "",
+            "cli-totp-share:1:2:00112233",
+            "cli-totp-share:0:2:00112233:ab",
+            "cli-totp-share:1:1:00112233:ab",
+            "cli-totp-share:1:2:001122:ab",
+            "cli-totp-share:1:2:00112233:a",
+            "other-share:1:2:00112233:ab",
+        ] {
+            assert!(line.parse::<Share>().is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn rejects_shares_of_different_splits() {
+        let first = split(&KEY, 2, 3).unwrap();
+        let second = split(&KEY, 2, 3).unwrap();
+        assert!(combine(&[first[0].clone(), second[1].clone()]).is_err());
+        let shorter = split(&KEY[..16], 2, 3).unwrap();
+        assert!(combine(&[first[0].clone(), shorter[1].clone()]).is_err());
+    }
+
+    #[test]
+    fn rejects_a_share_given_twice() {
+        let shares = split(&KEY, 2, 3).unwrap();
+        assert!(combine(&[shares[0].clone(), shares[0].clone()]).is_err());
+        assert!(combine(&[shares[0].clone(), shares[1].clone(), shares[0].clone()]).is_err());
+    }
+
+    #[test]
+    fn rejects_too_few_shares() {
+        let shares = split(&KEY, 3, 5).unwrap();
+        assert!(combine(&[]).is_err());
+        assert!(combine(&shares[..2]).is_err());
+        assert!(combine(&shares[..3]).is_ok());
+    }
+
+    #[test]
+    fn refuses_a_threshold_that_cannot_be_met() {
+        assert!(split(&KEY, 1, 3).is_err());
+        assert!(split(&KEY, 4, 3).is_err());
+    }
+}
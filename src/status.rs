@@ -0,0 +1,89 @@
+//! One-line summaries of an account's current code, for status bars like the
+//! one of tmux, Waybar or Polybar.
+
+use cli_totp::Account;
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// What a status line is written for
+#[derive(Clone, Copy, PartialEq, Debug, clap::ValueEnum)]
+pub enum Format {
+    /// `ISSUER 123456 (12s)`, e.g. for tmux
+    Plain,
+    /// The JSON of a Waybar custom module with `"return-type": "json"`
+    Waybar,
+    /// Text with Polybar color tags, red when the code is about to expire
+    Polybar,
+}
+
+// seconds left under which a code is about to expire, and under which it is too late to type it
+const WARNING: u64 = 10;
+const CRITICAL: u64 = 5;
+
+/// `ISSUER 123456 (12s)` for `account` at `now`, the name stands in for a missing issuer
+pub fn line(account: &Account, now: u64) -> String {
+    let label = if account.issuer.is_empty() {
+        &account.name
+    } else {
+        &account.issuer
+    };
+    format!(
+        "{} {} ({}s)",
+        label,
+        account.code_at(now),
+        account.totp().seconds_remaining(now)
+    )
+}
+
+/// The status line of `account` at `now` as `format` wants it
+pub fn format(account: &Account, now: u64, format: Format) -> String {
+    let remaining = account.totp().seconds_remaining(now);
+    let class = match remaining {
+        0..=CRITICAL => "critical",
+        _ if remaining <= WARNING => "warning",
+        _ => "ok",
+    };
+    match format {
+        Format::Plain => line(account, now),
+        Format::Waybar => serde_json::json!({
+            "text": account.code_at(now),
+            "tooltip": line(account, now),
+            "class": class,
+            "percentage": remaining * 100 / account.period.max(1),
+        })
+        .to_string(),
+        Format::Polybar => match class {
+            "ok" => line(account, now),
+            "warning" => format!("%{{F#e5c07b}}{}%{{F-}}", line(account, now)),
+            _ => format!("%{{F#e06c75}}{}%{{F-}}", line(account, now)),
+        },
+    }
+}
+
+/// Print the status line `line` makes of `account` once, or every second with `follow`
+/// until stdout is closed
+pub fn run(
+    account: &Account,
+    time_offset: i64,
+    follow: bool,
+    line: &dyn Fn(&Account, u64) -> String,
+) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    loop {
+        writeln!(stdout, "{}", line(account, now(time_offset)))?;
+        stdout.flush()?;
+        if !follow {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+}
+
+fn now(time_offset: i64) -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+        .saturating_add_signed(time_offset)
+}
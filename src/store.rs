@@ -0,0 +1,291 @@
+//! Where the accounts shown are kept.
+
+use crate::pass;
+use crate::{backoff, backup, permissions, yubikey};
+use cli_totp::audit::Action;
+use cli_totp::vault::Other;
+use cli_totp::{KdfParams, Key, Responder, Vault};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use tracing::{debug, info};
+
+// set from --read-only or the config at startup
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Never write the accounts back, e.g. for a vault synced from the machine that owns it
+pub fn set_read_only(read_only: bool) {
+    READ_ONLY.store(read_only, Ordering::Relaxed);
+}
+
+/// Whether adding, editing, deleting and exporting accounts is disabled
+pub fn read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+// set from the config at startup
+static KDF: OnceLock<KdfParams> = OnceLock::new();
+
+/// Derive the keys of new vaults with `kdf`
+pub fn set_kdf(kdf: KdfParams) {
+    KDF.set(kdf).expect("the KDF is set once");
+}
+
+/// What the keys of new vaults are derived with
+pub fn kdf() -> KdfParams {
+    KDF.get().copied().unwrap_or_default()
+}
+
+// set from --allow-exposed at startup
+static ALLOW_EXPOSED: AtomicBool = AtomicBool::new(false);
+
+/// Open a vault file others can read all the same, warning about it
+pub fn set_allow_exposed(allow: bool) {
+    ALLOW_EXPOSED.store(allow, Ordering::Relaxed);
+}
+
+/// An error telling that `what` cannot be done in read-only mode, if it is on
+pub fn check_writable(what: &str) -> Result<(), Box<dyn Error>> {
+    if read_only() {
+        return Err(format!("cannot {}, the vault is read-only", what).into());
+    }
+    Ok(())
+}
+
+/// Save the vault at `path`, opened from a file of an older version, in the current one,
+/// keeping the file as it was in the backups first; tells the version it was of and where
+/// the backup is, none if it is current or the vault is read-only
+pub fn upgrade(
+    path: &Path,
+    vault: &mut Vault,
+    key: &Key,
+) -> Result<Option<(u32, PathBuf)>, Box<dyn Error>> {
+    let Some(from) = vault.migrated_from else {
+        return Ok(None);
+    };
+    if read_only() {
+        debug!(from, "read-only, not upgrading");
+        return Ok(None);
+    }
+    let backup = backup::take_version(path, from)?;
+    vault.save(path, key)?;
+    vault.migrated_from = None;
+    info!(path = %path.display(), from, to = cli_totp::vault::VERSION, "vault upgraded");
+    Ok(Some((from, backup)))
+}
+
+/// The accounts in the vault file at `vault`, or else in the password store `pass`;
+/// a vault file is only created if `create` says so, needing a YubiKey if `yubikey_slot` is set
+pub fn open(
+    vault: Option<PathBuf>,
+    pass: Option<&str>,
+    yubikey_slot: Option<u8>,
+    create: bool,
+) -> Result<(Vault, Store), Box<dyn Error>> {
+    match (vault, pass) {
+        (Some(path), _) => {
+            if !create && !path.exists() {
+                return Err(format!("there is no vault at {}", path.display()).into());
+            }
+            let exposed = permissions::exposed(&path);
+            if !exposed.is_empty() {
+                if !ALLOW_EXPOSED.load(Ordering::Relaxed) {
+                    return Err(format!(
+                        "others than you can read {}, run `doctor --fix` or give --allow-exposed",
+                        permissions::describe(&exposed)
+                    )
+                    .into());
+                }
+                eprintln!(
+                    "Warning: others than you can read {}, run `doctor --fix`",
+                    permissions::describe(&exposed)
+                );
+            }
+            let (mut vault, key) = unlock(&path, yubikey_slot)?;
+            // the accounts are there all the same, it is saved in the new version next time
+            match upgrade(&path, &mut vault, &key) {
+                Ok(Some((from, backup))) => eprintln!(
+                    "Upgraded {} from version {} to {}, the old file is kept as {}",
+                    path.display(),
+                    from,
+                    cli_totp::vault::VERSION,
+                    backup.display()
+                ),
+                Ok(None) => {}
+                Err(err) => eprintln!("Warning: cannot upgrade {}: {}", path.display(), err),
+            }
+            Ok((vault, Store::Vault { path, key }))
+        }
+        (None, Some(folder)) => {
+            let mut folder = pass::Folder::new(folder);
+            Ok((folder.load()?, Store::Pass(folder)))
+        }
+        (None, None) => Err("tell where the accounts are with --vault or --pass".into()),
+    }
+}
+
+/// Open the vault at `path`, asking for the passphrase unless $CLI_TOTP_PASSPHRASE has it,
+/// again after a wrong one once the backoff is over;
+/// a vault that does not exist yet is created with a passphrase typed twice
+pub fn unlock(path: &Path, yubikey_slot: Option<u8>) -> Result<(Vault, Key), Box<dyn Error>> {
+    let from_env = std::env::var("CLI_TOTP_PASSPHRASE").ok();
+    let respond = |slot: u8, challenge: &[u8]| {
+        eprintln!("Touch your YubiKey if it blinks");
+        yubikey::respond(slot, challenge)
+    };
+    if path.exists() {
+        let mut backoff = backoff::Backoff::load();
+        loop {
+            backoff.wait();
+            let passphrase = match &from_env {
+                Some(passphrase) => passphrase.clone(),
+                None => rpassword::prompt_password(format!("Passphrase for {}: ", path.display()))?,
+            };
+            match Vault::load_with_yubikey(path, &passphrase, &respond) {
+                Err(cli_totp::Error::Passphrase) if from_env.is_none() => {
+                    backoff.fail();
+                    eprintln!("Wrong passphrase, attempt {}", backoff.failures());
+                }
+                Err(err @ cli_totp::Error::Passphrase) => {
+                    backoff.fail();
+                    return Err(err.into());
+                }
+                result => {
+                    let opened = result?;
+                    backoff.succeed();
+                    return Ok(opened);
+                }
+            }
+        }
+    }
+
+    let passphrase = match from_env {
+        Some(passphrase) => passphrase,
+        None => {
+            let passphrase =
+                rpassword::prompt_password(format!("New passphrase for {}: ", path.display()))?;
+            if rpassword::prompt_password("Repeat it: ")? != passphrase {
+                return Err("the passphrases differ".into());
+            }
+            passphrase
+        }
+    };
+    if let Some(dir) = path.parent() {
+        permissions::create_dir(dir)?;
+    }
+    let kdf = kdf();
+    let key = match yubikey_slot {
+        Some(slot) => Key::with_yubikey(&passphrase, kdf, slot, &respond)?,
+        None => Key::new(&passphrase, kdf)?,
+    };
+    let vault = Vault {
+        other: Some(Other::Padding),
+        ..Vault::default()
+    };
+    vault.save(path, &key)?;
+    Ok((vault, key))
+}
+
+pub enum Store {
+    /// An encrypted vault file and the key it is saved with
+    Vault { path: PathBuf, key: Key },
+    /// Entries of the password store
+    Pass(pass::Folder),
+}
+
+impl Store {
+    /// A short name for where the accounts are kept, the vault file or pass folder
+    pub fn label(&self) -> String {
+        match self {
+            Store::Vault { path, .. } => path
+                .file_stem()
+                .unwrap_or(path.as_os_str())
+                .to_string_lossy()
+                .to_string(),
+            Store::Pass(folder) => format!("pass:{}", folder.name()),
+        }
+    }
+
+    /// Read the accounts again from where they are kept, e.g. to check what was saved
+    pub fn load(&mut self) -> Result<Vault, Box<dyn Error>> {
+        match self {
+            Store::Vault { path, key } => Ok(Vault::reopen(path, key)?),
+            Store::Pass(folder) => folder.load(),
+        }
+    }
+
+    /// Write `vault` back to where it came from, keeping a backup of a vault file;
+    /// nothing is written in read-only mode, e.g. when a code was used
+    pub fn save(&mut self, vault: &Vault) -> Result<(), Box<dyn Error>> {
+        if read_only() {
+            debug!("read-only, not saving");
+            return Ok(());
+        }
+        match self {
+            Store::Vault { path, key } => {
+                backup::take(path)?;
+                Ok(vault.save(path, key)?)
+            }
+            Store::Pass(folder) => folder.save(vault),
+        }
+    }
+
+    /// Encrypt the vault file with a key derived from `passphrase` with `kdf` from now
+    /// on, a vault that needs a YubiKey keeps needing the same slot; `kdf` only changes
+    /// in a file with a single vault, the other one could not be opened after
+    pub fn set_passphrase(
+        &mut self,
+        vault: &mut Vault,
+        passphrase: &str,
+        kdf: KdfParams,
+        respond: Responder,
+    ) -> Result<(), Box<dyn Error>> {
+        check_writable("change the passphrase")?;
+        let Store::Vault { path, key } = self else {
+            return Err("only vault files have a passphrase".into());
+        };
+        if key.opens_other(&*path, passphrase, respond)? {
+            return Err(cli_totp::Error::SamePassphrase.into());
+        }
+        if key.kdf() != kdf && vault.other != Some(Other::Padding) {
+            return Err(
+                "the KDF parameters cannot change in a file that may have a decoy, \
+                 it would be lost; change the passphrase with the ones it has"
+                    .into(),
+            );
+        }
+        let new_key = key.rekey(passphrase, kdf, respond)?;
+        vault.record(Action::Rekeyed, "the vault");
+        backup::take(path)?;
+        // saved through a file moved over the vault, so a crash leaves the old passphrase or
+        // the new one working, never a half written file
+        vault.save(&*path, &new_key)?;
+        info!(path = %path.display(), "passphrase changed");
+        *key = new_key;
+        Ok(())
+    }
+
+    /// Make `passphrase` open an empty decoy vault in the vault file, replacing any decoy
+    /// made before; its accounts are added by opening it like the vault
+    pub fn make_decoy(
+        &mut self,
+        vault: &mut Vault,
+        passphrase: &str,
+        respond: Responder,
+    ) -> Result<(), Box<dyn Error>> {
+        check_writable("make a decoy")?;
+        let Store::Vault { path, key } = self else {
+            return Err("only vault files can have a decoy".into());
+        };
+        let decoy_key = key.decoy(passphrase, respond)?;
+        // nothing in the log of the vault tells it has a decoy; saved first, so the file is
+        // in the format with room for the decoy, knowing its key to seal it anew from now on
+        vault.other = Some(Other::Decoy(decoy_key.to_bytes()));
+        backup::take(path)?;
+        vault.save(&*path, key)?;
+        Vault::default().save(&*path, &decoy_key)?;
+        info!(path = %path.display(), "decoy made");
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+//! Starting the agent with systemd: a user socket unit listens on the agent's
+//! socket and starts the service on the first request, handing it the listener,
+//! and the service goes away with the session, forgetting the accounts.
+//!
+//! `agent install` writes both units to `~/.config/systemd/user`. The service asks
+//! for the passphrase with systemd-ask-password, unless $CLI_TOTP_PASSPHRASE is
+//! set in its environment.
+
+use crate::native;
+use crate::paths;
+use std::env;
+use std::fs::{self, Permissions};
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::io::FromRawFd;
+use std::os::unix::net::UnixListener;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// The name of the units, `cli-totp-agent.socket` and `cli-totp-agent.service`
+pub const UNIT: &str = "cli-totp-agent";
+
+// the first file descriptor systemd passes, see sd_listen_fds(3)
+const LISTEN_FDS_START: i32 = 3;
+
+/// The listener systemd started this process with, if it was socket activated
+pub fn listener() -> io::Result<Option<UnixListener>> {
+    let ours = env::var("LISTEN_PID").is_ok_and(|pid| pid == std::process::id().to_string());
+    let fds: u32 = match env::var("LISTEN_FDS").map(|fds| fds.parse()) {
+        Ok(Ok(fds)) => fds,
+        _ => return Ok(None),
+    };
+    // not passed on to the processes started from here, e.g. the picker of the hotkey
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+    if !ours || fds == 0 {
+        return Ok(None);
+    }
+    debug!(fds, "socket activated");
+    // systemd passes the socket of the unit as the first descriptor, it is ours from here on
+    let listener = unsafe { UnixListener::from_raw_fd(LISTEN_FDS_START) };
+    // fails unless it is a Unix socket, e.g. one of TCP, or no descriptor at all
+    if let Err(err) = listener.local_addr() {
+        // whatever it is, it is not ours to close
+        std::mem::forget(listener);
+        return Err(io::Error::new(
+            err.kind(),
+            format!("the socket systemd passed is not a Unix socket: {}", err),
+        ));
+    }
+    Ok(Some(listener))
+}
+
+/// Write the socket and service units starting the agent for the vault at `vault` on
+/// requests to `socket`, returning where they were written
+pub fn install(vault: &Path, socket: &Path) -> io::Result<[PathBuf; 2]> {
+    let dir = directories::BaseDirs::new()
+        .map(|dirs| dirs.config_dir().join("systemd/user"))
+        .ok_or_else(|| io::Error::other("no home directory to write the units in"))?;
+    fs::create_dir_all(&dir)?;
+
+    // the service starts it through a script, for the passphrase
+    let script = paths::data_dir().join("agent-service");
+    let vault = fs::canonicalize(vault).unwrap_or_else(|_| vault.to_path_buf());
+    let command = format!(
+        "#!/bin/sh\n\
+         # started by systemd on the first request to the agent's socket\n\
+         if [ -z \"$CLI_TOTP_PASSPHRASE\" ]; then\n\
+         \x20   CLI_TOTP_PASSPHRASE=$(systemd-ask-password --user 'Passphrase of the cli-TOTP vault:') || exit 1\n\
+         \x20   export CLI_TOTP_PASSPHRASE\n\
+         fi\n\
+         exec {} --vault {} agent\n",
+        native::quote(&env::current_exe()?.to_string_lossy()),
+        native::quote(&vault.to_string_lossy()),
+    );
+    fs::create_dir_all(paths::data_dir())?;
+    fs::write(&script, command)?;
+    fs::set_permissions(&script, Permissions::from_mode(0o700))?;
+
+    let socket_unit = dir.join(format!("{}.socket", UNIT));
+    fs::write(
+        &socket_unit,
+        format!(
+            "[Unit]\n\
+             Description=cli-TOTP agent socket\n\
+             \n\
+             [Socket]\n\
+             ListenStream={}\n\
+             SocketMode=0600\n\
+             DirectoryMode=0700\n\
+             \n\
+             [Install]\n\
+             WantedBy=sockets.target\n",
+            socket.to_string_lossy().replace('%', "%%")
+        ),
+    )?;
+    let service_unit = dir.join(format!("{}.service", UNIT));
+    fs::write(
+        &service_unit,
+        format!(
+            "[Unit]\n\
+             Description=cli-TOTP agent\n\
+             Requires={unit}.socket\n\
+             \n\
+             [Service]\n\
+             ExecStart={}\n\
+             # the accounts are forgotten once it stops, e.g. on logout\n\
+             Restart=no\n",
+            unit_path(&script),
+            unit = UNIT,
+        ),
+    )?;
+    Ok([socket_unit, service_unit])
+}
+
+// `path` as written in a unit file, quoted if it has spaces and with the specifiers escaped
+fn unit_path(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('%', "%%");
+    if path.contains(char::is_whitespace) {
+        format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        path
+    }
+}
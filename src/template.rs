@@ -0,0 +1,185 @@
+//! Templates shaping what the commands print about an account for scripts, given
+//! with --format, e.g. `'{issuer}\t{code}\t{expires_in}'`.
+//!
+//! `{field}` is replaced by a field of the account, `{{` and `}}` stand for braces,
+//! and `\t`, `\n` and `\\` for a tab, a newline and a backslash, as shells do not
+//! turn them into those in single quotes.
+//!
+//! The fields are `issuer`, `name`, `label` (the issuer, or the name without one),
+//! `code`, `next` (the code after it), `expires_in` (seconds until the code changes),
+//! `period`, `digits` and `algorithm`.
+
+use cli_totp::Account;
+use std::str::FromStr;
+
+/// The fields a template can have
+const FIELDS: &[&str] = &[
+    "issuer",
+    "name",
+    "label",
+    "code",
+    "next",
+    "expires_in",
+    "period",
+    "digits",
+    "algorithm",
+];
+
+/// A template read from --format
+#[derive(Clone, Debug)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug)]
+enum Part {
+    Text(String),
+    // one of FIELDS
+    Field(&'static str),
+}
+
+impl Template {
+    /// The template filled in for `account` at `now`
+    pub fn render(&self, account: &Account, now: u64) -> String {
+        let mut text = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(part) => text += part,
+                Part::Field(field) => {
+                    text += &match *field {
+                        "issuer" => account.issuer.clone(),
+                        "name" => account.name.clone(),
+                        "label" if account.issuer.is_empty() => account.name.clone(),
+                        "label" => account.issuer.clone(),
+                        "code" => account.code_at(now),
+                        "next" => account.code_at(now + account.period),
+                        "expires_in" => account.totp().seconds_remaining(now).to_string(),
+                        "period" => account.period.to_string(),
+                        "digits" => account.digits.to_string(),
+                        _ => account.algorithm.name().to_string(),
+                    }
+                }
+            }
+        }
+        text
+    }
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(template: &str) -> Result<Template, String> {
+        let (mut parts, mut text) = (vec![], String::new());
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    let closed = loop {
+                        match chars.next() {
+                            Some('}') => break true,
+                            Some(c) => name.push(c),
+                            None => break false,
+                        }
+                    };
+                    if !closed {
+                        return Err(format!("{{{} is not closed with }}", name));
+                    }
+                    let Some(&field) = FIELDS.iter().find(|&&field| field == name) else {
+                        let known: Vec<String> = FIELDS
+                            .iter()
+                            .map(|field| format!("{{{}}}", field))
+                            .collect();
+                        return Err(format!(
+                            "there is no field {{{}}}, there are {}",
+                            name,
+                            known.join(", ")
+                        ));
+                    };
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Field(field));
+                }
+                '}' => return Err("a lone } is written }}".to_string()),
+                '\\' => match chars.next() {
+                    Some('t') => text.push('\t'),
+                    Some('n') => text.push('\n'),
+                    Some(c) => text.push(c),
+                    None => text.push('\\'),
+                },
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template { parts })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account() -> Account {
+        Account::new(
+            "Example".to_string(),
+            "me@example.com".to_string(),
+            "12345678901234567890".to_string(),
+        )
+    }
+
+    fn render(template: &str, account: &Account, now: u64) -> String {
+        template.parse::<Template>().unwrap().render(account, now)
+    }
+
+    #[test]
+    fn fills_in_the_fields() {
+        let account = account();
+        assert_eq!(
+            render(
+                "{issuer}\\t{name}\\t{code} {next} {expires_in}",
+                &account,
+                59
+            ),
+            "Example\tme@example.com\t287082 359152 1"
+        );
+        assert_eq!(
+            render("{period}/{digits}/{algorithm}", &account, 0),
+            "30/6/SHA1"
+        );
+        assert_eq!(render("{label}", &account, 0), "Example");
+        let unnamed = Account {
+            issuer: String::new(),
+            ..account
+        };
+        assert_eq!(render("{label}", &unnamed, 0), "me@example.com");
+    }
+
+    #[test]
+    fn unescapes_braces_and_backslashes() {
+        let account = account();
+        assert_eq!(
+            render("{{{name}}}\\n\\\\", &account, 0),
+            "{me@example.com}\n\\"
+        );
+        assert_eq!(render("no fields", &account, 0), "no fields");
+        assert_eq!(render("", &account, 0), "");
+    }
+
+    #[test]
+    fn rejects_unknown_and_unclosed_fields() {
+        for template in ["{secret}", "{code", "code}", "{}"] {
+            assert!(template.parse::<Template>().is_err(), "{}", template);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! The colors of the UI, and the attributes that stand in for them without colors.
+
+use ratatui::buffer::Buffer;
+use ratatui::style::{Color, Modifier};
+use serde::Deserialize;
+
+/// The colors the UI is drawn with, by what they mean
+#[derive(Clone, Copy, Debug)]
+pub struct Theme {
+    /// Text and borders
+    pub text: Color,
+    /// The shortcut letters of the menu and the timer
+    pub key: Color,
+    /// Titles, big digits and what went well
+    pub accent: Color,
+    /// Status messages
+    pub info: Color,
+    /// Things to look at, but not wrong
+    pub warning: Color,
+    /// What went wrong and codes about to expire
+    pub error: Color,
+    /// The selected row and the field being typed into
+    pub highlight: Color,
+    /// Text on the highlight
+    pub on_highlight: Color,
+    /// What matters less, like used recovery codes
+    pub dim: Color,
+}
+
+/// The themes that can be picked by name
+#[derive(Clone, Copy, PartialEq, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeName {
+    /// Light text on a dark terminal
+    #[default]
+    Dark,
+    /// Dark text on a light terminal
+    Light,
+    /// Bright text on a dark terminal for low vision, nothing dimmed into the background
+    #[serde(rename = "high-contrast")]
+    HighContrast,
+}
+
+impl ThemeName {
+    pub fn theme(self) -> Theme {
+        match self {
+            ThemeName::Dark => Theme {
+                text: Color::White,
+                key: Color::Green,
+                accent: Color::LightGreen,
+                info: Color::LightCyan,
+                warning: Color::Yellow,
+                error: Color::Red,
+                highlight: Color::Yellow,
+                on_highlight: Color::Black,
+                dim: Color::DarkGray,
+            },
+            ThemeName::Light => Theme {
+                text: Color::Black,
+                key: Color::Green,
+                accent: Color::Green,
+                info: Color::Blue,
+                warning: Color::Magenta,
+                error: Color::Red,
+                highlight: Color::Blue,
+                on_highlight: Color::White,
+                dim: Color::Gray,
+            },
+            ThemeName::HighContrast => Theme {
+                text: Color::White,
+                key: Color::LightYellow,
+                accent: Color::LightGreen,
+                info: Color::White,
+                warning: Color::LightYellow,
+                error: Color::LightRed,
+                highlight: Color::LightYellow,
+                on_highlight: Color::Black,
+                dim: Color::Gray,
+            },
+        }
+    }
+}
+
+/// Take the colors off everything drawn in `buffer`, for $NO_COLOR and --no-color, keeping
+/// what they meant as attributes: highlighted cells are reversed, errors bold, what is drawn in
+/// the highlight color underlined and what matters less dim
+pub fn strip_colors(buffer: &mut Buffer, theme: Theme) {
+    for cell in &mut buffer.content {
+        if cell.bg != Color::Reset {
+            cell.modifier |= Modifier::REVERSED;
+        } else if cell.fg == theme.error {
+            cell.modifier |= Modifier::BOLD;
+        } else if cell.fg == theme.highlight {
+            cell.modifier |= Modifier::UNDERLINED;
+        } else if cell.fg == theme.dim {
+            cell.modifier |= Modifier::DIM;
+        }
+        cell.fg = Color::Reset;
+        cell.bg = Color::Reset;
+    }
+}
+
+/// The color `text` names, e.g. `red`, `lightblue` or `#ff8800`
+pub fn parse_color(text: &str) -> Option<Color> {
+    let text = text.trim().to_lowercase();
+    if let Some(hex) = text.strip_prefix('#') {
+        let value = u32::from_str_radix(hex, 16)
+            .ok()
+            .filter(|_| hex.len() == 6)?;
+        return Some(Color::Rgb(
+            (value >> 16) as u8,
+            (value >> 8) as u8,
+            value as u8,
+        ));
+    }
+    Some(match text.as_str() {
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => return None,
+    })
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        ThemeName::default().theme()
+    }
+}
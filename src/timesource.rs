@@ -0,0 +1,217 @@
+//! External clocks that can replace the system clock for code generation,
+//! for air-gapped machines without NTP.
+//!
+//! A source is sampled from time to time and the difference to the system
+//! clock is applied to every reading in between, so a slow source does not
+//! stall the UI.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// How many lines of a GPS receiver's output to read looking for a fix
+const NMEA_MAX_LINES: usize = 100;
+
+/// An authoritative clock other than the system one
+pub enum TimeSource {
+    /// Shell command printing the current unix time in seconds (fractions allowed)
+    Command(String),
+    /// Serial device of a GPS receiver emitting NMEA 0183 sentences
+    Nmea(PathBuf),
+}
+
+impl TimeSource {
+    /// Read the current unix time in seconds from the source
+    pub fn read(&self) -> io::Result<f64> {
+        match self {
+            TimeSource::Command(command) => read_command(command),
+            TimeSource::Nmea(device) => read_nmea(device),
+        }
+    }
+
+    /// Short name of the source for the UI
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimeSource::Command(_) => "time command",
+            TimeSource::Nmea(_) => "GPS",
+        }
+    }
+}
+
+fn read_command(command: &str) -> io::Result<f64> {
+    let output = if cfg!(windows) {
+        Command::new("cmd").args(["/C", command]).output()?
+    } else {
+        Command::new("sh").args(["-c", command]).output()?
+    };
+    if !output.status.success() {
+        return Err(invalid(format!(
+            "time command exited with {}",
+            output.status
+        )));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .trim()
+        .parse()
+        .map_err(|_| invalid(format!("time command printed {:?}", stdout.trim())))
+}
+
+// the port is expected to be set up already (e.g. with stty), we only read lines from it
+fn read_nmea(device: &PathBuf) -> io::Result<f64> {
+    let reader = BufReader::new(File::open(device)?);
+    for line in reader.lines().take(NMEA_MAX_LINES) {
+        if let Some(time) = parse_rmc(line?.trim()) {
+            return Ok(time);
+        }
+    }
+    Err(invalid(
+        "no valid RMC sentence from the GPS receiver".to_string(),
+    ))
+}
+
+/// Unix time of a valid `$xxRMC` sentence, None for other or invalid sentences
+fn parse_rmc(sentence: &str) -> Option<f64> {
+    let (body, checksum) = sentence.strip_prefix('$')?.split_once('*')?;
+    let expected = u8::from_str_radix(checksum.get(..2)?, 16).ok()?;
+    if body.bytes().fold(0, |acc, b| acc ^ b) != expected {
+        return None;
+    }
+
+    let fields: Vec<&str> = body.split(',').collect();
+    // talker id (GP, GN, ...) followed by RMC, status A means the fix is valid
+    if fields.len() < 10 || !fields[0].ends_with("RMC") || fields[2] != "A" {
+        return None;
+    }
+    let (time, date) = (fields[1], fields[9]);
+    if time.len() < 6 || date.len() != 6 {
+        return None;
+    }
+    let hours: u64 = time.get(0..2)?.parse().ok()?;
+    let minutes: u64 = time.get(2..4)?.parse().ok()?;
+    let seconds: f64 = time.get(4..)?.parse().ok()?;
+    let day: u64 = date.get(0..2)?.parse().ok()?;
+    let month: u64 = date.get(2..4)?.parse().ok()?;
+    // two digit years, receivers old enough to report the 1900s are long gone
+    let year: i64 = match date.get(4..6)?.parse::<i64>().ok()? {
+        yy if yy < 80 => 2000 + yy,
+        yy => 1900 + yy,
+    };
+
+    let days = days_from_civil(year, month, day);
+    Some((days * 86_400) as f64 + (hours * 3_600 + minutes * 60) as f64 + seconds)
+}
+
+/// Unix time of `text`, either seconds since the epoch or an RFC 3339 date like
+/// 2024-05-01T12:00:00Z or 2024-05-01T14:00:00+02:00; fractions of a second are dropped
+pub fn parse_time(text: &str) -> Option<u64> {
+    if let Ok(seconds) = text.parse() {
+        return Some(seconds);
+    }
+    let (date, rest) = (text.get(..10)?, text.get(10..)?);
+    let mut parts = date.splitn(3, '-');
+    let year: i64 = digits(parts.next()?, 4)?;
+    let month: u64 = digits(parts.next()?, 2)?;
+    let day: u64 = digits(parts.next()?, 2)?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let rest = rest.strip_prefix(['T', 't', ' '])?;
+    let (time, rest) = (rest.get(..8)?, rest.get(8..)?);
+    let mut parts = time.splitn(3, ':');
+    let hours: i64 = digits(parts.next()?, 2)?;
+    let minutes: i64 = digits(parts.next()?, 2)?;
+    // up to 60 for a leap second
+    let seconds: i64 = digits(parts.next()?, 2)?;
+    if hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+
+    let rest = match rest.strip_prefix('.') {
+        Some(fraction) => fraction.trim_start_matches(|c: char| c.is_ascii_digit()),
+        None => rest,
+    };
+    let offset = match rest {
+        "Z" | "z" => 0,
+        _ => {
+            let sign = match rest.get(..1)? {
+                "+" => 1,
+                "-" => -1,
+                _ => return None,
+            };
+            let (offset_hours, offset_minutes) = rest.get(1..)?.split_once(':')?;
+            let offset_hours: i64 = digits(offset_hours, 2)?;
+            let offset_minutes: i64 = digits(offset_minutes, 2)?;
+            if offset_hours > 23 || offset_minutes > 59 {
+                return None;
+            }
+            sign * (offset_hours * 3_600 + offset_minutes * 60)
+        }
+    };
+
+    let days = days_from_civil(year, month, day);
+    u64::try_from(days * 86_400 + hours * 3_600 + minutes * 60 + seconds - offset).ok()
+}
+
+// `text` as a number of exactly `count` ascii digits
+fn digits<T: std::str::FromStr>(text: &str, count: usize) -> Option<T> {
+    if text.len() != count || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+// days since 1970-01-01 of a proleptic Gregorian date, see
+// http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: u64, day: u64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_unix_seconds_and_rfc3339_dates() {
+        for text in [
+            "1700000000",
+            "2023-11-14T22:13:20Z",
+            "2023-11-14t22:13:20.999z",
+            "2023-11-14 22:13:20Z",
+            "2023-11-15T00:13:20+02:00",
+            "2023-11-14T17:43:20-04:30",
+        ] {
+            assert_eq!(parse_time(text), Some(1_700_000_000), "{}", text);
+        }
+        assert_eq!(parse_time("2000-02-29T00:00:00Z"), Some(951_782_400));
+    }
+
+    #[test]
+    fn rejects_malformed_dates() {
+        for text in [
+            "",
+            "-5",
+            "2023-11-14",
+            "2023-11-14T22:13:20",
+            "2023-13-14T22:13:20Z",
+            "2023-11-14T24:00:00Z",
+            "2023-11-14T22:13:20+0200",
+            "23-11-14T22:13:20Z",
+            "1969-12-31T23:59:59Z",
+        ] {
+            assert_eq!(parse_time(text), None, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,1043 @@
+//! Where the accounts are kept between runs.
+//!
+//! A vault file holds two vaults, each encrypted with a key derived from its own
+//! passphrase:
+//!
+//! ```text
+//! "CLITOTP3" | KDF (12 bytes) | vault | vault
+//! ```
+//!
+//! or from its passphrase and the response of a YubiKey to a challenge:
+//!
+//! ```text
+//! "CLITOTY3" | KDF (12) | YubiKey slot (1) | vault | vault
+//! ```
+//!
+//! where each vault is
+//!
+//! ```text
+//! salt (16) | [challenge (32)] | nonce (12) | sealed length (20) | nonce (12) | sealed JSON | padding
+//! ```
+//!
+//! The second vault is a decoy, opened by a passphrase that can be given up without
+//! giving up the real accounts. A file without one has random bytes in its place and
+//! both are padded with random bytes to the same size, a multiple of 4 KiB, so a file
+//! does not tell whether it has a decoy, nor which of the two is the real vault. The
+//! real vault keeps the key of its decoy and seals it anew whenever it is saved, or
+//! draws new random bytes in its place, so neither do two copies of the file: both
+//! vaults change every time. The decoy knows nothing of the real vault and keeps it as
+//! it is.
+//!
+//! The passphrase goes through Argon2id with the memory in KiB, iterations and
+//! parallelism stored as the KDF, each a little-endian u32. The YubiKey's HMAC-SHA1
+//! response is mixed in with HMAC-SHA256. The length of the JSON, a little-endian u32,
+//! and the JSON are sealed with ChaCha20-Poly1305, the header and the salt and challenge
+//! of the vault authenticated along with them. Files from before the decoy start with
+//! "CLITOTP2" or "CLITOTY2" and hold a single vault:
+//!
+//! ```text
+//! "CLITOTP2" | KDF (12) | salt (16) | [slot (1) | challenge (32)] | nonce (12) | sealed JSON
+//! ```
+//!
+//! and those from before the KDF was stored start with "CLITOTP1" or "CLITOTPY" and have
+//! no KDF field, they were derived with [`KdfParams::LEGACY`].
+//!
+//! The last digit of the magic is the version of the file, [`VERSION`] for those saved
+//! now, and covers the JSON as well as the layout. A vault of an older version is
+//! brought up to the current one as it is opened, going through the migrations of each
+//! version since, and saved in it the next time; one of a newer version is not opened,
+//! so the fields it has that this one does not know are not lost.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+use crate::account::Account;
+use crate::audit;
+use crate::Error;
+
+// start of every vault file, and of those that need a YubiKey
+const MAGIC: &[u8; 8] = b"CLITOTP3";
+const MAGIC_YUBIKEY: &[u8; 8] = b"CLITOTY3";
+// the same before the decoy, with a single vault
+const MAGIC_V2: &[u8; 8] = b"CLITOTP2";
+const MAGIC_V2_YUBIKEY: &[u8; 8] = b"CLITOTY2";
+// and before the KDF parameters were stored
+const MAGIC_V1: &[u8; 8] = b"CLITOTP1";
+const MAGIC_V1_YUBIKEY: &[u8; 8] = b"CLITOTPY";
+const KDF_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+const CHALLENGE_LEN: usize = 32;
+// the sealed length of the JSON in a vault, a u32 and its tag
+const LENGTH_LEN: usize = 4 + 16;
+
+/// The version of the vault files saved now, the digit their magic ends with
+pub const VERSION: u32 = 3;
+
+// changes the JSON of a vault from before a version to that version
+type Migration = fn(&mut serde_json::Value);
+
+// the migration to each version, applied in turn to a vault that is older; the fields
+// added otherwise have defaults
+const MIGRATIONS: &[(u32, Migration)] = &[(2, mark_legacy), (3, alone)];
+// the vaults of a file are padded to a multiple of this
+const PADDING: usize = 4096;
+
+/// Answers the challenge sent to a YubiKey's challenge-response slot
+pub type Responder<'a> = &'a dyn Fn(u8, &[u8]) -> Result<Vec<u8>, Error>;
+
+// makes the key of a vault from its KDF, salt, YubiKey slot and challenge, and place in the file
+type MakeKey<'a> = dyn Fn(KdfParams, [u8; SALT_LEN], Option<(u8, [u8; CHALLENGE_LEN])>, usize) -> Result<Key, Error>
+    + 'a;
+
+/// The accounts, as stored in the vault file
+#[derive(Default, Serialize, Deserialize)]
+pub struct Vault {
+    pub accounts: Vec<Account>,
+    /// Sensitive actions taken on the accounts, oldest first
+    #[serde(default)]
+    pub log: Vec<audit::Entry>,
+    /// The version of the file it was opened from, if older than [`VERSION`]; it is
+    /// saved in the current one
+    #[serde(skip)]
+    pub migrated_from: Option<u32>,
+    /// What the other vault of the file is, None if this may be a decoy that knows
+    /// nothing of it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub other: Option<Other>,
+}
+
+/// The other vault of a file, to the vault that owns it
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Other {
+    /// There is none, its place holds random bytes
+    Padding,
+    /// The decoy made from the vault, opened by this key
+    Decoy([u8; 32]),
+}
+
+/// How hard Argon2id works to turn a passphrase into a key
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KdfParams {
+    /// Memory used, in KiB
+    pub memory: u32,
+    pub iterations: u32,
+    /// Lanes computed in parallel
+    pub parallelism: u32,
+}
+
+impl KdfParams {
+    /// What vaults from before the parameters were stored were derived with
+    pub const LEGACY: KdfParams = KdfParams {
+        memory: 19 * 1024,
+        iterations: 2,
+        parallelism: 1,
+    };
+
+    /// The least new vaults may be derived with
+    pub const MIN: KdfParams = KdfParams::LEGACY;
+
+    /// The most a vault may be derived with: its header is read before anything is
+    /// authenticated, so a file tampered with could otherwise take all the memory or hours
+    pub const MAX: KdfParams = KdfParams {
+        memory: 1024 * 1024,
+        iterations: 32,
+        parallelism: 16,
+    };
+
+    /// Whether every parameter is at least that of [`KdfParams::MIN`]
+    pub fn is_safe(&self) -> bool {
+        self.memory >= KdfParams::MIN.memory
+            && self.iterations >= KdfParams::MIN.iterations
+            && self.parallelism >= KdfParams::MIN.parallelism
+    }
+
+    /// Whether no parameter is above that of [`KdfParams::MAX`]
+    pub fn is_bounded(&self) -> bool {
+        self.memory <= KdfParams::MAX.memory
+            && self.iterations <= KdfParams::MAX.iterations
+            && self.parallelism <= KdfParams::MAX.parallelism
+    }
+
+    fn argon2(&self) -> Result<Argon2<'static>, Error> {
+        let params = Params::new(self.memory, self.iterations, self.parallelism, None)
+            .map_err(|err| Error::Crypto(format!("bad KDF parameters: {}", err)))?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+
+    fn to_bytes(self) -> [u8; KDF_LEN] {
+        let mut bytes = [0; KDF_LEN];
+        bytes[..4].copy_from_slice(&self.memory.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.iterations.to_le_bytes());
+        bytes[8..].copy_from_slice(&self.parallelism.to_le_bytes());
+        bytes
+    }
+
+    // the parameters in the header of a file, refused above the maximum before any key
+    // is derived with them
+    fn from_bytes(bytes: &[u8]) -> Result<KdfParams, Error> {
+        let word = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().expect("4 bytes"));
+        let kdf = KdfParams {
+            memory: word(0),
+            iterations: word(4),
+            parallelism: word(8),
+        };
+        if !kdf.is_bounded() {
+            return Err(Error::Crypto(format!(
+                "the vault asks for {} KiB of memory, {} iterations and parallelism {}, \
+                 more than the {} KiB, {} and {} allowed; it is damaged or was tampered with",
+                kdf.memory,
+                kdf.iterations,
+                kdf.parallelism,
+                KdfParams::MAX.memory,
+                KdfParams::MAX.iterations,
+                KdfParams::MAX.parallelism
+            )));
+        }
+        Ok(kdf)
+    }
+}
+
+/// 64 MiB, 3 iterations and 4 lanes, the second recommendation of RFC 9106
+impl Default for KdfParams {
+    fn default() -> KdfParams {
+        KdfParams {
+            memory: 64 * 1024,
+            iterations: 3,
+            parallelism: 4,
+        }
+    }
+}
+
+/// The key a vault is encrypted with, derived from its passphrase
+pub struct Key {
+    key: [u8; 32],
+    kdf: KdfParams,
+    salt: [u8; SALT_LEN],
+    /// The YubiKey slot and challenge whose response is part of the key
+    challenge: Option<(u8, [u8; CHALLENGE_LEN])>,
+    /// Which of the two vaults of the file it opens
+    place: usize,
+}
+
+impl Key {
+    /// Derive the key of a new vault from `passphrase` with `kdf`, with a fresh salt
+    pub fn new(passphrase: &str, kdf: KdfParams) -> Result<Key, Error> {
+        let mut salt = [0; SALT_LEN];
+        random(&mut salt)?;
+        Key::derive(passphrase, kdf, salt, None, random_place()?)
+    }
+
+    /// Derive the key of a new vault from `passphrase` with `kdf` and the response of
+    /// the YubiKey `slot` to a fresh challenge, so opening it needs both
+    pub fn with_yubikey(
+        passphrase: &str,
+        kdf: KdfParams,
+        slot: u8,
+        respond: Responder,
+    ) -> Result<Key, Error> {
+        let mut salt = [0; SALT_LEN];
+        random(&mut salt)?;
+        let mut challenge = [0; CHALLENGE_LEN];
+        random(&mut challenge)?;
+        Key::derive(
+            passphrase,
+            kdf,
+            salt,
+            Some((slot, challenge, respond)),
+            random_place()?,
+        )
+    }
+
+    /// Derive a new key for the same vault from `passphrase` with `kdf`, needing the
+    /// same YubiKey slot if this one does; the decoy of the file is lost if `kdf` is
+    /// not the one it was made with
+    pub fn rekey(
+        &self,
+        passphrase: &str,
+        kdf: KdfParams,
+        respond: Responder,
+    ) -> Result<Key, Error> {
+        let mut key = match self.yubikey_slot() {
+            Some(slot) => Key::with_yubikey(passphrase, kdf, slot, respond)?,
+            None => Key::new(passphrase, kdf)?,
+        };
+        key.place = self.place;
+        Ok(key)
+    }
+
+    /// Derive the key of the decoy next to this vault from `passphrase`, which must not
+    /// be the passphrase of this vault; saving a vault with it replaces any decoy the file had
+    pub fn decoy(&self, passphrase: &str, respond: Responder) -> Result<Key, Error> {
+        if self.matches(passphrase, respond)? {
+            return Err(Error::SamePassphrase);
+        }
+        let mut key = self.rekey(passphrase, self.kdf, respond)?;
+        key.place = 1 - self.place;
+        Ok(key)
+    }
+
+    fn derive(
+        passphrase: &str,
+        kdf: KdfParams,
+        salt: [u8; SALT_LEN],
+        yubikey: Option<(u8, [u8; CHALLENGE_LEN], Responder)>,
+        place: usize,
+    ) -> Result<Key, Error> {
+        let mut key = [0; 32];
+        kdf.argon2()?
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|err| Error::Crypto(err.to_string()))?;
+        let challenge = match yubikey {
+            Some((slot, challenge, respond)) => {
+                let response = respond(slot, &challenge)?;
+                let mixed = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, &key), &response);
+                key.copy_from_slice(mixed.as_ref());
+                Some((slot, challenge))
+            }
+            None => None,
+        };
+        Ok(Key {
+            key,
+            kdf,
+            salt,
+            challenge,
+            place,
+        })
+    }
+
+    /// Whether the key is the one derived from `passphrase`, rather than that of the
+    /// other vault of the file or none
+    pub fn matches(&self, passphrase: &str, respond: Responder) -> Result<bool, Error> {
+        let yubikey = self
+            .challenge
+            .map(|(slot, challenge)| (slot, challenge, respond));
+        let key = Key::derive(passphrase, self.kdf, self.salt, yubikey, self.place)?;
+        Ok(key.key == self.key)
+    }
+
+    /// Whether `passphrase` opens the other vault of the file at `path`, the decoy of this
+    /// one or the one this is the decoy of; the first a passphrase opens is the one opened,
+    /// so giving it to this vault would leave the other out of reach
+    pub fn opens_other(
+        &self,
+        path: impl AsRef<Path>,
+        passphrase: &str,
+        respond: Responder,
+    ) -> Result<bool, Error> {
+        match Vault::load_with_yubikey(path, passphrase, respond) {
+            Ok((_, key)) => Ok(key.place != self.place),
+            Err(Error::Passphrase) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// The key itself: whoever has it opens the vault with [`Vault::load_with_key`],
+    /// without the passphrase or the YubiKey, until the passphrase is changed
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.key
+    }
+
+    /// The Argon2id parameters the key was derived with
+    pub fn kdf(&self) -> KdfParams {
+        self.kdf
+    }
+
+    /// The YubiKey slot whose response is part of the key, if any
+    pub fn yubikey_slot(&self) -> Option<u8> {
+        self.challenge.map(|(slot, _)| slot)
+    }
+
+    // the key `key` of the other vault of the file, sealed in `vault`
+    fn other(&self, key: [u8; 32], vault: &[u8]) -> Option<Key> {
+        let mut salt = [0; SALT_LEN];
+        salt.copy_from_slice(vault.get(..SALT_LEN)?);
+        let challenge = match self.challenge {
+            Some((slot, _)) => {
+                let mut challenge = [0; CHALLENGE_LEN];
+                challenge.copy_from_slice(vault.get(SALT_LEN..SALT_LEN + CHALLENGE_LEN)?);
+                Some((slot, challenge))
+            }
+            None => None,
+        };
+        Some(Key {
+            key,
+            kdf: self.kdf,
+            salt,
+            challenge,
+            place: 1 - self.place,
+        })
+    }
+
+    // the header of a file saved with this key, shared by its two vaults
+    fn header(&self) -> Vec<u8> {
+        let mut header = vec![];
+        header.extend_from_slice(match self.challenge {
+            None => MAGIC,
+            Some(_) => MAGIC_YUBIKEY,
+        });
+        header.extend_from_slice(&self.kdf.to_bytes());
+        if let Some((slot, _)) = &self.challenge {
+            header.push(*slot);
+        }
+        header
+    }
+
+    // what the vault saved with this key starts with, its salt and challenge
+    fn prefix(&self) -> Vec<u8> {
+        let mut prefix = self.salt.to_vec();
+        if let Some((_, challenge)) = &self.challenge {
+            prefix.extend_from_slice(challenge);
+        }
+        prefix
+    }
+
+    // `vault` sealed after its prefix, without padding
+    fn seal(&self, header: &[u8], vault: &Vault) -> Result<Vec<u8>, Error> {
+        let mut data = self.prefix();
+        let aad = [header, &data].concat();
+        let json = serde_json::to_vec(vault)?;
+        let length = u32::try_from(json.len())
+            .map_err(|_| Error::Crypto("the vault is too large".to_string()))?;
+        for mut sealed in [length.to_le_bytes().to_vec(), json] {
+            let mut nonce = [0; NONCE_LEN];
+            random(&mut nonce)?;
+            self.aead()
+                .seal_in_place_append_tag(
+                    Nonce::assume_unique_for_key(nonce),
+                    Aad::from(&aad),
+                    &mut sealed,
+                )
+                .map_err(|_| Error::Crypto("cannot encrypt the vault".to_string()))?;
+            data.extend_from_slice(&nonce);
+            data.extend_from_slice(&sealed);
+        }
+        Ok(data)
+    }
+
+    // the vault sealed in `data`, one of the two of a file with `header`
+    fn unseal(&self, header: &[u8], data: &[u8]) -> Result<Vault, Error> {
+        let prefix_len = self.prefix().len();
+        if data.len() < prefix_len + 2 * NONCE_LEN + LENGTH_LEN {
+            return Err(Error::NotAVault);
+        }
+        let aad = [header, &data[..prefix_len]].concat();
+        let open = |at: usize, len: usize| -> Result<Vec<u8>, Error> {
+            let mut nonce = [0; NONCE_LEN];
+            nonce.copy_from_slice(&data[at..at + NONCE_LEN]);
+            let mut sealed = data
+                .get(at + NONCE_LEN..at + NONCE_LEN + len)
+                .ok_or(Error::Passphrase)?
+                .to_vec();
+            let opened = self
+                .aead()
+                .open_in_place(
+                    Nonce::assume_unique_for_key(nonce),
+                    Aad::from(&aad),
+                    &mut sealed,
+                )
+                .map_err(|_| Error::Passphrase)?;
+            Ok(opened.to_vec())
+        };
+        let length = open(prefix_len, LENGTH_LEN)?;
+        let length = u32::from_le_bytes(length[..4].try_into().expect("4 bytes")) as usize;
+        let json = open(prefix_len + NONCE_LEN + LENGTH_LEN, length + 16)?;
+        Vault::from_json(&json, VERSION)
+    }
+
+    // the vault sealed in `data` after its header of `header_len` bytes, in a file
+    // from before the decoy
+    fn open(
+        &self,
+        data: &mut [u8],
+        header_len: usize,
+        nonce: [u8; NONCE_LEN],
+        version: u32,
+    ) -> Result<Vault, Error> {
+        let (header, sealed) = data.split_at_mut(header_len);
+        let accounts = self
+            .aead()
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(&*header),
+                sealed,
+            )
+            .map_err(|_| Error::Passphrase)?;
+        Vault::from_json(accounts, version)
+    }
+
+    fn aead(&self) -> LessSafeKey {
+        LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &self.key).expect("key is 32 bytes"))
+    }
+}
+
+impl Vault {
+    /// Whether `data` is a vault file, of any version, rather than e.g. an export
+    pub fn is_vault(data: &[u8]) -> bool {
+        Vault::version_of(data).is_some()
+    }
+
+    /// The version of the vault file `data`, from its magic, including those newer than
+    /// [`VERSION`] that cannot be opened
+    pub fn version_of(data: &[u8]) -> Option<u32> {
+        match data.get(..MAGIC.len())? {
+            magic if magic == MAGIC_V1 || magic == MAGIC_V1_YUBIKEY => Some(1),
+            [b'C', b'L', b'I', b'T', b'O', b'T', b'P' | b'Y', digit @ b'2'..=b'9'] => {
+                Some(u32::from(digit - b'0'))
+            }
+            _ => None,
+        }
+    }
+
+    // the vault in `json`, from a file of `version`, brought up to the current version
+    fn from_json(json: &[u8], version: u32) -> Result<Vault, Error> {
+        let mut value: serde_json::Value = serde_json::from_slice(json)?;
+        for (to, migrate) in MIGRATIONS {
+            if version < *to {
+                debug!(to, "migrating vault");
+                migrate(&mut value);
+            }
+        }
+        let mut vault: Vault = serde_json::from_value(value)?;
+        vault.migrated_from = (version < VERSION).then_some(version);
+        Ok(vault)
+    }
+
+    /// Note in the log that `action` was taken on `subject`
+    pub fn record(&mut self, action: audit::Action, subject: impl Into<String>) {
+        self.log.push(audit::Entry::now(action, subject));
+        let excess = self.log.len().saturating_sub(audit::MAX_ENTRIES);
+        self.log.drain(..excess);
+    }
+
+    /// Open the vault stored at `path` with `passphrase`, the key is kept to save it again
+    pub fn load(path: impl AsRef<Path>, passphrase: &str) -> Result<(Vault, Key), Error> {
+        Vault::load_with_yubikey(path, passphrase, &|_, _| {
+            Err(Error::YubiKey("no YubiKey support here".to_string()))
+        })
+    }
+
+    /// Open the vault stored at `path` with `passphrase`, asking `respond` for the
+    /// YubiKey's response if the vault needs one
+    pub fn load_with_yubikey(
+        path: impl AsRef<Path>,
+        passphrase: &str,
+        respond: Responder,
+    ) -> Result<(Vault, Key), Error> {
+        Vault::open_with(path.as_ref(), &|kdf, salt, challenge, place| {
+            let yubikey = challenge.map(|(slot, challenge)| (slot, challenge, respond));
+            Key::derive(passphrase, kdf, salt, yubikey, place)
+        })
+    }
+
+    /// Open the vault stored at `path` with the key itself, as [`Key::to_bytes`] gave it,
+    /// e.g. to recover a vault whose passphrase is lost
+    pub fn load_with_key(path: impl AsRef<Path>, key: [u8; 32]) -> Result<(Vault, Key), Error> {
+        Vault::open_with(path.as_ref(), &|kdf, salt, challenge, place| {
+            Ok(Key {
+                key,
+                kdf,
+                salt,
+                challenge,
+                place,
+            })
+        })
+    }
+
+    // open the vault at `path` with the key `make` gives for the KDF, salt, YubiKey slot
+    // and challenge and place in the file of a vault, trying both vaults of a file
+    fn open_with(path: &Path, make: &MakeKey) -> Result<(Vault, Key), Error> {
+        let mut data = fs::read(path)?;
+        if let Some(version) = Vault::version_of(&data).filter(|&version| version > VERSION) {
+            return Err(Error::TooNew(version));
+        }
+        let (yubikey, kdf_len) = match data.get(..MAGIC.len()) {
+            Some(magic) if magic == MAGIC => return Vault::open_pair(path, &data, false, make),
+            Some(magic) if magic == MAGIC_YUBIKEY => {
+                return Vault::open_pair(path, &data, true, make)
+            }
+            Some(magic) if magic == MAGIC_V2 => (false, KDF_LEN),
+            Some(magic) if magic == MAGIC_V2_YUBIKEY => (true, KDF_LEN),
+            Some(magic) if magic == MAGIC_V1 => (false, 0),
+            Some(magic) if magic == MAGIC_V1_YUBIKEY => (true, 0),
+            _ => return Err(Error::NotAVault),
+        };
+        let header_len = MAGIC.len()
+            + kdf_len
+            + SALT_LEN
+            + if yubikey { 1 + CHALLENGE_LEN } else { 0 }
+            + NONCE_LEN;
+        if data.len() < header_len {
+            return Err(Error::NotAVault);
+        }
+        debug!(
+            path = %path.display(),
+            old_format = kdf_len == 0,
+            yubikey,
+            "opening vault"
+        );
+        let mut at = MAGIC.len();
+        let kdf = if kdf_len == 0 {
+            KdfParams::LEGACY
+        } else {
+            KdfParams::from_bytes(&data[at..at + KDF_LEN])?
+        };
+        at += kdf_len;
+        let mut salt = [0; SALT_LEN];
+        salt.copy_from_slice(&data[at..at + SALT_LEN]);
+        at += SALT_LEN;
+        let mut nonce = [0; NONCE_LEN];
+        nonce.copy_from_slice(&data[header_len - NONCE_LEN..header_len]);
+        let challenge = yubikey.then(|| {
+            let mut challenge = [0; CHALLENGE_LEN];
+            challenge.copy_from_slice(&data[at + 1..at + 1 + CHALLENGE_LEN]);
+            (data[at], challenge)
+        });
+        // saved in either place of the new format, it is the only vault of the file
+        let key = make(kdf, salt, challenge, random_place()?)?;
+
+        let version = if kdf_len == 0 { 1 } else { 2 };
+        let vault = key.open(&mut data, header_len, nonce, version)?;
+        info!(path = %path.display(), accounts = vault.accounts.len(), "vault opened");
+        Ok((vault, key))
+    }
+
+    // open whichever of the two vaults of `data` the key `make` gives is for
+    fn open_pair(
+        path: &Path,
+        data: &[u8],
+        yubikey: bool,
+        make: &MakeKey,
+    ) -> Result<(Vault, Key), Error> {
+        let header_len = MAGIC.len() + KDF_LEN + usize::from(yubikey);
+        let prefix_len = SALT_LEN + if yubikey { CHALLENGE_LEN } else { 0 };
+        let Some(places) = split(data, header_len) else {
+            return Err(Error::NotAVault);
+        };
+        debug!(path = %path.display(), yubikey, "opening vault");
+        let (header, kdf) = (
+            &data[..header_len],
+            KdfParams::from_bytes(&data[MAGIC.len()..])?,
+        );
+        for (place, vault) in places.into_iter().enumerate() {
+            if vault.len() < prefix_len {
+                return Err(Error::NotAVault);
+            }
+            let mut salt = [0; SALT_LEN];
+            salt.copy_from_slice(&vault[..SALT_LEN]);
+            let challenge = yubikey.then(|| {
+                let mut challenge = [0; CHALLENGE_LEN];
+                challenge.copy_from_slice(&vault[SALT_LEN..prefix_len]);
+                (header[header_len - 1], challenge)
+            });
+            let key = make(kdf, salt, challenge, place)?;
+            match key.unseal(header, vault) {
+                Ok(vault) => {
+                    info!(path = %path.display(), accounts = vault.accounts.len(), "vault opened");
+                    return Ok((vault, key));
+                }
+                Err(Error::Passphrase) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Err(Error::Passphrase)
+    }
+
+    /// Open the vault stored at `path` with the `key` it was saved with, e.g. to read
+    /// back what was just written; a vault saved with another key fails as a wrong passphrase
+    pub fn reopen(path: impl AsRef<Path>, key: &Key) -> Result<Vault, Error> {
+        let data = fs::read(path)?;
+        let header = key.header();
+        if !data.starts_with(&header) {
+            return Err(Error::Passphrase);
+        }
+        let places = split(&data, header.len()).ok_or(Error::NotAVault)?;
+        let vault = places[key.place];
+        if !vault.starts_with(&key.prefix()) {
+            return Err(Error::Passphrase);
+        }
+        key.unseal(&header, vault)
+    }
+
+    /// Store the vault at `path` encrypted with `key`, replacing what was there but the
+    /// other vault of the file, if it was made with the same KDF and YubiKey slot; the
+    /// decoy is sealed anew if this vault has its key, the random bytes of a file without
+    /// one drawn again. The file is replaced whole or not at all
+    pub fn save(&self, path: impl AsRef<Path>, key: &Key) -> Result<(), Error> {
+        let header = key.header();
+        let mut own = key.seal(&header, self)?;
+        let other = match fs::read(&path) {
+            Ok(data) if data.starts_with(&header) => {
+                split(&data, header.len()).map(|places| places[1 - key.place].to_vec())
+            }
+            _ => None,
+        };
+        let mut other = match (self.other, other) {
+            (Some(Other::Decoy(decoy)), Some(sealed)) => {
+                match key
+                    .other(decoy, &sealed)
+                    .map(|decoy| (decoy.unseal(&header, &sealed), decoy))
+                {
+                    Some((Ok(vault), decoy)) => decoy.seal(&header, &vault)?,
+                    // not the decoy this vault made, kept in case it is another's
+                    _ => sealed,
+                }
+            }
+            (Some(Other::Padding), _) | (_, None) => vec![],
+            (None, Some(sealed)) => sealed,
+        };
+        // the two padded to the same size, the other one with nothing to keep is only padding
+        let len = own.len().max(other.len()).div_ceil(PADDING) * PADDING;
+        for vault in [&mut own, &mut other] {
+            let mut padding = vec![0; len - vault.len()];
+            random(&mut padding)?;
+            vault.extend_from_slice(&padding);
+        }
+        let mut data = header;
+        let (first, second) = if key.place == 0 {
+            (own, other)
+        } else {
+            (other, own)
+        };
+        data.extend_from_slice(&first);
+        data.extend_from_slice(&second);
+        write_private(path.as_ref(), &data)?;
+        info!(path = %path.as_ref().display(), accounts = self.accounts.len(), "vault saved");
+        Ok(())
+    }
+}
+
+/// Write `data` to `path` readable by its owner only, through a file next to it that is
+/// synced and moved over it, so a crash or a full disk halfway leaves the old file whole
+pub fn write_private(path: &Path, data: &[u8]) -> io::Result<()> {
+    // a link to the vault stays one, the file it points to is the one replaced
+    let path = match (fs::canonicalize(path), fs::read_link(path)) {
+        (Ok(path), _) => path,
+        // to a file not made yet
+        (Err(_), Ok(target)) => path.parent().unwrap_or(Path::new("")).join(target),
+        (Err(_), Err(_)) => path.to_path_buf(),
+    };
+    let mut temp = path.clone().into_os_string();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    let written = (|| {
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&temp)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(&temp, &path)
+    })();
+    if let Err(err) = written {
+        let _ = fs::remove_file(&temp);
+        return Err(err);
+    }
+    // the rename is only there for good once the directory is synced too
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+// mark legacy the accounts saved before the codes were the RFC 6238 ones, those with no
+// algorithm; the files of version 2 came after, so all of theirs have one
+fn mark_legacy(vault: &mut serde_json::Value) {
+    let Some(accounts) = vault
+        .get_mut("accounts")
+        .and_then(serde_json::Value::as_array_mut)
+    else {
+        return;
+    };
+    for account in accounts
+        .iter_mut()
+        .filter_map(serde_json::Value::as_object_mut)
+    {
+        if !account.contains_key("algorithm") {
+            account.insert("legacy".to_string(), true.into());
+        }
+    }
+}
+
+// note that the files from before the decoy hold a single vault
+fn alone(vault: &mut serde_json::Value) {
+    if let Some(vault) = vault.as_object_mut() {
+        vault.insert("other".to_string(), "padding".into());
+    }
+}
+
+// the two vaults of a file in the current format, after its header of `header_len` bytes
+fn split(data: &[u8], header_len: usize) -> Option<[&[u8]; 2]> {
+    let rest = data.get(header_len..)?;
+    if rest.is_empty() || rest.len() % 2 != 0 {
+        return None;
+    }
+    let (first, second) = rest.split_at(rest.len() / 2);
+    Some([first, second])
+}
+
+// where in a file a new vault goes, at random
+fn random_place() -> Result<usize, Error> {
+    let mut byte = [0];
+    random(&mut byte)?;
+    Ok(usize::from(byte[0] & 1))
+}
+
+// fill `buf` from the system's secure random source
+fn random(buf: &mut [u8]) -> Result<(), Error> {
+    SystemRandom::new()
+        .fill(buf)
+        .map_err(|_| Error::Crypto("no random numbers available".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a file of the test's own in the temporary directory, removed as it is dropped
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> TempFile {
+            let path = std::env::temp_dir().join(format!(
+                "cli-totp-{}-{}.vault",
+                std::process::id(),
+                name
+            ));
+            let _ = fs::remove_file(&path);
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    // a file of a single vault as the versions before 3 wrote them, `header` then
+    // `json` sealed with `key`
+    fn sealed(mut header: Vec<u8>, key: &Key, json: &[u8]) -> Vec<u8> {
+        header.extend_from_slice(&key.salt);
+        header.extend_from_slice(&[9; NONCE_LEN]);
+        let mut sealed = json.to_vec();
+        key.aead()
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key([9; NONCE_LEN]),
+                Aad::from(&header),
+                &mut sealed,
+            )
+            .unwrap();
+        header.extend_from_slice(&sealed);
+        header
+    }
+
+    #[test]
+    fn opens_a_vault_of_the_first_version() {
+        let file = TempFile::new("version-1");
+        // an account of before the RFC 6238 codes, then one of after
+        let json = br#"{"accounts": [
+            {"issuer": "Old", "name": "old@example.com", "secret": "JBSWY3DPEHPK3PXP"},
+            {"issuer": "New", "name": "new@example.com", "secret": "JBSWY3DPEHPK3PXP",
+             "algorithm": "SHA1", "digits": 6, "period": 30}
+        ]}"#;
+        let key = Key::derive("passphrase", KdfParams::LEGACY, [7; SALT_LEN], None, 0).unwrap();
+        fs::write(&file.0, sealed(MAGIC_V1.to_vec(), &key, json)).unwrap();
+
+        let (vault, key) = Vault::load(&file.0, "passphrase").unwrap();
+        assert_eq!(vault.migrated_from, Some(1));
+        assert_eq!(key.kdf(), KdfParams::LEGACY);
+        // a file of a single vault, the other place is padding
+        assert_eq!(vault.other, Some(Other::Padding));
+        let legacy: Vec<bool> = vault
+            .accounts
+            .iter()
+            .map(|account| account.legacy)
+            .collect();
+        assert_eq!(legacy, [true, false]);
+
+        // saved in the current version, it is not migrated again
+        vault.save(&file.0, &key).unwrap();
+        assert_eq!(
+            Vault::version_of(&fs::read(&file.0).unwrap()),
+            Some(VERSION)
+        );
+        let (vault, _) = Vault::load(&file.0, "passphrase").unwrap();
+        assert_eq!(vault.migrated_from, None);
+        let legacy: Vec<bool> = vault
+            .accounts
+            .iter()
+            .map(|account| account.legacy)
+            .collect();
+        assert_eq!(legacy, [true, false]);
+    }
+
+    #[test]
+    fn opens_a_vault_of_the_second_version() {
+        let file = TempFile::new("version-2");
+        // written since the RFC 6238 codes, an account without an algorithm has the default one
+        let json = br#"{"accounts": [
+            {"issuer": "New", "name": "new@example.com", "secret": "JBSWY3DPEHPK3PXP"}
+        ]}"#;
+        let key = Key::derive("passphrase", KDF, [7; SALT_LEN], None, 0).unwrap();
+        let mut header = MAGIC_V2.to_vec();
+        header.extend_from_slice(&KDF.to_bytes());
+        fs::write(&file.0, sealed(header, &key, json)).unwrap();
+
+        let (vault, key) = Vault::load(&file.0, "passphrase").unwrap();
+        assert_eq!(vault.migrated_from, Some(2));
+        assert_eq!(key.kdf(), KDF);
+        assert_eq!(vault.other, Some(Other::Padding));
+        assert!(!vault.accounts[0].legacy);
+    }
+
+    // quick to derive, the tests are not about Argon2
+    const KDF: KdfParams = KdfParams {
+        memory: 8,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    fn no_yubikey(_: u8, _: &[u8]) -> Result<Vec<u8>, Error> {
+        Err(Error::YubiKey("none in tests".to_string()))
+    }
+
+    // the two vaults of the file at `path`
+    fn halves(path: &Path) -> (Vec<u8>, Vec<u8>) {
+        let data = fs::read(path).unwrap();
+        let [first, second] = split(&data, MAGIC.len() + KDF_LEN).unwrap();
+        (first.to_vec(), second.to_vec())
+    }
+
+    #[test]
+    fn saves_and_opens_again() {
+        let file = TempFile::new("round-trip");
+        let key = Key::new("passphrase", KDF).unwrap();
+        let mut vault = Vault {
+            other: Some(Other::Padding),
+            ..Vault::default()
+        };
+        vault.accounts.push(Account::new(
+            "Example".to_string(),
+            "me@example.com".to_string(),
+            "JBSWY3DPEHPK3PXP".to_string(),
+        ));
+        vault.save(&file.0, &key).unwrap();
+        assert_eq!(fs::read(&file.0).unwrap().len() % 2, 0);
+
+        let (opened, opened_key) = Vault::load(&file.0, "passphrase").unwrap();
+        assert_eq!(opened.accounts.len(), 1);
+        assert_eq!(opened.accounts[0].secret, "JBSWY3DPEHPK3PXP");
+        assert_eq!(opened.other, Some(Other::Padding));
+        assert_eq!(opened_key.to_bytes(), key.to_bytes());
+        assert_eq!(Vault::reopen(&file.0, &key).unwrap().accounts.len(), 1);
+        assert!(matches!(
+            Vault::load(&file.0, "wrong"),
+            Err(Error::Passphrase)
+        ));
+    }
+
+    #[test]
+    fn draws_the_padding_again() {
+        let file = TempFile::new("padding");
+        let key = Key::new("passphrase", KDF).unwrap();
+        let vault = Vault {
+            other: Some(Other::Padding),
+            ..Vault::default()
+        };
+        vault.save(&file.0, &key).unwrap();
+        let before = halves(&file.0);
+        vault.save(&file.0, &key).unwrap();
+        let after = halves(&file.0);
+        assert_ne!(before.0, after.0);
+        assert_ne!(before.1, after.1);
+    }
+
+    #[test]
+    fn keeps_the_decoy_apart() {
+        let file = TempFile::new("decoy");
+        let key = Key::new("real", KDF).unwrap();
+        let mut vault = Vault {
+            other: Some(Other::Padding),
+            ..Vault::default()
+        };
+        vault.save(&file.0, &key).unwrap();
+        assert!(matches!(
+            key.decoy("real", &no_yubikey),
+            Err(Error::SamePassphrase)
+        ));
+        let decoy_key = key.decoy("decoy", &no_yubikey).unwrap();
+        vault.other = Some(Other::Decoy(decoy_key.to_bytes()));
+        vault.save(&file.0, &key).unwrap();
+        let mut decoy = Vault::default();
+        decoy.accounts.push(Account::new(
+            "Decoy".to_string(),
+            "me@example.com".to_string(),
+            "JBSWY3DPEHPK3PXP".to_string(),
+        ));
+        decoy.save(&file.0, &decoy_key).unwrap();
+
+        let (real, real_key) = Vault::load(&file.0, "real").unwrap();
+        assert!(real.accounts.is_empty());
+        let (opened, opened_key) = Vault::load(&file.0, "decoy").unwrap();
+        assert_eq!(opened.accounts.len(), 1);
+        assert_eq!(opened.other, None);
+        assert_ne!(real_key.place, opened_key.place);
+        assert!(real_key.opens_other(&file.0, "decoy", &no_yubikey).unwrap());
+        assert!(!real_key.opens_other(&file.0, "real", &no_yubikey).unwrap());
+
+        // saving the real vault seals both anew, the decoy keeps its accounts
+        let before = halves(&file.0);
+        real.save(&file.0, &real_key).unwrap();
+        let after = halves(&file.0);
+        assert_ne!(before.0, after.0);
+        assert_ne!(before.1, after.1);
+        let (opened, _) = Vault::load(&file.0, "decoy").unwrap();
+        assert_eq!(opened.accounts.len(), 1);
+
+        // the decoy cannot, it keeps the real vault as it is
+        let before = halves(&file.0);
+        opened.save(&file.0, &opened_key).unwrap();
+        let after = halves(&file.0);
+        let [real_before, real_after] = match real_key.place {
+            0 => [before.0, after.0],
+            _ => [before.1, after.1],
+        };
+        assert_eq!(real_before, real_after);
+        assert!(Vault::load(&file.0, "real").is_ok());
+    }
+
+    #[test]
+    fn refuses_a_kdf_above_the_maximum() {
+        let file = TempFile::new("kdf");
+        let key = Key::new("passphrase", KDF).unwrap();
+        Vault::default().save(&file.0, &key).unwrap();
+        let mut data = fs::read(&file.0).unwrap();
+        data[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        fs::write(&file.0, &data).unwrap();
+        assert!(matches!(
+            Vault::load(&file.0, "passphrase"),
+            Err(Error::Crypto(_))
+        ));
+    }
+
+    #[test]
+    fn refuses_a_vault_of_a_newer_version() {
+        let file = TempFile::new("newer");
+        fs::write(&file.0, b"CLITOTP9 and whatever it holds").unwrap();
+        assert!(matches!(
+            Vault::load(&file.0, "passphrase"),
+            Err(Error::TooNew(9))
+        ));
+    }
+}
@@ -0,0 +1,52 @@
+//! Streams codes to stdout, one line each time a code rotates, for piping
+//! into other programs or a terminal without the UI.
+
+use crate::template::Template;
+use cli_totp::Account;
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Print the code of every account in `accounts` now and again whenever it
+/// changes, until stdout is closed; with more than one account each line
+/// starts with the account's name, unless `template` shapes the lines
+pub fn run(accounts: &[&Account], time_offset: i64, template: Option<&Template>) -> io::Result<()> {
+    let mut shown = vec![String::new(); accounts.len()];
+    let mut stdout = io::stdout();
+    loop {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_secs()
+            .saturating_add_signed(time_offset);
+        for (account, shown) in accounts.iter().zip(shown.iter_mut()) {
+            let code = account.code_at(now);
+            if code != *shown {
+                if let Some(template) = template {
+                    writeln!(stdout, "{}", template.render(account, now))?;
+                } else if accounts.len() > 1 {
+                    writeln!(stdout, "{} {}", account.name, code)?;
+                } else {
+                    writeln!(stdout, "{}", code)?;
+                }
+                *shown = code;
+            }
+        }
+        stdout.flush()?;
+        // wake up when the next code rotates
+        let wait = accounts
+            .iter()
+            .map(|account| account.totp().seconds_remaining(now))
+            .min()
+            .unwrap_or(1);
+        thread::sleep(Duration::from_secs(wait).saturating_sub(subsec()));
+    }
+}
+
+// how far into the current second the clock is
+fn subsec() -> Duration {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards");
+    Duration::from_nanos(now.subsec_nanos().into())
+}
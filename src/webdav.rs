@@ -0,0 +1,423 @@
+//! Keeping the vault file in step with a copy on a WebDAV server, e.g. Nextcloud,
+//! set in the `[sync]` table of the config:
+//!
+//! ```toml
+//! [sync]
+//! url = "https://cloud.example.com/remote.php/dav/files/me/cli-totp/"
+//! user = "me"
+//! password_command = "pass show cloud.example.com"
+//! on_exit = true
+//! ```
+//!
+//! Each vault goes in the folder under its file name, encrypted as it is on disk.
+//! Requests are made with curl, the password is given to it on stdin, and
+//! $CLI_TOTP_WEBDAV_PASSWORD is used when there is no command for it.
+//!
+//! The ETag the server gave the vault and a hash of the file as it was are kept
+//! next to it after each sync, in `<vault>.sync`. The side that changed since
+//! is copied to the other; when both did, nothing is and `sync --pull` or
+//! `sync --push` tells which one to keep. Uploads are made with If-Match, so one
+//! from another machine in between is not overwritten either.
+
+use crate::backup;
+use crate::hooks;
+use crate::permissions;
+use crate::store;
+use cli_totp::{vault, Vault};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tracing::{debug, info};
+
+// how long curl waits for the server, the UI waits for it meanwhile
+const CONNECT_TIMEOUT: &str = "10";
+const MAX_TIME: &str = "60";
+
+/// The WebDAV folder of the `[sync]` table
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Remote {
+    /// The folder the vaults are kept in
+    pub url: String,
+    /// Who to log in as, no login without it
+    pub user: Option<String>,
+    /// Shell command printing the password, $CLI_TOTP_WEBDAV_PASSWORD without it
+    pub password_command: Option<String>,
+    /// Sync the open vaults when the UI quits
+    #[serde(default)]
+    pub on_exit: bool,
+}
+
+/// Which side is kept when both changed
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Direction {
+    /// Whichever changed, neither if both did
+    Both,
+    /// The vault file, the copy on the server is overwritten
+    Push,
+    /// The copy on the server, the vault file is backed up and overwritten
+    Pull,
+}
+
+/// What syncing did
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Outcome {
+    UpToDate,
+    /// The vault file was uploaded
+    Pushed,
+    /// The vault file was replaced by the copy on the server
+    Pulled,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Outcome::UpToDate => "up to date",
+            Outcome::Pushed => "pushed",
+            Outcome::Pulled => "pulled",
+        })
+    }
+}
+
+// the status and ETag of a response
+struct Response {
+    status: u32,
+    etag: Option<String>,
+}
+
+// what the server and the vault file were at the last sync
+struct State {
+    etag: String,
+    hash: String,
+}
+
+impl Remote {
+    /// Where the vault at `path` is kept on the server
+    pub fn url_of(&self, path: &Path) -> Result<String, Box<dyn Error>> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| format!("{} is not a file", path.display()))?;
+        Ok(format!(
+            "{}/{}",
+            self.url.trim_end_matches('/'),
+            encode(&name.to_string_lossy())
+        ))
+    }
+
+    /// Bring the vault at `path` and its copy on the server in step, keeping the side
+    /// of `direction` if both changed since the last sync
+    pub fn sync(&self, path: &Path, direction: Direction) -> Result<Outcome, Box<dyn Error>> {
+        let url = self.url_of(path)?;
+        let local = match fs::read(path) {
+            Ok(data) => Some(data),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(format!("cannot read {}: {}", path.display(), err).into()),
+        };
+        // made before curl writes the copy on the server to it, so others never can read it
+        let download = with_suffix(path, ".download");
+        private_file(&download)
+            .map_err(|err| format!("cannot make {}: {}", download.display(), err))?;
+        let result = self.sync_with(path, &url, local, &download, direction);
+        let _ = fs::remove_file(&download);
+        if let Ok(outcome @ (Outcome::Pushed | Outcome::Pulled)) = result {
+            hooks::pushed_or_pulled(&outcome.to_string());
+        }
+        result
+    }
+
+    fn sync_with(
+        &self,
+        path: &Path,
+        url: &str,
+        local: Option<Vec<u8>>,
+        download: &Path,
+        direction: Direction,
+    ) -> Result<Outcome, Box<dyn Error>> {
+        let response = self.curl(url, &["--output".as_ref(), download.as_os_str()])?;
+        let remote = match response.status {
+            200 => {
+                let data = fs::read(download)?;
+                if !Vault::is_vault(&data) {
+                    return Err(format!("{} is not a vault", url).into());
+                }
+                let etag = response
+                    .etag
+                    .ok_or_else(|| format!("{} gave no ETag to tell later changes by", url))?;
+                Some((etag, data))
+            }
+            404 => None,
+            status => return Err(failed("download", url, status).into()),
+        };
+        let state = State::load(path);
+        debug!(
+            url,
+            remote = remote.is_some(),
+            synced = state.is_some(),
+            "syncing"
+        );
+        let (local, (etag, remote)) = match (local, remote) {
+            (None, None) => {
+                return Err(format!("there is no vault at {} or {}", path.display(), url).into())
+            }
+            (None, Some((etag, remote))) => return self.pull(path, &etag, &remote),
+            (Some(local), None) => return self.push(path, url, &local, None),
+            (Some(local), Some(remote)) => (local, remote),
+        };
+        if local == remote {
+            State::save(path, &etag, &local)?;
+            return Ok(Outcome::UpToDate);
+        }
+        let local_changed = state
+            .as_ref()
+            .is_none_or(|state| state.hash != hash(&local));
+        let remote_changed = state.as_ref().is_none_or(|state| state.etag != etag);
+        match direction {
+            Direction::Push => self.push(path, url, &local, Some(&etag)),
+            Direction::Pull => self.pull(path, &etag, &remote),
+            Direction::Both if !remote_changed => self.push(path, url, &local, Some(&etag)),
+            Direction::Both if !local_changed => self.pull(path, &etag, &remote),
+            Direction::Both => Err(format!(
+                "both {} and the copy on the server changed since they were last synced; \
+                 run sync --pull to keep the one on the server, or sync --push to keep this one",
+                path.display()
+            )
+            .into()),
+        }
+    }
+
+    // upload `local` read from `path`, unless the copy on the server is no longer the one
+    // with `etag`, or there is one when there was none; what is uploaded is `local` itself,
+    // not the file that may have changed since it was read
+    fn push(
+        &self,
+        path: &Path,
+        url: &str,
+        local: &[u8],
+        etag: Option<&str>,
+    ) -> Result<Outcome, Box<dyn Error>> {
+        store::check_writable("push the vault")?;
+        let condition = match etag {
+            Some(etag) => format!("If-Match: {}", etag),
+            None => "If-None-Match: *".to_string(),
+        };
+        // stdin already has the config of curl, so `local` goes through a file of its own
+        let upload = with_suffix(path, ".upload");
+        private_file(&upload)
+            .and_then(|mut file| file.write_all(local))
+            .map_err(|err| format!("cannot make {}: {}", upload.display(), err))?;
+        let args = [
+            "--upload-file".as_ref(),
+            upload.as_os_str(),
+            "--header".as_ref(),
+            condition.as_ref(),
+            "--output".as_ref(),
+            NULL.as_ref(),
+        ];
+        let response = self.curl(url, &args);
+        let _ = fs::remove_file(&upload);
+        let response = response?;
+        match response.status {
+            200..=299 => {}
+            412 => {
+                return Err(
+                    format!("{} changed on the server while syncing, sync again", url).into(),
+                )
+            }
+            409 => return Err(format!("there is no folder {} on the server", self.url).into()),
+            status => return Err(failed("upload", url, status).into()),
+        }
+        // not every server tells the ETag of an upload
+        let etag = match response.etag {
+            Some(etag) => etag,
+            None => self
+                .curl(url, &["--head".as_ref()])?
+                .etag
+                .ok_or_else(|| format!("{} gave no ETag to tell later changes by", url))?,
+        };
+        State::save(path, &etag, local)?;
+        info!(url, "pushed");
+        Ok(Outcome::Pushed)
+    }
+
+    // replace the vault at `path` with `remote`, keeping a backup of it
+    fn pull(&self, path: &Path, etag: &str, remote: &[u8]) -> Result<Outcome, Box<dyn Error>> {
+        store::check_writable("pull the vault")?;
+        // checked before it replaces the vault, which could not be opened after
+        match Vault::version_of(remote) {
+            None => {
+                return Err(format!(
+                    "the copy of {} on the server is not a vault",
+                    path.display()
+                )
+                .into())
+            }
+            Some(version) if version > vault::VERSION => {
+                return Err(cli_totp::Error::TooNew(version).into())
+            }
+            Some(_) => {}
+        }
+        backup::take(path)?;
+        vault::write_private(path, remote)?;
+        State::save(path, etag, remote)?;
+        info!(path = %path.display(), "pulled");
+        Ok(Outcome::Pulled)
+    }
+
+    // run curl on `url` with `args`, the login given on its stdin
+    fn curl(&self, url: &str, args: &[&OsStr]) -> Result<Response, Box<dyn Error>> {
+        let mut config = String::new();
+        if let Some(user) = &self.user {
+            let login = format!("{}:{}", user, self.password()?);
+            config.push_str(&format!("user = \"{}\"\n", quote(&login)));
+        }
+        let mut child = Command::new("curl")
+            .args([
+                "--silent",
+                "--show-error",
+                "--config",
+                "-",
+                "--connect-timeout",
+                CONNECT_TIMEOUT,
+                "--max-time",
+                MAX_TIME,
+                "--dump-header",
+                "-",
+            ])
+            .args(args)
+            .arg("--")
+            .arg(url)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| format!("cannot run curl: {}", err))?;
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(config.as_bytes())?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(format!(
+                "cannot reach {}: {}",
+                url,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+        let headers = String::from_utf8_lossy(&output.stdout);
+        let response =
+            parse_headers(&headers).ok_or_else(|| format!("no response from {}", url))?;
+        debug!(url, status = response.status, "webdav");
+        Ok(response)
+    }
+
+    // the password of the login
+    fn password(&self) -> Result<String, Box<dyn Error>> {
+        if let Some(command) = &self.password_command {
+            let output = Command::new("sh").args(["-c", command]).output()?;
+            if !output.status.success() {
+                return Err(format!("the password command failed: {}", output.status).into());
+            }
+            let password = String::from_utf8(output.stdout)?;
+            return Ok(password.lines().next().unwrap_or_default().to_string());
+        }
+        std::env::var("CLI_TOTP_WEBDAV_PASSWORD").map_err(|_| {
+            "set password_command in [sync] or $CLI_TOTP_WEBDAV_PASSWORD for the login".into()
+        })
+    }
+}
+
+impl State {
+    fn load(path: &Path) -> Option<State> {
+        let text = fs::read_to_string(with_suffix(path, ".sync")).ok()?;
+        let mut lines = text.lines();
+        Some(State {
+            etag: lines.next()?.to_string(),
+            hash: lines.next()?.to_string(),
+        })
+    }
+
+    fn save(path: &Path, etag: &str, data: &[u8]) -> io::Result<()> {
+        fs::write(
+            with_suffix(path, ".sync"),
+            format!("{}\n{}\n", etag, hash(data)),
+        )
+    }
+}
+
+// where curl writes what is not wanted
+#[cfg(windows)]
+const NULL: &str = "NUL";
+#[cfg(not(windows))]
+const NULL: &str = "/dev/null";
+
+// the status and ETag of the last response in `headers`, after any redirect or 100 Continue
+fn parse_headers(headers: &str) -> Option<Response> {
+    let mut response = None;
+    for line in headers.lines() {
+        if line.starts_with("HTTP/") {
+            let status = line.split_whitespace().nth(1)?.parse().ok()?;
+            response = Some(Response { status, etag: None });
+        } else if let (Some(response), Some((name, value))) = (&mut response, line.split_once(':'))
+        {
+            if name.eq_ignore_ascii_case("etag") {
+                response.etag = Some(value.trim().to_string());
+            }
+        }
+    }
+    response
+}
+
+fn failed(what: &str, url: &str, status: u32) -> String {
+    match status {
+        401 | 403 => format!("the server refused the login for {}", url),
+        status => format!("cannot {} {}: HTTP {}", what, url, status),
+    }
+}
+
+fn hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// a new empty file at `path` only its owner can read, in place of one left behind by a
+// sync that did not finish
+fn private_file(path: &Path) -> io::Result<fs::File> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+        _ => {}
+    }
+    permissions::create_new(path)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+// `value` in double quotes of a curl config file
+fn quote(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// `name` as a path segment of a URL
+fn encode(name: &str) -> String {
+    name.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{:02X}", byte),
+        })
+        .collect()
+}
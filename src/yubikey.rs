@@ -0,0 +1,36 @@
+//! Challenge-response with a YubiKey slot programmed for HMAC-SHA1, through
+//! `ykchalresp` from the YubiKey Personalization tools.
+
+use cli_totp::Error;
+use std::process::Command;
+
+/// Send `challenge` to `slot` of the first YubiKey plugged in and return its response
+pub fn respond(slot: u8, challenge: &[u8]) -> Result<Vec<u8>, Error> {
+    let hex: String = challenge.iter().map(|b| format!("{:02x}", b)).collect();
+    let output = Command::new("ykchalresp")
+        .arg(format!("-{}", slot))
+        .arg("-x")
+        .arg(hex)
+        .output()
+        .map_err(|err| Error::YubiKey(format!("cannot run ykchalresp: {}", err)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::YubiKey(match stderr.trim() {
+            "" => format!("ykchalresp failed ({})", output.status),
+            message => message.to_string(),
+        }));
+    }
+    let response = String::from_utf8_lossy(&output.stdout);
+    let response = response.trim();
+    if response.is_empty() || !response.len().is_multiple_of(2) {
+        return Err(Error::YubiKey(format!(
+            "unexpected response {:?}",
+            response
+        )));
+    }
+    (0..response.len())
+        .step_by(2)
+        .map(|at| u8::from_str_radix(&response[at..at + 2], 16))
+        .collect::<Result<_, _>>()
+        .map_err(|_| Error::YubiKey(format!("unexpected response {:?}", response)))
+}